@@ -0,0 +1,240 @@
+use std::error;
+use std::fmt;
+
+/// The bytes that begin every encoded accumulator.
+const MAGIC: &[u8; 4] = b"STAT";
+
+/// The version of the binary format written by this crate.
+///
+/// Decoders accept every version up to and including this one. Version `2`
+/// added the null count of `OnlineStats`.
+pub const VERSION: u8 = 2;
+
+/// Identifies the type of an encoded accumulator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    OnlineStats = 1,
+    Frequencies = 2,
+}
+
+/// An error that occurs when decoding an accumulator from bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input ended before the value was fully decoded.
+    UnexpectedEof,
+    /// The input does not begin with a header for the expected type.
+    InvalidHeader,
+    /// The input was written with a newer, unsupported format version.
+    UnsupportedVersion(u8),
+    /// The input contains a value that is not valid for the type.
+    InvalidData,
+    /// The input has bytes remaining after the value was decoded.
+    TrailingBytes,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeError::UnexpectedEof => {
+                write!(f, "unexpected end of input")
+            }
+            DecodeError::InvalidHeader => write!(f, "invalid header"),
+            DecodeError::UnsupportedVersion(v) => {
+                write!(f, "unsupported format version {}", v)
+            }
+            DecodeError::InvalidData => write!(f, "invalid data"),
+            DecodeError::TrailingBytes => {
+                write!(f, "trailing bytes after encoded value")
+            }
+        }
+    }
+}
+
+impl error::Error for DecodeError {}
+
+/// A type that can be used as a key in the binary format of `Frequencies`.
+///
+/// All multi-byte values are written in little endian byte order.
+pub trait BinaryKey: Sized {
+    /// Append the encoding of this key to `dst`.
+    fn encode_key(&self, dst: &mut Vec<u8>);
+
+    /// Decode a key from the front of `src`, advancing `src` past it.
+    fn decode_key(src: &mut &[u8]) -> Result<Self, DecodeError>;
+}
+
+macro_rules! impl_binary_key_int {
+    ($($ty:ty),*) => {$(
+        impl BinaryKey for $ty {
+            fn encode_key(&self, dst: &mut Vec<u8>) {
+                dst.extend_from_slice(&self.to_le_bytes());
+            }
+
+            fn decode_key(src: &mut &[u8]) -> Result<$ty, DecodeError> {
+                let mut buf = [0; std::mem::size_of::<$ty>()];
+                let len = buf.len();
+                buf.copy_from_slice(read_bytes(src, len)?);
+                Ok(<$ty>::from_le_bytes(buf))
+            }
+        }
+    )*}
+}
+
+impl_binary_key_int!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+impl BinaryKey for bool {
+    fn encode_key(&self, dst: &mut Vec<u8>) {
+        dst.push(*self as u8);
+    }
+
+    fn decode_key(src: &mut &[u8]) -> Result<bool, DecodeError> {
+        match u8::decode_key(src)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(DecodeError::InvalidData),
+        }
+    }
+}
+
+impl BinaryKey for char {
+    fn encode_key(&self, dst: &mut Vec<u8>) {
+        (*self as u32).encode_key(dst);
+    }
+
+    fn decode_key(src: &mut &[u8]) -> Result<char, DecodeError> {
+        std::char::from_u32(u32::decode_key(src)?)
+            .ok_or(DecodeError::InvalidData)
+    }
+}
+
+impl BinaryKey for Vec<u8> {
+    fn encode_key(&self, dst: &mut Vec<u8>) {
+        write_u64(dst, self.len() as u64);
+        dst.extend_from_slice(self);
+    }
+
+    fn decode_key(src: &mut &[u8]) -> Result<Vec<u8>, DecodeError> {
+        let len = read_len(src)?;
+        Ok(read_bytes(src, len)?.to_vec())
+    }
+}
+
+impl BinaryKey for String {
+    fn encode_key(&self, dst: &mut Vec<u8>) {
+        write_u64(dst, self.len() as u64);
+        dst.extend_from_slice(self.as_bytes());
+    }
+
+    fn decode_key(src: &mut &[u8]) -> Result<String, DecodeError> {
+        String::from_utf8(Vec::<u8>::decode_key(src)?)
+            .map_err(|_| DecodeError::InvalidData)
+    }
+}
+
+/// Start a new encoding of an accumulator of the given kind.
+pub fn write_header(kind: Kind) -> Vec<u8> {
+    let mut dst = MAGIC.to_vec();
+    dst.push(VERSION);
+    dst.push(kind as u8);
+    dst
+}
+
+/// Read the header of an accumulator of the given kind and return the
+/// version it was written with.
+pub fn read_header(src: &mut &[u8], kind: Kind) -> Result<u8, DecodeError> {
+    if read_bytes(src, MAGIC.len())? != MAGIC {
+        return Err(DecodeError::InvalidHeader);
+    }
+    let version = u8::decode_key(src)?;
+    if version == 0 || version > VERSION {
+        return Err(DecodeError::UnsupportedVersion(version));
+    }
+    if u8::decode_key(src)? != kind as u8 {
+        return Err(DecodeError::InvalidHeader);
+    }
+    Ok(version)
+}
+
+/// Return an error if any input remains.
+pub fn read_end(src: &[u8]) -> Result<(), DecodeError> {
+    if src.is_empty() { Ok(()) } else { Err(DecodeError::TrailingBytes) }
+}
+
+pub fn write_u64(dst: &mut Vec<u8>, n: u64) {
+    n.encode_key(dst);
+}
+
+pub fn read_u64(src: &mut &[u8]) -> Result<u64, DecodeError> {
+    u64::decode_key(src)
+}
+
+pub fn write_f64(dst: &mut Vec<u8>, x: f64) {
+    write_u64(dst, x.to_bits());
+}
+
+pub fn read_f64(src: &mut &[u8]) -> Result<f64, DecodeError> {
+    read_u64(src).map(f64::from_bits)
+}
+
+/// Read a length that must not exceed the remaining input, which prevents
+/// corrupt input from causing a huge allocation.
+pub fn read_len(src: &mut &[u8]) -> Result<usize, DecodeError> {
+    let len = read_u64(src)?;
+    if len > src.len() as u64 {
+        return Err(DecodeError::UnexpectedEof);
+    }
+    Ok(len as usize)
+}
+
+fn read_bytes<'a>(
+    src: &mut &'a [u8],
+    len: usize,
+) -> Result<&'a [u8], DecodeError> {
+    if src.len() < len {
+        return Err(DecodeError::UnexpectedEof);
+    }
+    let (bytes, rest) = src.split_at(len);
+    *src = rest;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BinaryKey, DecodeError};
+
+    fn roundtrip<T: BinaryKey + PartialEq + ::std::fmt::Debug>(key: T) {
+        let mut buf = vec![];
+        key.encode_key(&mut buf);
+        let mut src = &buf[..];
+        assert_eq!(T::decode_key(&mut src).unwrap(), key);
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn keys() {
+        roundtrip(0xDEADBEEFu32);
+        roundtrip(-5i64);
+        roundtrip(true);
+        roundtrip('☃');
+        roundtrip("hello".to_string());
+        roundtrip(vec![0u8, 255, 7]);
+    }
+
+    #[test]
+    fn little_endian() {
+        let mut buf = vec![];
+        0x0102u16.encode_key(&mut buf);
+        assert_eq!(buf, vec![0x02, 0x01]);
+    }
+
+    #[test]
+    fn invalid_keys() {
+        let mut src = &[1u8, 2][..];
+        assert_eq!(u32::decode_key(&mut src), Err(DecodeError::UnexpectedEof));
+        let mut src = &[2u8][..];
+        assert_eq!(bool::decode_key(&mut src), Err(DecodeError::InvalidData));
+        let mut src = &[100u8, 0, 0, 0, 0, 0, 0, 0, b'a'][..];
+        assert_eq!(String::decode_key(&mut src),
+                   Err(DecodeError::UnexpectedEof));
+    }
+}
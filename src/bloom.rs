@@ -0,0 +1,219 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+use {Commute, MergeError};
+use stable_hash::StableHasher;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A commutative filter for approximate set membership.
+///
+/// Each element sets `k` bits in a fixed size bit vector, chosen by `k`
+/// hash functions. An element that was added is always reported as present,
+/// but an element that wasn't added may also be reported as present if all
+/// of its bits were set by other elements. The probability of such a false
+/// positive grows as more elements are added.
+///
+/// Elements are hashed with an algorithm that is fixed by this crate, not
+/// with the standard library's `DefaultHasher`, which may change between
+/// Rust releases. So filters with the same parameters can be merged even if
+/// they were built in different places, by different builds or on different
+/// platforms, as long as the elements' `Hash` implementations agree.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BloomFilter<T> {
+    nbits: usize,
+    nhashes: u32,
+    bits: Vec<u64>,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Hash> BloomFilter<T> {
+    /// Create an empty filter with `nbits` bits and `nhashes` hash
+    /// functions.
+    ///
+    /// This panics if `nbits` or `nhashes` is `0`.
+    pub fn new(nbits: usize, nhashes: u32) -> BloomFilter<T> {
+        assert!(nbits > 0, "a bloom filter must have at least one bit");
+        assert!(nhashes > 0, "a bloom filter needs at least one hash");
+        BloomFilter {
+            nbits,
+            nhashes,
+            bits: vec![0; nbits.div_ceil(64)],
+            _marker: PhantomData,
+        }
+    }
+
+    /// Add an element.
+    pub fn add(&mut self, v: &T) {
+        for i in self.indices(v) {
+            self.bits[i / 64] |= 1 << (i % 64);
+        }
+    }
+
+    /// Returns true if `v` may have been added.
+    ///
+    /// If this returns `false`, then `v` was definitely never added.
+    pub fn maybe_contains(&self, v: &T) -> bool {
+        self.indices(v).all(|i| self.bits[i / 64] & (1 << (i % 64)) != 0)
+    }
+
+    /// Returns the estimated probability that `maybe_contains` reports an
+    /// element that was never added as present.
+    ///
+    /// This is `(s / m)^k`, where `s` is the number of set bits, `m` is the
+    /// number of bits and `k` is the number of hash functions.
+    pub fn false_positive_rate(&self) -> f64 {
+        let set: u32 = self.bits.iter().map(|w| w.count_ones()).sum();
+        (set as f64 / self.nbits as f64).powi(self.nhashes as i32)
+    }
+
+    /// Returns the number of bits.
+    pub fn num_bits(&self) -> usize {
+        self.nbits
+    }
+
+    /// Returns the number of hash functions.
+    pub fn num_hashes(&self) -> u32 {
+        self.nhashes
+    }
+
+    /// Returns the bit indices for `v`.
+    ///
+    /// The `k` hash functions are simulated by combining two hashes as
+    /// `h1 + i * h2`. See: Kirsch and Mitzenmacher, "Less Hashing, Same
+    /// Performance: Building a Better Bloom Filter", 2006.
+    fn indices(&self, v: &T) -> impl Iterator<Item=usize> {
+        let mut hasher = StableHasher::new();
+        v.hash(&mut hasher);
+        let h1 = hasher.finish();
+        hasher.write_u8(0xFF);
+        // An odd step can't get stuck on a subset of the bits when `nbits`
+        // is a power of two.
+        let h2 = hasher.finish() | 1;
+        let nbits = self.nbits as u64;
+        (0..self.nhashes as u64).map(move |i| {
+            (h1.wrapping_add(i.wrapping_mul(h2)) % nbits) as usize
+        })
+    }
+}
+
+impl<T> Commute for BloomFilter<T> {
+    fn merge(&mut self, v: BloomFilter<T>) {
+        assert!(self.nbits == v.nbits && self.nhashes == v.nhashes,
+                "cannot merge bloom filters with different parameters");
+        for (w1, w2) in self.bits.iter_mut().zip(v.bits) {
+            *w1 |= w2;
+        }
+    }
+
+    fn check_merge(&self, v: &BloomFilter<T>) -> Result<(), MergeError> {
+        if self.nbits != v.nbits || self.nhashes != v.nhashes {
+            return Err(MergeError::parameter_mismatch("bloom filters"));
+        }
+        Ok(())
+    }
+}
+
+impl<T> Clone for BloomFilter<T> {
+    fn clone(&self) -> BloomFilter<T> {
+        BloomFilter {
+            nbits: self.nbits,
+            nhashes: self.nhashes,
+            bits: self.bits.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for BloomFilter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let set: u32 = self.bits.iter().map(|w| w.count_ones()).sum();
+        write!(f, "BloomFilter({}/{} bits set, {} hashes)",
+               set, self.nbits, self.nhashes)
+    }
+}
+
+impl<'a, T: 'a + Hash> Extend<&'a T> for BloomFilter<T> {
+    fn extend<I: IntoIterator<Item=&'a T>>(&mut self, it: I) {
+        for v in it {
+            self.add(v);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {Commute, MergeErrorKind};
+    use super::BloomFilter;
+
+    #[test]
+    fn no_false_negatives() {
+        let mut filter = BloomFilter::new(10_000, 7);
+        for i in 0..1000u64 {
+            filter.add(&(i * 7919));
+        }
+        for i in 0..1000u64 {
+            assert!(filter.maybe_contains(&(i * 7919)));
+        }
+        assert!(!BloomFilter::new(64, 3).maybe_contains(&"a"));
+    }
+
+    #[test]
+    fn false_positive_rate() {
+        // With 10 bits per element and 7 hashes, the expected false
+        // positive rate is about 0.8%.
+        let mut filter = BloomFilter::new(10_000, 7);
+        let added: Vec<String> =
+            (0..1000).map(|i| format!("in{}", i)).collect();
+        filter.extend(&added);
+        let fp = (0..10_000)
+            .filter(|i| filter.maybe_contains(&format!("out{}", i)))
+            .count();
+        let rate = fp as f64 / 10_000.0;
+        assert!(rate < 0.02, "false positive rate: {}", rate);
+        let estimate = filter.false_positive_rate();
+        assert!((estimate - 0.008).abs() < 0.005, "estimate: {}", estimate);
+    }
+
+    #[test]
+    fn stable_indices() {
+        // The bits an element maps to are part of the serialized format,
+        // so they must not change between builds.
+        let filter = BloomFilter::new(1000, 4);
+        let indices: Vec<usize> = filter.indices(&42u32).collect();
+        assert_eq!(indices, vec![826, 515, 588, 277]);
+    }
+
+    #[test]
+    fn merge() {
+        let mut f1 = BloomFilter::new(1000, 4);
+        let mut f2 = BloomFilter::new(1000, 4);
+        for i in 0..50u32 {
+            f1.add(&i);
+            f2.add(&(i + 100));
+        }
+        f1.merge(f2);
+        assert!((0..50u32).all(|i| f1.maybe_contains(&i)));
+        assert!((100..150u32).all(|i| f1.maybe_contains(&i)));
+    }
+
+    #[test]
+    fn try_merge_different_parameters() {
+        let mut f1 = BloomFilter::<u32>::new(1000, 4);
+        f1.add(&1);
+        let err = f1.try_merge(BloomFilter::new(1000, 5)).unwrap_err();
+        assert_eq!(err.kind(),
+                   MergeErrorKind::ParameterMismatch("bloom filters"));
+        assert!(f1.maybe_contains(&1));
+        assert_eq!(f1.try_merge(BloomFilter::new(1000, 4)), Ok(()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn merge_different_parameters() {
+        let mut f1 = BloomFilter::<u32>::new(1000, 4);
+        f1.merge(BloomFilter::new(1000, 5));
+    }
+}
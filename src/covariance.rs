@@ -0,0 +1,258 @@
+use std::default::Default;
+use std::fmt;
+use std::iter::{FromIterator, IntoIterator};
+
+use num_traits::ToPrimitive;
+
+use Commute;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Online state for computing the covariance and correlation of paired
+/// samples.
+///
+/// The co-moment of the pairs and the second moments of each variable are
+/// updated incrementally, so this uses constant space.
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Covariance {
+    size: u64,
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    mean_x: f64,
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    mean_y: f64,
+    /// The sum of squared differences from the mean of `x`.
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    m2_x: f64,
+    /// The sum of squared differences from the mean of `y`.
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    m2_y: f64,
+    /// The sum of products of differences from the means.
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    comoment: f64,
+}
+
+impl Covariance {
+    /// Create initial state with no pairs.
+    pub fn new() -> Covariance {
+        Default::default()
+    }
+
+    /// Add a new pair of samples.
+    pub fn add<T: ToPrimitive>(&mut self, x: T, y: T) {
+        let (x, y) = (x.to_f64().unwrap(), y.to_f64().unwrap());
+        self.size += 1;
+        let n = self.size as f64;
+        let dx = x - self.mean_x;
+        let dy = y - self.mean_y;
+        self.mean_x += dx / n;
+        self.mean_y += dy / n;
+        self.m2_x += dx * (x - self.mean_x);
+        self.m2_y += dy * (y - self.mean_y);
+        self.comoment += dx * (y - self.mean_y);
+    }
+
+    /// Return the mean of the `x` samples.
+    pub fn mean_x(&self) -> f64 {
+        self.mean_x
+    }
+
+    /// Return the mean of the `y` samples.
+    pub fn mean_y(&self) -> f64 {
+        self.mean_y
+    }
+
+    /// Return the population variance of the `x` samples.
+    pub fn variance_x(&self) -> f64 {
+        self.m2_x / (self.size as f64)
+    }
+
+    /// Return the population variance of the `y` samples.
+    pub fn variance_y(&self) -> f64 {
+        self.m2_y / (self.size as f64)
+    }
+
+    /// Return the population covariance.
+    ///
+    /// If there is no data, then `NaN` is returned.
+    pub fn covariance(&self) -> f64 {
+        self.comoment / (self.size as f64)
+    }
+
+    /// Return the sample covariance, which divides by `N - 1`.
+    ///
+    /// If there are fewer than two pairs, then `NaN` is returned.
+    pub fn sample_covariance(&self) -> f64 {
+        if self.size <= 1 {
+            return f64::NAN;
+        }
+        self.comoment / ((self.size - 1) as f64)
+    }
+
+    /// Return Pearson's correlation coefficient.
+    ///
+    /// The result is clamped to `[-1, 1]` to guard against rounding error.
+    /// If either variable has a variance of `0` (including when there is no
+    /// data), then `NaN` is returned.
+    pub fn correlation(&self) -> f64 {
+        if self.m2_x == 0.0 || self.m2_y == 0.0 {
+            return f64::NAN;
+        }
+        let r = self.comoment / (self.m2_x * self.m2_y).sqrt();
+        r.clamp(-1.0, 1.0)
+    }
+
+    /// Returns the number of pairs.
+    pub fn len(&self) -> usize {
+        self.size as usize
+    }
+
+    /// Returns true if there are no pairs.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+}
+
+impl Commute for Covariance {
+    fn merge(&mut self, v: Covariance) {
+        if v.size == 0 {
+            return;
+        } else if self.size == 0 {
+            *self = v;
+            return;
+        }
+        let (s1, s2) = (self.size as f64, v.size as f64);
+        let n = s1 + s2;
+        let dx = v.mean_x - self.mean_x;
+        let dy = v.mean_y - self.mean_y;
+        self.m2_x += v.m2_x + dx * dx * s1 * s2 / n;
+        self.m2_y += v.m2_y + dy * dy * s1 * s2 / n;
+        self.comoment += v.comoment + dx * dy * s1 * s2 / n;
+        self.mean_x += dx * s2 / n;
+        self.mean_y += dy * s2 / n;
+        self.size += v.size;
+    }
+}
+
+impl Default for Covariance {
+    fn default() -> Covariance {
+        Covariance {
+            size: 0,
+            mean_x: 0.0,
+            mean_y: 0.0,
+            m2_x: 0.0,
+            m2_y: 0.0,
+            comoment: 0.0,
+        }
+    }
+}
+
+impl fmt::Debug for Covariance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.10} (population covariance)", self.covariance())
+    }
+}
+
+impl<T: ToPrimitive> FromIterator<(T, T)> for Covariance {
+    fn from_iter<I: IntoIterator<Item=(T, T)>>(it: I) -> Covariance {
+        let mut v = Covariance::new();
+        v.extend(it);
+        v
+    }
+}
+
+impl<T: ToPrimitive> Extend<(T, T)> for Covariance {
+    fn extend<I: IntoIterator<Item=(T, T)>>(&mut self, it: I) {
+        for (x, y) in it {
+            self.add(x, y);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::iter::FromIterator;
+
+    use {Commute, merge_all};
+    use super::Covariance;
+
+    fn pairs() -> Vec<(f64, f64)> {
+        (0..50).map(|i| {
+            let x = ((i * 37) % 17) as f64 / 3.0;
+            let y = 2.0 * x + ((i * 53) % 11) as f64 - 4.0;
+            (x, y)
+        }).collect()
+    }
+
+    /// Computes the co-moment with two passes over `pairs`.
+    fn naive_comoment(pairs: &[(f64, f64)]) -> f64 {
+        let n = pairs.len() as f64;
+        let mx = pairs.iter().map(|p| p.0).sum::<f64>() / n;
+        let my = pairs.iter().map(|p| p.1).sum::<f64>() / n;
+        pairs.iter().map(|&(x, y)| (x - mx) * (y - my)).sum()
+    }
+
+    #[test]
+    fn covariance() {
+        let pairs = pairs();
+        let n = pairs.len() as f64;
+        let cov: Covariance = pairs.iter().cloned().collect();
+        let comoment = naive_comoment(&pairs);
+        assert!((cov.covariance() - comoment / n).abs() < 1e-9);
+        assert!((cov.sample_covariance() - comoment / (n - 1.0)).abs()
+                < 1e-9);
+
+        let cov: Covariance = vec![(1, 2), (2, 4), (3, 6)].into_iter().collect();
+        assert!((cov.covariance() - 4.0 / 3.0).abs() < 1e-12);
+        assert!((cov.sample_covariance() - 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn covariance_empty() {
+        assert!(Covariance::new().covariance().is_nan());
+        let mut cov = Covariance::new();
+        cov.add(1.0, 2.0);
+        assert_eq!(cov.covariance(), 0.0);
+        assert!(cov.sample_covariance().is_nan());
+    }
+
+    #[test]
+    fn covariance_merge() {
+        let pairs = pairs();
+        let expected: Covariance = pairs.iter().cloned().collect();
+        let parts = pairs.chunks(7).map(|c| c.iter().cloned().collect());
+        let got: Covariance = merge_all(parts).unwrap();
+        assert_eq!(got.len(), expected.len());
+        assert!((got.covariance() - expected.covariance()).abs() < 1e-9);
+        assert!((got.mean_x() - expected.mean_x()).abs() < 1e-9);
+        assert!((got.mean_y() - expected.mean_y()).abs() < 1e-9);
+
+        assert!((got.correlation() - expected.correlation()).abs() < 1e-9);
+
+        let mut got = expected;
+        got.merge(Covariance::new());
+        assert_eq!(got.covariance(), expected.covariance());
+    }
+
+    #[test]
+    fn correlation() {
+        let pos: Covariance =
+            (0..20).map(|i| (i as f64, 3.0 * i as f64 + 1.0)).collect();
+        assert_eq!(pos.correlation(), 1.0);
+        let neg: Covariance =
+            (0..20).map(|i| (i as f64, -0.5 * i as f64)).collect();
+        assert_eq!(neg.correlation(), -1.0);
+
+        let pairs = pairs();
+        let r = Covariance::from_iter(pairs.iter().cloned()).correlation();
+        assert!(r > 0.0 && r < 1.0);
+    }
+
+    #[test]
+    fn correlation_zero_variance() {
+        assert!(Covariance::new().correlation().is_nan());
+        let flat: Covariance = (0..5).map(|i| (i, 7)).collect();
+        assert!(flat.correlation().is_nan());
+    }
+}
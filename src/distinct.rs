@@ -0,0 +1,200 @@
+use std::collections::hash_map::RandomState;
+use std::collections::hash_set::{self, HashSet};
+use std::default::Default;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::iter::{FromIterator, IntoIterator};
+
+use Commute;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A commutative data structure for the exact set of distinct samples.
+///
+/// This is like `Frequencies`, except it only records whether a sample has
+/// been seen, not how many times. Merging two sets takes their union.
+///
+/// Memory grows with the number of distinct samples. When an estimate of
+/// the number of distinct samples is good enough, `HyperLogLog` uses a fixed
+/// amount of memory instead.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(
+    serialize = "T: Serialize",
+    deserialize = "T: Deserialize<'de> + Eq + Hash, \
+                   S: BuildHasher + Default",
+)))]
+pub struct DistinctSet<T, S = RandomState> {
+    data: HashSet<T, S>,
+}
+
+impl<T: fmt::Debug, S> fmt::Debug for DistinctSet<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.data)
+    }
+}
+
+impl<T: Eq + Hash> DistinctSet<T> {
+    /// Create an empty set.
+    pub fn new() -> DistinctSet<T> {
+        Default::default()
+    }
+
+    /// Create an empty set with room for `capacity` distinct samples
+    /// before reallocating.
+    pub fn with_capacity(capacity: usize) -> DistinctSet<T> {
+        DistinctSet { data: HashSet::with_capacity(capacity) }
+    }
+}
+
+impl<T: Eq + Hash, S: BuildHasher> DistinctSet<T, S> {
+    /// Create an empty set that hashes samples with `hasher`.
+    pub fn with_hasher(hasher: S) -> DistinctSet<T, S> {
+        DistinctSet { data: HashSet::with_hasher(hasher) }
+    }
+
+    /// Create an empty set with room for `capacity` distinct samples
+    /// before reallocating, that hashes samples with `hasher`.
+    pub fn with_capacity_and_hasher(
+        capacity: usize,
+        hasher: S,
+    ) -> DistinctSet<T, S> {
+        let data = HashSet::with_capacity_and_hasher(capacity, hasher);
+        DistinctSet { data }
+    }
+
+    /// Add a sample to the set.
+    ///
+    /// This returns `true` if the sample had not been seen before.
+    pub fn add(&mut self, v: T) -> bool {
+        self.data.insert(v)
+    }
+
+    /// Returns true if `v` has been added to the set.
+    pub fn contains(&self, v: &T) -> bool {
+        self.data.contains(v)
+    }
+
+    /// Returns the number of distinct samples.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns true if there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns an iterator over the distinct samples, in arbitrary order.
+    pub fn iter(&self) -> hash_set::Iter<'_, T> {
+        self.data.iter()
+    }
+}
+
+impl<T: Eq + Hash, S: BuildHasher> Commute for DistinctSet<T, S> {
+    fn merge(&mut self, v: DistinctSet<T, S>) {
+        self.data.extend(v.data);
+    }
+}
+
+impl<T: Eq + Hash, S: BuildHasher + Default> Default for DistinctSet<T, S> {
+    fn default() -> DistinctSet<T, S> {
+        DistinctSet { data: HashSet::default() }
+    }
+}
+
+impl<T, S> FromIterator<T> for DistinctSet<T, S>
+        where T: Eq + Hash, S: BuildHasher + Default {
+    fn from_iter<I: IntoIterator<Item=T>>(it: I) -> DistinctSet<T, S> {
+        let mut v = DistinctSet::default();
+        v.extend(it);
+        v
+    }
+}
+
+impl<T: Eq + Hash, S: BuildHasher> Extend<T> for DistinctSet<T, S> {
+    fn extend<I: IntoIterator<Item=T>>(&mut self, it: I) {
+        self.data.extend(it);
+    }
+}
+
+impl<T, S> IntoIterator for DistinctSet<T, S> {
+    type Item = T;
+    type IntoIter = hash_set::IntoIter<T>;
+
+    fn into_iter(self) -> hash_set::IntoIter<T> {
+        self.data.into_iter()
+    }
+}
+
+impl<'a, T, S> IntoIterator for &'a DistinctSet<T, S>
+        where T: Eq + Hash, S: BuildHasher {
+    type Item = &'a T;
+    type IntoIter = hash_set::Iter<'a, T>;
+
+    fn into_iter(self) -> hash_set::Iter<'a, T> {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::hash::BuildHasherDefault;
+
+    use {Commute, Frequencies};
+    use stable_hash::StableHasher;
+    use super::DistinctSet;
+
+    #[test]
+    fn add_contains() {
+        let mut set = DistinctSet::new();
+        assert!(set.is_empty());
+        assert!(set.add("a"));
+        assert!(set.add("b"));
+        assert!(!set.add("a"));
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&"a"));
+        assert!(!set.contains(&"c"));
+    }
+
+    #[test]
+    fn explicit_constructors() {
+        let mut set = DistinctSet::with_capacity(2);
+        set.extend(vec![1, 2, 3, 2]);
+        assert_eq!(set.len(), 3);
+
+        type Stable = BuildHasherDefault<StableHasher>;
+        let mut set = DistinctSet::with_hasher(Stable::default());
+        set.extend(vec![1, 2, 2]);
+        let mut more = DistinctSet::with_capacity_and_hasher(
+            1, Stable::default());
+        more.add(3);
+        set.merge(more);
+        let mut got: Vec<u32> = set.into_iter().collect();
+        got.sort();
+        assert_eq!(got, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn merge_overlap() {
+        let mut s1: DistinctSet<u32> = (0..60).collect();
+        let s2: DistinctSet<u32> = (40..100).chain(0..10).collect();
+        s1.merge(s2);
+        assert_eq!(s1.len(), 100);
+        assert!((0..100).all(|i| s1.contains(&i)));
+
+        let mut got: Vec<u32> = s1.into_iter().collect();
+        got.sort();
+        assert_eq!(got, (0..100).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn matches_cardinality() {
+        let data: Vec<u64> = (0..500).map(|i| (i * 7919) % 173).collect();
+        let set: DistinctSet<u64> = data.iter().cloned().collect();
+        let freqs: Frequencies<u64> = data.iter().cloned().collect();
+        assert_eq!(set.len() as u64, freqs.cardinality());
+        assert!(set.iter().all(|x| freqs.count(x) > 0));
+    }
+}
@@ -0,0 +1,140 @@
+use std::fmt;
+use std::iter::IntoIterator;
+
+use num_traits::ToPrimitive;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Online state for an exponentially weighted moving average and variance.
+///
+/// Each new sample `x` moves the mean towards it by a fraction `alpha` of the
+/// difference: `mean += alpha * (x - mean)`. Larger values of `alpha` weight
+/// recent samples more heavily. The variance uses the corresponding
+/// exponentially weighted recurrence.
+///
+/// Since the result depends on the order in which samples are added, this
+/// type does not implement `Commute`: there is no way to combine the moving
+/// averages of two partitions of a stream.
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Ewma {
+    alpha: f64,
+    len: u64,
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    mean: f64,
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    variance: f64,
+}
+
+impl Ewma {
+    /// Create initial state with the smoothing factor `alpha`.
+    ///
+    /// This panics if `alpha` is not in `(0, 1]`.
+    pub fn new(alpha: f64) -> Ewma {
+        assert!(alpha > 0.0 && alpha <= 1.0,
+                "smoothing factor must be in (0, 1], got {}", alpha);
+        Ewma { alpha, len: 0, mean: 0.0, variance: 0.0 }
+    }
+
+    /// Add a new sample.
+    ///
+    /// The first sample initializes the mean, with a variance of `0`.
+    pub fn add<T: ToPrimitive>(&mut self, sample: T) {
+        let x = sample.to_f64().unwrap();
+        self.len += 1;
+        if self.len == 1 {
+            self.mean = x;
+            return;
+        }
+        // See: Finch, "Incremental calculation of weighted mean and
+        // variance", 2009.
+        let diff = x - self.mean;
+        let incr = self.alpha * diff;
+        self.mean += incr;
+        self.variance = (1.0 - self.alpha) * (self.variance + diff * incr);
+    }
+
+    /// Returns the smoothing factor.
+    pub fn alpha(&self) -> f64 {
+        self.alpha
+    }
+
+    /// Returns the exponentially weighted mean.
+    ///
+    /// If there are no samples, then `NaN` is returned.
+    pub fn mean(&self) -> f64 {
+        if self.len == 0 { f64::NAN } else { self.mean }
+    }
+
+    /// Returns the exponentially weighted variance.
+    ///
+    /// If there are no samples, then `NaN` is returned.
+    pub fn variance(&self) -> f64 {
+        if self.len == 0 { f64::NAN } else { self.variance }
+    }
+
+    /// Returns the exponentially weighted standard deviation.
+    ///
+    /// If there are no samples, then `NaN` is returned.
+    pub fn stddev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    /// Returns the number of samples.
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Returns true if there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl fmt::Debug for Ewma {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.10} +/- {:.10}", self.mean(), self.stddev())
+    }
+}
+
+impl<T: ToPrimitive> Extend<T> for Ewma {
+    fn extend<I: IntoIterator<Item=T>>(&mut self, it: I) {
+        for sample in it {
+            self.add(sample);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Ewma;
+
+    #[test]
+    fn recurrence() {
+        let mut ewma = Ewma::new(0.5);
+        assert!(ewma.mean().is_nan());
+
+        ewma.add(1);
+        assert_eq!((ewma.mean(), ewma.variance()), (1.0, 0.0));
+        ewma.add(2);
+        assert_eq!((ewma.mean(), ewma.variance()), (1.5, 0.25));
+        ewma.add(3);
+        assert_eq!((ewma.mean(), ewma.variance()), (2.25, 0.6875));
+        assert_eq!(ewma.len(), 3);
+    }
+
+    #[test]
+    fn alpha_one_tracks_last() {
+        let mut ewma = Ewma::new(1.0);
+        ewma.extend(vec![4.0, -2.0, 7.5]);
+        assert_eq!(ewma.mean(), 7.5);
+        assert_eq!(ewma.variance(), 0.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn bad_alpha() {
+        Ewma::new(0.0);
+    }
+}
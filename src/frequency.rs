@@ -1,18 +1,36 @@
-use std::collections::hash_map::{HashMap, Entry};
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::collections::hash_map::{HashMap, Entry, RandomState};
 use std::fmt;
-use std::hash::Hash;
+use std::hash::{BuildHasher, Hash};
 use std::iter::{FromIterator, IntoIterator};
 use std::default::Default;
 
-use Commute;
+use num_traits::Num;
+
+use {Commute, Partial};
+use binary::{self, BinaryKey, DecodeError, Kind};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 /// A commutative data structure for exact frequency counts.
+///
+/// Like `HashMap`, elements are hashed with `RandomState` by default, and
+/// `with_hasher` uses a different hasher.
 #[derive(Clone)]
-pub struct Frequencies<T> {
-    data: HashMap<T, u64>,
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(
+    serialize = "T: Serialize",
+    deserialize = "T: Deserialize<'de> + Eq + Hash, \
+                   S: BuildHasher + Default",
+)))]
+pub struct Frequencies<T, S = RandomState> {
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::entries"))]
+    data: HashMap<T, u64, S>,
 }
 
-impl<T: fmt::Debug + Eq + Hash> fmt::Debug for Frequencies<T> {
+impl<T: fmt::Debug, S> fmt::Debug for Frequencies<T, S> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{:?}", self.data)
     }
@@ -20,10 +38,37 @@ impl<T: fmt::Debug + Eq + Hash> fmt::Debug for Frequencies<T> {
 
 impl<T: Eq + Hash> Frequencies<T> {
     /// Create a new frequency table with no samples.
+    ///
+    /// This does not allocate until the first sample is added. Use
+    /// `with_capacity` if the number of distinct samples is known.
     pub fn new() -> Frequencies<T> {
         Default::default()
     }
 
+    /// Create a new frequency table with room for `capacity` distinct
+    /// samples before reallocating.
+    pub fn with_capacity(capacity: usize) -> Frequencies<T> {
+        Frequencies { data: HashMap::with_capacity(capacity) }
+    }
+}
+
+impl<T: Eq + Hash, S: BuildHasher> Frequencies<T, S> {
+    /// Create a new frequency table with no samples that hashes elements
+    /// with `hasher`.
+    pub fn with_hasher(hasher: S) -> Frequencies<T, S> {
+        Frequencies { data: HashMap::with_hasher(hasher) }
+    }
+
+    /// Create a new frequency table with room for `capacity` distinct
+    /// samples before reallocating, that hashes elements with `hasher`.
+    pub fn with_capacity_and_hasher(
+        capacity: usize,
+        hasher: S,
+    ) -> Frequencies<T, S> {
+        let data = HashMap::with_capacity_and_hasher(capacity, hasher);
+        Frequencies { data }
+    }
+
     /// Add a sample to the frequency table.
     pub fn add(&mut self, v: T) {
         match self.data.entry(v) {
@@ -32,9 +77,33 @@ impl<T: Eq + Hash> Frequencies<T> {
         }
     }
 
+    /// Remove one occurrence of `v` from the frequency table.
+    ///
+    /// Elements whose count drops to zero are removed entirely. This returns
+    /// `false` if `v` was not in the table.
+    pub fn remove(&mut self, v: &T) -> bool {
+        let emptied = match self.data.get_mut(v) {
+            None => return false,
+            Some(count) => {
+                *count -= 1;
+                *count == 0
+            }
+        };
+        if emptied {
+            self.data.remove(v);
+        }
+        true
+    }
+
     /// Return the number of occurrences of `v` in the data.
     pub fn count(&self, v: &T) -> u64 {
-        self.data.get(v).map(|&v| v).unwrap_or(0)
+        self.data.get(v).cloned().unwrap_or(0)
+    }
+
+    /// Return an iterator over elements and their corresponding counts in
+    /// an arbitrary order.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item=(&'a T, u64)> + 'a {
+        self.data.iter().map(|(k, &v)| (k, v))
     }
 
     /// Return the cardinality (number of unique elements) in the data.
@@ -45,43 +114,253 @@ impl<T: Eq + Hash> Frequencies<T> {
     /// Returns the mode if one exists.
     pub fn mode(&self) -> Option<&T> {
         let counts = self.most_frequent();
-        if counts.is_empty() {
-            None
-        } else if counts.len() >= 2 && counts[0].1 == counts[1].1 {
+        if counts.is_empty()
+           || (counts.len() >= 2 && counts[0].1 == counts[1].1) {
             None
         } else {
             Some(counts[0].0)
         }
     }
 
+    /// Returns every element whose count equals the maximum count.
+    ///
+    /// Unlike `mode`, ties are not discarded. Elements are returned in an
+    /// arbitrary order, and an empty table has no modes.
+    pub fn modes(&self) -> Vec<&T> {
+        let max = match self.data.values().max() {
+            None => return vec![],
+            Some(&max) => max,
+        };
+        self.data.iter().filter(|&(_, &c)| c == max).map(|(k, _)| k).collect()
+    }
+
     /// Return a `Vec` of elements and their corresponding counts in
     /// descending order.
+    ///
+    /// Elements with equal counts are returned in an arbitrary order, which
+    /// may differ between runs. Use `most_frequent_sorted` for a
+    /// deterministic order.
     pub fn most_frequent(&self) -> Vec<(&T, u64)> {
         let mut counts: Vec<_> = self.data.iter()
                                           .map(|(k, &v)| (k, v))
                                           .collect();
-        counts.sort_by(|&(_, c1), &(_, c2)| c2.cmp(&c1));
+        counts.sort_by_key(|&(_, c)| Reverse(c));
         counts
     }
 
+    /// Return the `k` most frequent elements and their counts in descending
+    /// order.
+    ///
+    /// This runs in `O(n log k)` time and only allocates room for `k`
+    /// elements. When several elements tie for the `k`th position, which of
+    /// them are returned is arbitrary.
+    pub fn top_k(&self, k: usize) -> Vec<(&T, u64)> {
+        if k == 0 {
+            return vec![];
+        }
+        // A min-heap of the largest counts seen so far.
+        let mut heap = BinaryHeap::with_capacity(k + 1);
+        for (v, &c) in &self.data {
+            if heap.len() < k {
+                heap.push(Reverse(ByCount(c, v)));
+            } else if heap.peek().is_some_and(|m| c > (m.0).0) {
+                heap.pop();
+                heap.push(Reverse(ByCount(c, v)));
+            }
+        }
+        heap.into_sorted_vec().into_iter()
+            .map(|Reverse(ByCount(c, v))| (v, c))
+            .collect()
+    }
+
     /// Return a `Vec` of elements and their corresponding counts in
     /// ascending order.
     pub fn least_frequent(&self) -> Vec<(&T, u64)> {
         let mut counts: Vec<_> = self.data.iter()
                                           .map(|(k, &v)| (k, v))
                                           .collect();
-        counts.sort_by(|&(_, c1), &(_, c2)| c1.cmp(&c2));
+        counts.sort_by_key(|&(_, c)| c);
         counts
     }
 
+    /// Return an iterator over elements and their corresponding counts in
+    /// descending order.
+    ///
+    /// This still sorts all of the elements up front, but saves the caller
+    /// from collecting the results when they are consumed immediately.
+    pub fn most_frequent_iter<'a>(
+        &'a self,
+    ) -> impl Iterator<Item=(&'a T, u64)> + 'a {
+        self.most_frequent().into_iter()
+    }
+
+    /// Return an iterator over elements and their corresponding counts in
+    /// ascending order.
+    ///
+    /// This still sorts all of the elements up front, but saves the caller
+    /// from collecting the results when they are consumed immediately.
+    pub fn least_frequent_iter<'a>(
+        &'a self,
+    ) -> impl Iterator<Item=(&'a T, u64)> + 'a {
+        self.least_frequent().into_iter()
+    }
+
+    /// Return each distinct element with its count divided by the total
+    /// count, i.e., the empirical probability mass function.
+    ///
+    /// Elements are returned in an arbitrary order.
+    pub fn probabilities(&self) -> Vec<(&T, f64)> {
+        let total = self.data.values().sum::<u64>() as f64;
+        self.data.iter().map(|(k, &c)| (k, c as f64 / total)).collect()
+    }
+
+    /// Returns the Shannon entropy of the data, in bits.
+    ///
+    /// This is `0.0` if there is no data.
+    pub fn entropy(&self) -> f64 {
+        let total: u64 = self.data.values().sum();
+        if total == 0 {
+            return 0.0;
+        }
+        let total = total as f64;
+        -self.data.values().map(|&c| {
+            let p = c as f64 / total;
+            p * p.log2()
+        }).sum::<f64>()
+    }
+
     /// Returns the cardinality of the data.
     pub fn len(&self) -> usize {
         self.data.len()
     }
+
+    /// Returns true if there is no frequency/cardinality data.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: Eq + Hash + Ord, S: BuildHasher> Frequencies<T, S> {
+    /// Return a `Vec` of elements and their corresponding counts in
+    /// descending order of count, with ties broken by ascending element.
+    ///
+    /// Unlike `most_frequent`, the order is fully determined by the data.
+    pub fn most_frequent_sorted(&self) -> Vec<(&T, u64)> {
+        let mut counts: Vec<_> = self.data.iter()
+                                          .map(|(k, &v)| (k, v))
+                                          .collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        counts
+    }
+
+    /// Returns the `q`th quantile of the data, where `q` is in `[0, 1]`.
+    ///
+    /// This is the smallest element whose cumulative count reaches
+    /// `q * total`, where `total` is the number of samples. No interpolation
+    /// is done, so the quantile is always one of the elements. Only the
+    /// distinct elements are sorted, which makes this efficient when there
+    /// are many samples but few distinct elements.
+    ///
+    /// Since most decimal fractions can't be represented exactly, `q * total`
+    /// is taken to be an integer if it is within 4 units in the last place
+    /// of one. For example, `0.28 * 25` is `7.000000000000001` in floating
+    /// point, but selects the 7th sample rather than the 8th. Since the
+    /// tolerance is relative to `q * total`, it only absorbs rounding error:
+    /// a product more than a few units in the last place past an integer
+    /// still selects the next rank, however large `total` is.
+    ///
+    /// `None` is returned if there is no data or if `q` is not in `[0, 1]`.
+    pub fn quantile(&self, q: f64) -> Option<&T> {
+        if self.data.is_empty() || !(0.0..=1.0).contains(&q) {
+            return None;
+        }
+        let mut counts: Vec<(&T, u64)> =
+            self.data.iter().map(|(k, &c)| (k, c)).collect();
+        counts.sort_by(|a, b| a.0.cmp(b.0));
+        let total: u64 = counts.iter().map(|&(_, c)| c).sum();
+        let mut x = q * total as f64;
+        if (x - x.round()).abs() <= 4.0 * f64::EPSILON * x {
+            x = x.round();
+        }
+        let rank = (x.ceil() as u64).clamp(1, total);
+        counts.into_iter()
+            .scan(0, |cumulative, (k, c)| {
+                *cumulative += c;
+                Some((k, *cumulative))
+            })
+            .find(|&(_, cumulative)| cumulative >= rank)
+            .map(|(k, _)| k)
+    }
+}
+
+impl<T, S> Frequencies<T, S>
+        where T: BinaryKey + Eq + Hash, S: BuildHasher + Default {
+    /// Encode this frequency table in a compact, portable binary format.
+    ///
+    /// The encoding begins with a versioned header, followed by the number
+    /// of distinct elements and then each element and its count in an
+    /// arbitrary order. Every integer is in little endian byte order. Use
+    /// `from_bytes` to decode it.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut dst = binary::write_header(Kind::Frequencies);
+        binary::write_u64(&mut dst, self.data.len() as u64);
+        for (k, &count) in &self.data {
+            k.encode_key(&mut dst);
+            binary::write_u64(&mut dst, count);
+        }
+        dst
+    }
+
+    /// Decode a frequency table that was encoded with `to_bytes`.
+    ///
+    /// An error is returned if `bytes` is not a complete encoding of a
+    /// frequency table, or if it contains a duplicate element or an element
+    /// with a count of zero.
+    pub fn from_bytes(
+        bytes: &[u8],
+    ) -> Result<Frequencies<T, S>, DecodeError> {
+        let mut src = bytes;
+        binary::read_header(&mut src, Kind::Frequencies)?;
+        let len = binary::read_len(&mut src)?;
+        let mut freqs =
+            Frequencies::with_capacity_and_hasher(len, S::default());
+        for _ in 0..len {
+            let k = T::decode_key(&mut src)?;
+            let count = binary::read_u64(&mut src)?;
+            if count == 0 || freqs.data.insert(k, count).is_some() {
+                return Err(DecodeError::InvalidData);
+            }
+        }
+        binary::read_end(src)?;
+        Ok(freqs)
+    }
+}
+
+/// An element ordered only by its count.
+struct ByCount<'a, T: 'a>(u64, &'a T);
+
+impl<'a, T> PartialEq for ByCount<'a, T> {
+    fn eq(&self, other: &ByCount<'a, T>) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<'a, T> Eq for ByCount<'a, T> {}
+
+impl<'a, T> PartialOrd for ByCount<'a, T> {
+    fn partial_cmp(&self, other: &ByCount<'a, T>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a, T> Ord for ByCount<'a, T> {
+    fn cmp(&self, other: &ByCount<'a, T>) -> Ordering {
+        self.0.cmp(&other.0)
+    }
 }
 
-impl<T: Eq + Hash> Commute for Frequencies<T> {
-    fn merge(&mut self, v: Frequencies<T>) {
+impl<T: Eq + Hash, S: BuildHasher> Commute for Frequencies<T, S> {
+    fn merge(&mut self, v: Frequencies<T, S>) {
         for (k, v2) in v.data.into_iter() {
             match self.data.entry(k) {
                 Entry::Vacant(v1) => { v1.insert(v2); }
@@ -91,21 +370,22 @@ impl<T: Eq + Hash> Commute for Frequencies<T> {
     }
 }
 
-impl<T: Eq + Hash> Default for Frequencies<T> {
-    fn default() -> Frequencies<T> {
-        Frequencies { data: HashMap::with_capacity(100000) }
+impl<T: Eq + Hash, S: BuildHasher + Default> Default for Frequencies<T, S> {
+    fn default() -> Frequencies<T, S> {
+        Frequencies { data: HashMap::default() }
     }
 }
 
-impl<T: Eq + Hash> FromIterator<T> for Frequencies<T> {
-    fn from_iter<I: IntoIterator<Item=T>>(it: I) -> Frequencies<T> {
-        let mut v = Frequencies::new();
+impl<T, S> FromIterator<T> for Frequencies<T, S>
+        where T: Eq + Hash, S: BuildHasher + Default {
+    fn from_iter<I: IntoIterator<Item=T>>(it: I) -> Frequencies<T, S> {
+        let mut v = Frequencies::default();
         v.extend(it);
         v
     }
 }
 
-impl<T: Eq + Hash> Extend<T> for Frequencies<T> {
+impl<T: Eq + Hash, S: BuildHasher> Extend<T> for Frequencies<T, S> {
     fn extend<I: IntoIterator<Item=T>>(&mut self, it: I) {
         for sample in it {
             self.add(sample);
@@ -113,15 +393,964 @@ impl<T: Eq + Hash> Extend<T> for Frequencies<T> {
     }
 }
 
+/// A commutative data structure for exact frequency counts of pairs.
+///
+/// This is a contingency table for two paired categorical variables, and can
+/// be used to measure the association between them.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(
+    serialize = "A: Serialize, B: Serialize",
+    deserialize = "A: Deserialize<'de> + Eq + Hash, \
+                   B: Deserialize<'de> + Eq + Hash, \
+                   S: BuildHasher + Default",
+)))]
+pub struct JointFrequencies<A, B, S = RandomState> {
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::entries"))]
+    data: HashMap<(A, B), u64, S>,
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::entries"))]
+    rows: HashMap<A, u64, S>,
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::entries"))]
+    cols: HashMap<B, u64, S>,
+    total: u64,
+}
+
+impl<A, B, S> fmt::Debug for JointFrequencies<A, B, S>
+        where A: fmt::Debug, B: fmt::Debug {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.data)
+    }
+}
+
+impl<A, B> JointFrequencies<A, B>
+        where A: Eq + Hash + Clone, B: Eq + Hash + Clone {
+    /// Create a new contingency table with no samples.
+    pub fn new() -> JointFrequencies<A, B> {
+        Default::default()
+    }
+}
+
+impl<A, B, S> JointFrequencies<A, B, S>
+        where A: Eq + Hash + Clone, B: Eq + Hash + Clone, S: BuildHasher {
+    /// Create a new contingency table with no samples that hashes samples
+    /// with `hasher`.
+    ///
+    /// The pairs and each of the two variables are counted in separate
+    /// maps, each of which gets a clone of `hasher`.
+    pub fn with_hasher(hasher: S) -> JointFrequencies<A, B, S>
+            where S: Clone {
+        JointFrequencies {
+            data: HashMap::with_hasher(hasher.clone()),
+            rows: HashMap::with_hasher(hasher.clone()),
+            cols: HashMap::with_hasher(hasher),
+            total: 0,
+        }
+    }
+
+    /// Add a pair of samples to the contingency table.
+    pub fn add(&mut self, a: A, b: B) {
+        *self.rows.entry(a.clone()).or_insert(0) += 1;
+        *self.cols.entry(b.clone()).or_insert(0) += 1;
+        *self.data.entry((a, b)).or_insert(0) += 1;
+        self.total += 1;
+    }
+
+    /// Return the number of occurrences of the pair `(a, b)` in the data.
+    pub fn count(&self, a: &A, b: &B) -> u64 {
+        // This lookup needs an owned key, but is only used for queries.
+        self.data.get(&(a.clone(), b.clone())).cloned().unwrap_or(0)
+    }
+
+    /// Return Pearson's chi-square statistic for independence of the two
+    /// variables.
+    pub fn chi_square(&self) -> f64 {
+        // Cells that were never observed contribute exactly their expected
+        // count, so the statistic can be computed from the observed cells:
+        //
+        //     chi^2 = n * (sum(O^2 / (R * C)) - 1)
+        let n = self.total as f64;
+        let mut sum = 0.0;
+        for ((a, b), &count) in &self.data {
+            let (r, c) = (self.rows[a] as f64, self.cols[b] as f64);
+            sum += (count as f64) * (count as f64) / (r * c);
+        }
+        n * (sum - 1.0)
+    }
+
+    /// Return Cramér's V, a measure of association between the two variables
+    /// in `[0, 1]`.
+    ///
+    /// A value of `0` means no association and a value of `1` means one
+    /// variable completely determines the other. `NaN` is returned if either
+    /// variable has fewer than two distinct values.
+    ///
+    /// Cramér's V overestimates the association of small samples. See
+    /// `cramers_v_corrected` for a bias corrected variant.
+    pub fn cramers_v(&self) -> f64 {
+        let (r, c) = (self.rows.len() as f64, self.cols.len() as f64);
+        let k = (r - 1.0).min(c - 1.0);
+        if k <= 0.0 {
+            return f64::NAN;
+        }
+        // Rounding can make the statistic of an independent table slightly
+        // negative, which would make the square root `NaN`.
+        (self.chi_square().max(0.0) / (self.total as f64) / k).sqrt()
+    }
+
+    /// Return the bias corrected Cramér's V of Bergsma (2013).
+    ///
+    /// This corrects both the statistic and the table dimensions for the
+    /// sample size, which makes it a better estimate for small samples.
+    /// `NaN` is returned if either variable has fewer than two distinct
+    /// values or if there are fewer than two samples.
+    pub fn cramers_v_corrected(&self) -> f64 {
+        let n = self.total as f64;
+        let (r, c) = (self.rows.len() as f64, self.cols.len() as f64);
+        if r < 2.0 || c < 2.0 || n < 2.0 {
+            return f64::NAN;
+        }
+        let phi2 = self.chi_square() / n;
+        let phi2 = (phi2 - (r - 1.0) * (c - 1.0) / (n - 1.0)).max(0.0);
+        let r = r - (r - 1.0) * (r - 1.0) / (n - 1.0);
+        let c = c - (c - 1.0) * (c - 1.0) / (n - 1.0);
+        let k = (r - 1.0).min(c - 1.0);
+        if k <= 0.0 {
+            return f64::NAN;
+        }
+        (phi2 / k).sqrt()
+    }
+
+    /// Returns the number of pairs in the data.
+    pub fn len(&self) -> usize {
+        self.total as usize
+    }
+
+    /// Returns true if there are no pairs in the data.
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+}
+
+impl<A, B, S> Commute for JointFrequencies<A, B, S>
+        where A: Eq + Hash, B: Eq + Hash, S: BuildHasher {
+    fn merge(&mut self, v: JointFrequencies<A, B, S>) {
+        for (k, v2) in v.data {
+            *self.data.entry(k).or_insert(0) += v2;
+        }
+        for (k, v2) in v.rows {
+            *self.rows.entry(k).or_insert(0) += v2;
+        }
+        for (k, v2) in v.cols {
+            *self.cols.entry(k).or_insert(0) += v2;
+        }
+        self.total += v.total;
+    }
+}
+
+impl<A, B, S> Default for JointFrequencies<A, B, S>
+        where A: Eq + Hash, B: Eq + Hash, S: BuildHasher + Default {
+    fn default() -> JointFrequencies<A, B, S> {
+        JointFrequencies {
+            data: HashMap::default(),
+            rows: HashMap::default(),
+            cols: HashMap::default(),
+            total: 0,
+        }
+    }
+}
+
+impl<A, B, S> FromIterator<(A, B)> for JointFrequencies<A, B, S>
+        where A: Eq + Hash + Clone,
+              B: Eq + Hash + Clone,
+              S: BuildHasher + Default {
+    fn from_iter<I: IntoIterator<Item=(A, B)>>(
+        it: I,
+    ) -> JointFrequencies<A, B, S> {
+        let mut v = JointFrequencies::default();
+        v.extend(it);
+        v
+    }
+}
+
+impl<A, B, S> Extend<(A, B)> for JointFrequencies<A, B, S>
+        where A: Eq + Hash + Clone, B: Eq + Hash + Clone, S: BuildHasher {
+    fn extend<I: IntoIterator<Item=(A, B)>>(&mut self, it: I) {
+        for (a, b) in it {
+            self.add(a, b);
+        }
+    }
+}
+
+/// A commutative data structure for summing values grouped by key.
+///
+/// This is the weighted analog of `Frequencies`: instead of counting the
+/// occurrences of each key, the values associated with each key are summed.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(
+    serialize = "K: Serialize, V: Serialize",
+    deserialize = "K: Deserialize<'de> + Eq + Hash, V: Deserialize<'de>, \
+                   S: BuildHasher + Default",
+)))]
+pub struct SumByKey<K, V, S = RandomState> {
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::entries"))]
+    data: HashMap<K, V, S>,
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, S> fmt::Debug for SumByKey<K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.data)
+    }
+}
+
+impl<K: Eq + Hash, V: Num + Copy + PartialOrd> SumByKey<K, V> {
+    /// Create a new table with no samples.
+    pub fn new() -> SumByKey<K, V> {
+        Default::default()
+    }
+
+    /// Create a new table with room for `capacity` distinct keys before
+    /// reallocating.
+    pub fn with_capacity(capacity: usize) -> SumByKey<K, V> {
+        SumByKey { data: HashMap::with_capacity(capacity) }
+    }
+}
+
+impl<K, V, S> SumByKey<K, V, S>
+        where K: Eq + Hash, V: Num + Copy + PartialOrd, S: BuildHasher {
+    /// Create a new table with no samples that hashes keys with `hasher`.
+    pub fn with_hasher(hasher: S) -> SumByKey<K, V, S> {
+        SumByKey { data: HashMap::with_hasher(hasher) }
+    }
+
+    /// Create a new table with room for `capacity` distinct keys before
+    /// reallocating, that hashes keys with `hasher`.
+    pub fn with_capacity_and_hasher(
+        capacity: usize,
+        hasher: S,
+    ) -> SumByKey<K, V, S> {
+        let data = HashMap::with_capacity_and_hasher(capacity, hasher);
+        SumByKey { data }
+    }
+
+    /// Add `value` to the sum for `key`.
+    pub fn add(&mut self, key: K, value: V) {
+        let sum = self.data.entry(key).or_insert_with(V::zero);
+        *sum = *sum + value;
+    }
+
+    /// Return the sum of the values added for `key`.
+    ///
+    /// If no values were added for `key`, then `0` is returned.
+    pub fn sum(&self, key: &K) -> V {
+        self.data.get(key).cloned().unwrap_or_else(V::zero)
+    }
+
+    /// Return the `k` keys with the largest sums, along with their sums, in
+    /// descending order.
+    ///
+    /// Keys with equal sums are returned in an arbitrary order.
+    pub fn top_k_by_sum(&self, k: usize) -> Vec<(&K, V)> {
+        let mut sums: Vec<_> = self.data.iter()
+                                        .map(|(k, &v)| (k, v))
+                                        .collect();
+        sums.sort_by_key(|&(_, s)| Reverse(Partial(s)));
+        sums.truncate(k);
+        sums
+    }
+
+    /// Returns the number of distinct keys.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns true if there are no keys.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+impl<K, V, S> Commute for SumByKey<K, V, S>
+        where K: Eq + Hash, V: Num + Copy, S: BuildHasher {
+    fn merge(&mut self, v: SumByKey<K, V, S>) {
+        for (k, v2) in v.data {
+            match self.data.entry(k) {
+                Entry::Vacant(v1) => { v1.insert(v2); }
+                Entry::Occupied(mut v1) => {
+                    let sum = *v1.get() + v2;
+                    v1.insert(sum);
+                }
+            }
+        }
+    }
+}
+
+impl<K, V, S> Default for SumByKey<K, V, S>
+        where K: Eq + Hash, S: BuildHasher + Default {
+    fn default() -> SumByKey<K, V, S> {
+        SumByKey { data: HashMap::default() }
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for SumByKey<K, V, S>
+        where K: Eq + Hash,
+              V: Num + Copy + PartialOrd,
+              S: BuildHasher + Default {
+    fn from_iter<I: IntoIterator<Item=(K, V)>>(it: I) -> SumByKey<K, V, S> {
+        let mut v = SumByKey::default();
+        v.extend(it);
+        v
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for SumByKey<K, V, S>
+        where K: Eq + Hash, V: Num + Copy + PartialOrd, S: BuildHasher {
+    fn extend<I: IntoIterator<Item=(K, V)>>(&mut self, it: I) {
+        for (key, value) in it {
+            self.add(key, value);
+        }
+    }
+}
+
+/// A commutative data structure for weighted frequency counts.
+///
+/// This is like `Frequencies`, except each sample carries a fractional
+/// weight, and the weights of each distinct sample are summed instead of
+/// counted. Adding every sample with a weight of `1` gives the same results
+/// as `Frequencies`.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(
+    serialize = "T: Serialize",
+    deserialize = "T: Deserialize<'de> + Eq + Hash, \
+                   S: BuildHasher + Default",
+)))]
+pub struct WeightedFrequencies<T, S = RandomState> {
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::entries"))]
+    data: HashMap<T, f64, S>,
+}
+
+impl<T: fmt::Debug, S> fmt::Debug for WeightedFrequencies<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.data)
+    }
+}
+
+impl<T: Eq + Hash> WeightedFrequencies<T> {
+    /// Create a new frequency table with no samples.
+    pub fn new() -> WeightedFrequencies<T> {
+        Default::default()
+    }
+
+    /// Create a new frequency table with room for `capacity` distinct
+    /// samples before reallocating.
+    pub fn with_capacity(capacity: usize) -> WeightedFrequencies<T> {
+        WeightedFrequencies { data: HashMap::with_capacity(capacity) }
+    }
+}
+
+impl<T: Eq + Hash, S: BuildHasher> WeightedFrequencies<T, S> {
+    /// Create a new frequency table with no samples that hashes elements
+    /// with `hasher`.
+    pub fn with_hasher(hasher: S) -> WeightedFrequencies<T, S> {
+        WeightedFrequencies { data: HashMap::with_hasher(hasher) }
+    }
+
+    /// Create a new frequency table with room for `capacity` distinct
+    /// samples before reallocating, that hashes elements with `hasher`.
+    pub fn with_capacity_and_hasher(
+        capacity: usize,
+        hasher: S,
+    ) -> WeightedFrequencies<T, S> {
+        let data = HashMap::with_capacity_and_hasher(capacity, hasher);
+        WeightedFrequencies { data }
+    }
+
+    /// Add a sample with the given weight to the frequency table.
+    pub fn add(&mut self, v: T, weight: f64) {
+        *self.data.entry(v).or_insert(0.0) += weight;
+    }
+
+    /// Return the total weight of `v` in the data.
+    pub fn weight(&self, v: &T) -> f64 {
+        self.data.get(v).cloned().unwrap_or(0.0)
+    }
+
+    /// Return the total weight of all samples.
+    pub fn total_weight(&self) -> f64 {
+        self.data.values().sum()
+    }
+
+    /// Returns the element with the largest total weight if one exists.
+    ///
+    /// Like `Frequencies::mode`, `None` is returned if two elements tie for
+    /// the largest weight.
+    pub fn mode(&self) -> Option<&T> {
+        let weights = self.most_frequent();
+        if weights.is_empty()
+           || (weights.len() >= 2 && weights[0].1 == weights[1].1) {
+            None
+        } else {
+            Some(weights[0].0)
+        }
+    }
+
+    /// Return a `Vec` of elements and their corresponding total weights in
+    /// descending order.
+    pub fn most_frequent(&self) -> Vec<(&T, f64)> {
+        let mut weights: Vec<_> = self.data.iter()
+                                           .map(|(k, &w)| (k, w))
+                                           .collect();
+        weights.sort_by_key(|&(_, w)| Reverse(Partial(w)));
+        weights
+    }
+
+    /// Return each distinct element with its weight divided by the total
+    /// weight.
+    ///
+    /// Elements are returned in an arbitrary order.
+    pub fn probabilities(&self) -> Vec<(&T, f64)> {
+        let total = self.total_weight();
+        self.data.iter().map(|(k, &w)| (k, w / total)).collect()
+    }
+
+    /// Returns the number of distinct elements.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns true if there are no elements.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+impl<T: Eq + Hash, S: BuildHasher> Commute for WeightedFrequencies<T, S> {
+    fn merge(&mut self, v: WeightedFrequencies<T, S>) {
+        for (k, w2) in v.data {
+            match self.data.entry(k) {
+                Entry::Vacant(w1) => { w1.insert(w2); }
+                Entry::Occupied(mut w1) => { *w1.get_mut() += w2; }
+            }
+        }
+    }
+}
+
+impl<T, S> Default for WeightedFrequencies<T, S>
+        where T: Eq + Hash, S: BuildHasher + Default {
+    fn default() -> WeightedFrequencies<T, S> {
+        WeightedFrequencies { data: HashMap::default() }
+    }
+}
+
+impl<T, S> FromIterator<(T, f64)> for WeightedFrequencies<T, S>
+        where T: Eq + Hash, S: BuildHasher + Default {
+    fn from_iter<I>(it: I) -> WeightedFrequencies<T, S>
+            where I: IntoIterator<Item=(T, f64)> {
+        let mut v = WeightedFrequencies::default();
+        v.extend(it);
+        v
+    }
+}
+
+impl<T, S> Extend<(T, f64)> for WeightedFrequencies<T, S>
+        where T: Eq + Hash, S: BuildHasher {
+    fn extend<I: IntoIterator<Item=(T, f64)>>(&mut self, it: I) {
+        for (v, weight) in it {
+            self.add(v, weight);
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::Frequencies;
+    use std::collections::HashMap;
+    use std::hash::BuildHasherDefault;
+
+    #[cfg(feature = "serde")]
+    use serde_json;
+
+    use {Commute, DecodeError};
+    use stable_hash::StableHasher;
+    use super::{
+        Frequencies, JointFrequencies, SumByKey, WeightedFrequencies,
+    };
+
+    type Stable = BuildHasherDefault<StableHasher>;
+
+    #[test]
+    fn modes_bimodal() {
+        let counts: Frequencies<u32> =
+            vec![1, 2, 2, 3, 3, 4].into_iter().collect();
+        let mut modes = counts.modes();
+        modes.sort();
+        assert_eq!(modes, vec![&2, &3]);
+        assert_eq!(counts.mode(), None);
+    }
+
+    #[test]
+    fn modes_unimodal() {
+        let counts: Frequencies<u32> =
+            vec![1, 2, 2, 3].into_iter().collect();
+        assert_eq!(counts.modes(), vec![&2]);
+        assert!(Frequencies::<u32>::new().modes().is_empty());
+    }
+
+    #[test]
+    fn iter() {
+        let counts: Frequencies<u32> =
+            vec![1, 1, 2, 3, 3, 3].into_iter().collect();
+        let map: HashMap<&u32, u64> = counts.iter().collect();
+        assert_eq!(map.len(), 3);
+        for (v, c) in map {
+            assert_eq!(c, counts.count(v));
+        }
+    }
+
+    #[test]
+    fn quantile() {
+        // 10 samples: 1 x1, 2 x2, 3 x3, 4 x4.
+        let mut counts = Frequencies::new();
+        for x in 1..5u32 {
+            for _ in 0..x {
+                counts.add(x);
+            }
+        }
+        assert_eq!(counts.quantile(0.0), Some(&1));
+        assert_eq!(counts.quantile(0.1), Some(&1));
+        assert_eq!(counts.quantile(0.11), Some(&2));
+        assert_eq!(counts.quantile(0.3), Some(&2));
+        assert_eq!(counts.quantile(0.5), Some(&3));
+        assert_eq!(counts.quantile(0.6), Some(&3));
+        assert_eq!(counts.quantile(0.61), Some(&4));
+        assert_eq!(counts.quantile(1.0), Some(&4));
+        assert_eq!(counts.quantile(1.5), None);
+        assert_eq!(Frequencies::<u32>::new().quantile(0.5), None);
+    }
+
+    #[test]
+    fn quantile_exact_rank() {
+        // `0.28 * 25` is slightly more than `7`, which must not round up to
+        // the 8th sample.
+        let mut counts = Frequencies::new();
+        counts.extend(vec![1u32; 7]);
+        counts.extend(vec![2u32; 18]);
+        assert_eq!(counts.quantile(0.28), Some(&1));
+        assert_eq!(counts.quantile(0.29), Some(&2));
+
+        // With 100 distinct samples, the `k`th percentile is the `k`th
+        // smallest sample, however `q` is computed.
+        let counts: Frequencies<u32> = (1..101).collect();
+        for k in 1..101u32 {
+            assert_eq!(counts.quantile(k as f64 / 100.0), Some(&k));
+            let q = (k as f64 * 0.01).min(1.0);
+            assert_eq!(counts.quantile(q), Some(&k), "q = {}", q);
+        }
+
+        // The tolerance doesn't merge ranks that really are distinct, even
+        // with a huge total.
+        let mut counts = Frequencies::new();
+        counts.extend(vec![1u32, 2, 3]);
+        let big = 1u64 << 40;
+        *counts.data.get_mut(&1).unwrap() = big - 1;
+        *counts.data.get_mut(&2).unwrap() = 1;
+        *counts.data.get_mut(&3).unwrap() = big;
+        // `q * total` is `big - 3/4`, so the rank is `big`, which is past
+        // the last `1`.
+        let q = (big as f64 - 0.75) / (2 * big) as f64;
+        assert_eq!(counts.quantile(q), Some(&2));
+        let q = (big - 1) as f64 / (2 * big) as f64;
+        assert_eq!(counts.quantile(q), Some(&1));
+    }
+
+    #[test]
+    fn top_k() {
+        let mut counts = Frequencies::new();
+        for i in 1..20u32 {
+            for _ in 0..i {
+                counts.add(i);
+            }
+        }
+        let all = counts.most_frequent();
+        for k in 0..25 {
+            let top = counts.top_k(k);
+            assert_eq!(&top[..], &all[..k.min(all.len())]);
+        }
+    }
+
+    #[test]
+    fn remove() {
+        let mut counts: Frequencies<u32> =
+            vec![1, 1, 2].into_iter().collect();
+        assert!(counts.remove(&1));
+        assert_eq!(counts.count(&1), 1);
+        assert_eq!(counts.cardinality(), 2);
+        assert!(counts.remove(&1));
+        assert_eq!(counts.count(&1), 0);
+        assert_eq!(counts.cardinality(), 1);
+        assert!(!counts.remove(&1));
+        assert!(!counts.remove(&5));
+        assert!(counts.remove(&2));
+        assert!(counts.is_empty());
+    }
+
+    #[test]
+    fn probabilities() {
+        let counts: Frequencies<u32> =
+            vec![1, 1, 1, 2, 2, 3, 3, 3, 3, 3].into_iter().collect();
+        let probs = counts.probabilities();
+        assert_eq!(probs.len(), 3);
+        let total: f64 = probs.iter().map(|&(_, p)| p).sum();
+        assert!((total - 1.0).abs() < 1e-12);
+        for (v, p) in probs {
+            let expected = match *v { 1 => 0.3, 2 => 0.2, _ => 0.5 };
+            assert!((p - expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn many_small_tables() {
+        let tables: Vec<Frequencies<u32>> = (0..10_000u32)
+            .map(|i| vec![i % 3, i % 5].into_iter().collect())
+            .collect();
+        assert!(tables.iter().all(|t| t.len() <= 2));
+        assert!(Frequencies::<u32>::new().is_empty());
+        let mut merged = Frequencies::new();
+        merged.consume(tables.into_iter());
+        assert_eq!(merged.len(), 5);
+        assert_eq!(merged.count(&0), 3334 + 2000);
+        assert_eq!(merged.count(&4), 2000);
+    }
+
+    #[test]
+    fn entropy_uniform() {
+        let counts: Frequencies<u32> =
+            (0..8).flat_map(|i| vec![i; 5]).collect();
+        assert!((counts.entropy() - 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn entropy_degenerate() {
+        let counts: Frequencies<u32> = vec![7; 10].into_iter().collect();
+        assert_eq!(counts.entropy(), 0.0);
+        assert_eq!(Frequencies::<u32>::new().entropy(), 0.0);
+    }
 
     #[test]
     fn ranked() {
         let mut counts = Frequencies::new();
-        counts.extend(vec![1usize, 1, 2, 2, 2, 2, 2, 3, 4, 4, 4].into_iter());
+        counts.extend(vec![1usize, 1, 2, 2, 2, 2, 2, 3, 4, 4, 4]);
         assert_eq!(counts.most_frequent()[0], (&2, 5));
         assert_eq!(counts.least_frequent()[0], (&3, 1));
     }
+
+    #[test]
+    fn most_frequent_sorted() {
+        let data = vec!["d", "b", "a", "c", "b", "d", "e", "a", "c", "e"];
+        let expected = [
+            (&"a", 2), (&"b", 2), (&"c", 2), (&"d", 2), (&"e", 2),
+        ];
+        for n in 0..data.len() {
+            // Rotating the input changes the hash map's insertion order.
+            let mut rotated = data.clone();
+            rotated.rotate_left(n);
+            let mut counts: Frequencies<&str> = rotated.into_iter().collect();
+            counts.add("z");
+            counts.add("z");
+            counts.add("z");
+            let got = counts.most_frequent_sorted();
+            assert_eq!(got[0], (&"z", 3));
+            assert_eq!(&got[1..], &expected[..]);
+        }
+        assert!(Frequencies::<u8>::new().most_frequent_sorted().is_empty());
+    }
+
+    #[test]
+    fn merge_sums_counts() {
+        let mut f1: Frequencies<&str> =
+            vec!["a", "a", "b"].into_iter().collect();
+        let f2: Frequencies<&str> =
+            vec!["a", "b", "b", "b", "c"].into_iter().collect();
+        f1.merge(f2);
+        assert_eq!(f1.count(&"a"), 3);
+        assert_eq!(f1.count(&"b"), 4);
+        assert_eq!(f1.count(&"c"), 1);
+        assert_eq!(f1.cardinality(), 3);
+        assert_eq!(f1.mode(), Some(&"b"));
+    }
+
+    #[test]
+    fn ranked_iter() {
+        let counts: Frequencies<usize> =
+            vec![1usize, 1, 2, 2, 2, 2, 2, 3, 4, 4, 4].into_iter().collect();
+        let most: Vec<_> = counts.most_frequent_iter().collect();
+        assert_eq!(most, counts.most_frequent());
+        let least: Vec<_> = counts.least_frequent_iter().collect();
+        assert_eq!(least, counts.least_frequent());
+    }
+
+    #[test]
+    fn with_capacity() {
+        let mut counts = Frequencies::with_capacity(4);
+        counts.extend(vec![1usize, 1, 2, 3, 3, 3]);
+        assert_eq!(counts.count(&3), 3);
+        assert_eq!(counts.cardinality(), 3);
+        assert_eq!(counts.mode(), Some(&3));
+    }
+
+    #[test]
+    fn with_hasher() {
+        let mut counts = Frequencies::with_hasher(Stable::default());
+        counts.extend(vec![1u64, 1, 2, 3, 3, 3]);
+        assert_eq!(counts.count(&3), 3);
+        assert_eq!(counts.mode(), Some(&3));
+
+        let mut more: Frequencies<u64, Stable> =
+            Frequencies::with_capacity_and_hasher(2, Stable::default());
+        more.extend(vec![2, 2, 2, 2]);
+        counts.merge(more);
+        assert_eq!(counts.mode(), Some(&2));
+        assert_eq!(counts.quantile(0.5), Some(&2));
+
+        let bytes = counts.to_bytes();
+        let got: Frequencies<u64, Stable> =
+            Frequencies::from_bytes(&bytes).unwrap();
+        assert_eq!(got.most_frequent_sorted(), counts.most_frequent_sorted());
+        let collected: Frequencies<u64, Stable> =
+            vec![1, 1, 2].into_iter().collect();
+        assert_eq!(collected.mode(), Some(&1));
+    }
+
+    #[test]
+    fn explicit_constructors() {
+        let mut joint = JointFrequencies::with_hasher(Stable::default());
+        joint.extend(vec![("a", 1), ("a", 1), ("b", 2)]);
+        let other: JointFrequencies<_, _, Stable> =
+            vec![("b", 2)].into_iter().collect();
+        joint.merge(other);
+        assert_eq!(joint.count(&"b", &2), 2);
+        assert_eq!(joint.len(), 4);
+
+        let mut sums = SumByKey::with_capacity(2);
+        sums.extend(vec![("a", 2), ("b", 5), ("a", 4)]);
+        assert_eq!(sums.sum(&"a"), 6);
+        let mut sums = SumByKey::with_hasher(Stable::default());
+        sums.extend(vec![("a", 2), ("b", 5), ("a", 4)]);
+        let mut more =
+            SumByKey::with_capacity_and_hasher(1, Stable::default());
+        more.add("b", 3);
+        sums.merge(more);
+        assert_eq!(sums.top_k_by_sum(1), vec![(&"b", 8)]);
+
+        let mut weights = WeightedFrequencies::with_capacity(2);
+        weights.add("a", 0.5);
+        assert_eq!(weights.weight(&"a"), 0.5);
+        let mut weights = WeightedFrequencies::with_hasher(Stable::default());
+        weights.extend(vec![("a", 0.5), ("b", 1.0)]);
+        let mut more = WeightedFrequencies::with_capacity_and_hasher(
+            1, Stable::default());
+        more.add("a", 1.0);
+        weights.merge(more);
+        assert_eq!(weights.mode(), Some(&"a"));
+        assert_eq!(weights.total_weight(), 2.5);
+    }
+
+    #[test]
+    fn top_k_by_sum() {
+        let transactions = vec![
+            ("books", 12.5), ("games", 60.0), ("books", 30.0),
+            ("food", 8.25), ("food", 9.75), ("food", 4.0),
+            ("games", 20.0), ("music", 1.0),
+        ];
+        let sums: SumByKey<&str, f64> = transactions.into_iter().collect();
+        assert_eq!(sums.sum(&"food"), 22.0);
+        assert_eq!(sums.sum(&"toys"), 0.0);
+        assert_eq!(sums.top_k_by_sum(3), vec![
+            (&"games", 80.0), (&"books", 42.5), (&"food", 22.0),
+        ]);
+        assert_eq!(sums.top_k_by_sum(10).len(), 4);
+    }
+
+    #[test]
+    fn sum_by_key_merge() {
+        let mut s1: SumByKey<u8, u64> =
+            vec![(1, 5), (2, 7)].into_iter().collect();
+        let s2: SumByKey<u8, u64> =
+            vec![(2, 3), (3, 1)].into_iter().collect();
+        s1.merge(s2);
+        assert_eq!(s1.len(), 3);
+        assert_eq!(s1.sum(&2), 10);
+        assert_eq!(s1.top_k_by_sum(1), vec![(&2, 10)]);
+    }
+
+    #[test]
+    fn weighted_matches_unweighted() {
+        let data = vec![1, 2, 4, 3, 3, 4, 4, 2, 3, 4];
+        let counts: Frequencies<u32> = data.iter().cloned().collect();
+        let weighted: WeightedFrequencies<u32> =
+            data.iter().map(|&x| (x, 1.0)).collect();
+        assert_eq!(weighted.len(), counts.len());
+        assert_eq!(weighted.mode(), counts.mode());
+        let w: Vec<(&u32, f64)> = weighted.most_frequent();
+        let c: Vec<(&u32, f64)> = counts.most_frequent().into_iter()
+            .map(|(k, c)| (k, c as f64))
+            .collect();
+        assert_eq!(w, c);
+        let mut wp = weighted.probabilities();
+        let mut cp = counts.probabilities();
+        wp.sort_by_key(|&(k, _)| *k);
+        cp.sort_by_key(|&(k, _)| *k);
+        assert_eq!(wp, cp);
+
+        // Integer weights act like repeated samples.
+        let mut packed = WeightedFrequencies::new();
+        packed.add(3, 3.0);
+        packed.add(4, 4.0);
+        packed.add(2, 2.0);
+        packed.add(1, 1.0);
+        assert_eq!(packed.most_frequent(), w);
+    }
+
+    #[test]
+    fn weighted_fractional() {
+        let mut freqs: WeightedFrequencies<&str> =
+            vec![("cat", 0.5), ("dog", 0.25), ("dog", 0.5)]
+                .into_iter().collect();
+        assert_eq!(freqs.mode(), Some(&"dog"));
+        assert_eq!(freqs.weight(&"cat"), 0.5);
+        assert_eq!(freqs.weight(&"bird"), 0.0);
+        freqs.add("cat", 0.25);
+        assert_eq!(freqs.mode(), None);
+        assert!(WeightedFrequencies::<u8>::new().mode().is_none());
+    }
+
+    #[test]
+    fn weighted_merge() {
+        let mut f1: WeightedFrequencies<u32> =
+            vec![(1, 0.5), (2, 2.0)].into_iter().collect();
+        let f2: WeightedFrequencies<u32> =
+            vec![(1, 2.0), (3, 0.25)].into_iter().collect();
+        f1.merge(f2);
+        assert_eq!(f1.len(), 3);
+        assert_eq!(f1.weight(&1), 2.5);
+        assert_eq!(f1.weight(&2), 2.0);
+        assert_eq!(f1.weight(&3), 0.25);
+        assert_eq!(f1.total_weight(), 4.75);
+        assert_eq!(f1.mode(), Some(&1));
+    }
+
+    #[test]
+    fn cramers_v_associated() {
+        let pairs = [("a", 1), ("b", 2), ("c", 3)];
+        let table: JointFrequencies<&str, u32> = pairs.iter()
+            .cycle()
+            .take(300)
+            .cloned()
+            .collect();
+        assert_eq!(table.count(&"b", &2), 100);
+        assert_eq!(table.count(&"b", &3), 0);
+        assert!((table.cramers_v() - 1.0).abs() < 1e-12);
+        assert!(table.cramers_v_corrected() > 0.99);
+    }
+
+    #[test]
+    fn cramers_v_independent() {
+        let mut table = JointFrequencies::new();
+        for _ in 0..10 {
+            for a in 0..3 {
+                for b in 0..4 {
+                    table.add(a, b);
+                }
+            }
+        }
+        assert!(table.cramers_v().abs() < 1e-6);
+        assert_eq!(table.cramers_v_corrected(), 0.0);
+    }
+
+    #[test]
+    fn cramers_v_independent_shapes() {
+        for rows in 2..9u32 {
+            for cols in 2..9u32 {
+                // Every cell is a product of a row weight and a column
+                // weight, so the variables are exactly independent.
+                let mut table = JointFrequencies::new();
+                for a in 0..rows {
+                    for b in 0..cols {
+                        for _ in 0..(a + 1) * (2 * b + 3) {
+                            table.add(a, b);
+                        }
+                    }
+                }
+                let v = table.cramers_v();
+                assert!((0.0..1e-6).contains(&v), "{}x{}: {}", rows, cols, v);
+            }
+        }
+    }
+
+    #[test]
+    fn cramers_v_merge() {
+        let mut t1: JointFrequencies<u8, u8> =
+            vec![(0, 0), (0, 1), (1, 1)].into_iter().collect();
+        let t2: JointFrequencies<u8, u8> =
+            vec![(1, 1), (1, 0), (0, 0), (1, 1)].into_iter().collect();
+        let all: JointFrequencies<u8, u8> = vec![
+            (0, 0), (0, 1), (1, 1), (1, 1), (1, 0), (0, 0), (1, 1),
+        ].into_iter().collect();
+        t1.merge(t2);
+        assert_eq!(t1.len(), 7);
+        assert!((t1.cramers_v() - all.cramers_v()).abs() < 1e-12);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_roundtrip() {
+        let counts: Frequencies<String> = vec!["a", "b", "a", "c", "a"]
+            .into_iter().map(String::from).collect();
+        let json = serde_json::to_string(&counts).unwrap();
+        let mut got: Frequencies<String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(got.mode(), Some(&"a".to_string()));
+        for (v, c) in counts.iter() {
+            assert_eq!(got.count(v), c);
+        }
+        assert_eq!(got.len(), counts.len());
+
+        got.merge(vec!["b".to_string(), "d".to_string()].into_iter().collect());
+        assert_eq!(got.count(&"a".to_string()), 3);
+        assert_eq!(got.count(&"b".to_string()), 2);
+        assert_eq!(got.count(&"d".to_string()), 1);
+    }
+
+    #[test]
+    fn bytes_roundtrip() {
+        let counts: Frequencies<String> = vec!["a", "b", "a", "c", "a"]
+            .into_iter().map(String::from).collect();
+        let got = Frequencies::<String>::from_bytes(&counts.to_bytes())
+            .unwrap();
+        assert_eq!(got.len(), counts.len());
+        for (v, c) in counts.iter() {
+            assert_eq!(got.count(v), c);
+        }
+        let empty = Frequencies::<u32>::new().to_bytes();
+        assert!(Frequencies::<u32>::from_bytes(&empty).unwrap().is_empty());
+    }
+
+    #[test]
+    fn bytes_invalid() {
+        let counts: Frequencies<u32> = vec![1, 2, 2].into_iter().collect();
+        let bytes = counts.to_bytes();
+        for i in 0..bytes.len() {
+            assert!(Frequencies::<u32>::from_bytes(&bytes[..i]).is_err());
+        }
+        // The wrong kind of accumulator.
+        let stats = ::OnlineStats::new().to_bytes();
+        assert_eq!(Frequencies::<u32>::from_bytes(&stats).unwrap_err(),
+                   DecodeError::InvalidHeader);
+        // A count of zero.
+        let mut zero = Frequencies::<u32>::new().to_bytes();
+        zero[6] = 1;
+        zero.extend_from_slice(&[7, 0, 0, 0]);
+        zero.extend_from_slice(&[0; 8]);
+        assert_eq!(Frequencies::<u32>::from_bytes(&zero).unwrap_err(),
+                   DecodeError::InvalidData);
+    }
 }
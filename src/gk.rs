@@ -0,0 +1,289 @@
+use std::fmt;
+use std::iter::IntoIterator;
+
+use num_traits::ToPrimitive;
+
+use Commute;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A commutative data structure for approximate quantiles with a
+/// guaranteed error bound.
+///
+/// This is the Greenwald-Khanna summary. For a summary of `n` samples with
+/// error `epsilon`, the rank of the value returned by `query(q)` is within
+/// `epsilon * n` of `q * n`. The summary keeps a sorted list of samples,
+/// each annotated with bounds on its rank, and periodically discards
+/// samples whose neighbors already bound their rank tightly enough.
+///
+/// Two summaries are merged by combining their rank bounds and then
+/// compressing the result, which preserves the larger of the two error
+/// bounds.
+///
+/// See: Greenwald and Khanna, "Space-Efficient Online Computation of
+/// Quantile Summaries", 2001.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GKSummary {
+    epsilon: f64,
+    len: u64,
+    tuples: Vec<Tuple>,
+}
+
+/// A sample, along with the difference between its minimum rank and the
+/// minimum rank of its predecessor (`g`) and the difference between its
+/// maximum and minimum rank (`delta`).
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct Tuple {
+    v: f64,
+    g: u64,
+    delta: u64,
+}
+
+impl GKSummary {
+    /// Create an empty summary with the error bound `epsilon`.
+    ///
+    /// This panics if `epsilon` is not in `(0, 1)`.
+    pub fn new(epsilon: f64) -> GKSummary {
+        assert!(epsilon > 0.0 && epsilon < 1.0,
+                "epsilon must be in (0, 1), got {}", epsilon);
+        GKSummary { epsilon, len: 0, tuples: vec![] }
+    }
+
+    /// Add a sample to the summary.
+    ///
+    /// `NaN` samples are ignored.
+    pub fn add<T: ToPrimitive>(&mut self, sample: T) {
+        let x = sample.to_f64().unwrap();
+        if x.is_nan() {
+            return;
+        }
+        let i = self.tuples.partition_point(|t| t.v <= x);
+        // A new minimum or maximum has an exact rank. Otherwise, the rank is
+        // as uncertain as the rank of the next sample.
+        let delta = match self.tuples.get(i) {
+            Some(next) if i > 0 => next.g + next.delta - 1,
+            _ => 0,
+        };
+        self.tuples.insert(i, Tuple { v: x, g: 1, delta });
+        self.len += 1;
+        let period = (1.0 / (2.0 * self.epsilon)).floor().max(1.0) as u64;
+        if self.len.is_multiple_of(period) {
+            self.compress();
+        }
+    }
+
+    /// Returns a value whose rank is within `epsilon * n` of `q * n`, where
+    /// `q` is in `[0, 1]`.
+    ///
+    /// If the summary is empty or `q` is not in `[0, 1]`, then `NaN` is
+    /// returned.
+    pub fn query(&self, q: f64) -> f64 {
+        if self.tuples.is_empty() || !(0.0..=1.0).contains(&q) {
+            return f64::NAN;
+        }
+        let n = self.len as f64;
+        // Ranks start at `1`.
+        let (target, bound) = ((q * n).ceil().max(1.0), self.epsilon * n);
+        let mut rmin = 0;
+        for t in &self.tuples {
+            rmin += t.g;
+            let rmax = rmin + t.delta;
+            if target - (rmin as f64) <= bound
+               && (rmax as f64) - target <= bound {
+                return t.v;
+            }
+        }
+        self.tuples[self.tuples.len() - 1].v
+    }
+
+    /// Returns a value whose rank is within `epsilon * n` of `q * n`, where
+    /// `q` is in `[0, 1]`.
+    ///
+    /// This is like `query`, except `None` is returned instead of `NaN`
+    /// when the summary is empty or `q` is not in `[0, 1]`.
+    pub fn try_query(&self, q: f64) -> Option<f64> {
+        let x = self.query(q);
+        if x.is_nan() { None } else { Some(x) }
+    }
+
+    /// Returns the error bound.
+    pub fn epsilon(&self) -> f64 {
+        self.epsilon
+    }
+
+    /// Returns the number of samples.
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Returns true if there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Merge each sample into its successor when the combined rank bounds
+    /// stay within `2 * epsilon * n`. The minimum and maximum are kept.
+    fn compress(&mut self) {
+        if self.tuples.len() < 3 {
+            return;
+        }
+        let threshold = (2.0 * self.epsilon * self.len as f64).floor() as u64;
+        let mut tuples = std::mem::take(&mut self.tuples).into_iter();
+        let first = tuples.next().unwrap();
+        let mut kept: Vec<Tuple> = vec![tuples.next_back().unwrap()];
+        for t in tuples.rev() {
+            let next = kept.last_mut().unwrap();
+            if t.g + next.g + next.delta <= threshold {
+                next.g += t.g;
+            } else {
+                kept.push(t);
+            }
+        }
+        kept.push(first);
+        kept.reverse();
+        self.tuples = kept;
+    }
+
+    /// Returns each sample with its minimum and maximum rank.
+    fn ranks(&self) -> Vec<(f64, u64, u64)> {
+        let mut rmin = 0;
+        self.tuples.iter().map(|t| {
+            rmin += t.g;
+            (t.v, rmin, rmin + t.delta)
+        }).collect()
+    }
+}
+
+impl Commute for GKSummary {
+    fn merge(&mut self, v: GKSummary) {
+        let epsilon = self.epsilon.max(v.epsilon);
+        if v.is_empty() {
+            self.epsilon = epsilon;
+            return;
+        } else if self.is_empty() {
+            *self = v;
+            self.epsilon = epsilon;
+            return;
+        }
+        // See: Agarwal et al., "Mergeable Summaries", 2012. A sample's rank
+        // in the union is its rank in its own summary plus its rank among
+        // the samples of the other summary, which is bounded by the ranks
+        // of its neighbors there.
+        let (a, b) = (self.ranks(), v.ranks());
+        let (na, nb) = (self.len, v.len);
+        let mut merged = Vec::with_capacity(a.len() + b.len());
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            let from_a = j == b.len() || (i < a.len() && a[i].0 <= b[j].0);
+            let (x, rmin, rmax, other, k, n) = if from_a {
+                i += 1;
+                (a[i - 1].0, a[i - 1].1, a[i - 1].2, &b, j, nb)
+            } else {
+                j += 1;
+                (b[j - 1].0, b[j - 1].1, b[j - 1].2, &a, i, na)
+            };
+            let rmin_other = if k > 0 { other[k - 1].1 } else { 0 };
+            let rmax_other = if k < other.len() { other[k].2 - 1 } else { n };
+            merged.push((x, rmin + rmin_other, rmax + rmax_other));
+        }
+
+        let mut prev = 0;
+        self.tuples = merged.into_iter().map(|(x, rmin, rmax)| {
+            let g = rmin - prev;
+            prev = rmin;
+            Tuple { v: x, g, delta: rmax - rmin }
+        }).collect();
+        self.len = na + nb;
+        self.epsilon = epsilon;
+        self.compress();
+    }
+}
+
+impl fmt::Debug for GKSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "GKSummary(len: {}, median ~ {:.10})",
+               self.len, self.query(0.5))
+    }
+}
+
+impl<T: ToPrimitive> Extend<T> for GKSummary {
+    fn extend<I: IntoIterator<Item=T>>(&mut self, it: I) {
+        for sample in it {
+            self.add(sample);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+
+    use merge_all;
+    use super::GKSummary;
+
+    /// A shuffled permutation of `0..n`, so the rank of `i` is `i + 1`.
+    fn data(seed: u64, n: u64) -> Vec<f64> {
+        let mut data: Vec<f64> = (0..n).map(|i| i as f64).collect();
+        data.shuffle(&mut StdRng::seed_from_u64(seed));
+        data
+    }
+
+    fn check(summary: &GKSummary) {
+        let n = summary.len() as f64;
+        for i in 0..101 {
+            let q = i as f64 / 100.0;
+            let rank = summary.query(q) + 1.0;
+            assert!((rank - q * n).abs() <= summary.epsilon() * n + 1.0,
+                    "q: {}, rank: {}, n: {}", q, rank, n);
+        }
+    }
+
+    #[test]
+    fn epsilon_guarantee() {
+        for &epsilon in &[0.1, 0.01, 0.001] {
+            let mut summary = GKSummary::new(epsilon);
+            summary.extend(data(0, 50_000));
+            check(&summary);
+            assert!((summary.tuples.len() as f64) <= 2.0 / epsilon);
+        }
+    }
+
+    #[test]
+    fn merge() {
+        let all = data(1, 50_000);
+        let summaries = all.chunks(7_000).map(|chunk| {
+            let mut summary = GKSummary::new(0.01);
+            summary.extend(chunk.iter().cloned());
+            summary
+        });
+        let merged = merge_all(summaries).unwrap();
+        assert_eq!(merged.len(), 50_000);
+        check(&merged);
+    }
+
+    #[test]
+    fn small() {
+        let mut summary = GKSummary::new(0.01);
+        assert!(summary.query(0.5).is_nan());
+        summary.extend(vec![3.0, 1.0, f64::NAN, 2.0]);
+        assert_eq!(summary.len(), 3);
+        assert_eq!(summary.query(0.0), 1.0);
+        assert_eq!(summary.query(0.5), 2.0);
+        assert_eq!(summary.query(1.0), 3.0);
+        assert!(summary.query(-0.1).is_nan());
+    }
+
+    #[test]
+    fn try_query() {
+        let mut summary = GKSummary::new(0.01);
+        assert_eq!(summary.try_query(0.5), None);
+        summary.extend(vec![3.0, 1.0, 2.0]);
+        assert_eq!(summary.try_query(0.5), Some(2.0));
+        assert_eq!(summary.try_query(-0.1), None);
+    }
+}
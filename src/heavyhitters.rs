@@ -0,0 +1,172 @@
+use std::cmp::Reverse;
+use std::collections::hash_map::{HashMap, Entry};
+use std::fmt;
+use std::hash::Hash;
+use std::iter::IntoIterator;
+
+use Commute;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A commutative summary of the most frequent elements in a stream.
+///
+/// This is the Misra-Gries summary, which keeps at most `k` counters. Every
+/// element that occurs more than `n / (k + 1)` times in a stream of `n`
+/// elements is guaranteed to have a counter, and each counter underestimates
+/// the true count by at most `n / (k + 1)`.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(
+    serialize = "T: Serialize",
+    deserialize = "T: Deserialize<'de> + Eq + Hash",
+)))]
+pub struct HeavyHitters<T> {
+    k: usize,
+    len: u64,
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::entries"))]
+    counters: HashMap<T, u64>,
+}
+
+impl<T: Eq + Hash> HeavyHitters<T> {
+    /// Create an empty summary with at most `k` counters.
+    ///
+    /// This panics if `k` is `0`.
+    pub fn new(k: usize) -> HeavyHitters<T> {
+        assert!(k > 0, "a summary needs at least one counter");
+        HeavyHitters { k, len: 0, counters: HashMap::with_capacity(k) }
+    }
+
+    /// Add an element to the summary.
+    pub fn add(&mut self, v: T) {
+        self.len += 1;
+        if let Some(count) = self.counters.get_mut(&v) {
+            *count += 1;
+            return;
+        }
+        if self.counters.len() < self.k {
+            self.counters.insert(v, 1);
+        } else {
+            // The new element and one occurrence of every tracked element
+            // cancel each other out.
+            self.counters.retain(|_, count| {
+                *count -= 1;
+                *count > 0
+            });
+        }
+    }
+
+    /// Return the candidate heavy hitters and their estimated counts in
+    /// descending order of count.
+    ///
+    /// Elements with equal counts are returned in an arbitrary order.
+    pub fn items(&self) -> Vec<(&T, u64)> {
+        let mut items: Vec<_> = self.counters.iter()
+                                             .map(|(k, &v)| (k, v))
+                                             .collect();
+        items.sort_by_key(|&(_, c)| Reverse(c));
+        items
+    }
+
+    /// Return the estimated count of `v`, which is `0` if `v` isn't tracked.
+    pub fn count(&self, v: &T) -> u64 {
+        self.counters.get(v).cloned().unwrap_or(0)
+    }
+
+    /// Returns the maximum number of counters.
+    pub fn capacity(&self) -> usize {
+        self.k
+    }
+
+    /// Returns the number of elements added to the summary.
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Returns true if no elements have been added.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<T: Eq + Hash> Commute for HeavyHitters<T> {
+    fn merge(&mut self, v: HeavyHitters<T>) {
+        // See: Agarwal et al., "Mergeable Summaries", 2012.
+        self.len += v.len;
+        for (k, c2) in v.counters {
+            match self.counters.entry(k) {
+                Entry::Vacant(c1) => { c1.insert(c2); }
+                Entry::Occupied(mut c1) => { *c1.get_mut() += c2; }
+            }
+        }
+        if self.counters.len() <= self.k {
+            return;
+        }
+        let mut counts: Vec<u64> = self.counters.values().cloned().collect();
+        counts.sort_by_key(|&c| Reverse(c));
+        let cut = counts[self.k];
+        self.counters.retain(|_, count| {
+            *count = count.saturating_sub(cut);
+            *count > 0
+        });
+    }
+}
+
+impl<T: fmt::Debug + Eq + Hash> fmt::Debug for HeavyHitters<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.counters)
+    }
+}
+
+impl<T: Eq + Hash> Extend<T> for HeavyHitters<T> {
+    fn extend<I: IntoIterator<Item=T>>(&mut self, it: I) {
+        for v in it {
+            self.add(v);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use Commute;
+    use super::HeavyHitters;
+
+    /// A stream where `1` is half of the data and `2` is a quarter of the
+    /// data, and the rest is distinct noise.
+    fn skewed(offset: u32, n: u32) -> Vec<u32> {
+        (0..n).map(|i| match i % 4 {
+            0 | 2 => 1,
+            1 => 2,
+            _ => 1000 + offset + i,
+        }).collect()
+    }
+
+    #[test]
+    fn dominant_items() {
+        let mut hh = HeavyHitters::new(4);
+        hh.extend(skewed(0, 10_000));
+        let items = hh.items();
+        assert!(items.len() <= 4);
+        assert_eq!(items[0].0, &1);
+        assert_eq!(items[1].0, &2);
+        // Counts underestimate by at most n / (k + 1).
+        assert!(hh.count(&1) <= 5000 && hh.count(&1) >= 5000 - 2000);
+        assert!(hh.count(&2) <= 2500 && hh.count(&2) >= 2500 - 2000);
+        assert_eq!(hh.len(), 10_000);
+    }
+
+    #[test]
+    fn merge() {
+        let mut h1 = HeavyHitters::new(3);
+        h1.extend(skewed(0, 4000));
+        let mut h2 = HeavyHitters::new(3);
+        h2.extend(skewed(1_000_000, 6000));
+        h1.merge(h2);
+        let items = h1.items();
+        assert!(items.len() <= 3);
+        assert_eq!(items[0].0, &1);
+        assert_eq!(items[1].0, &2);
+        assert!(h1.count(&1) >= 5000 - 2500);
+        assert_eq!(h1.len(), 10_000);
+    }
+}
@@ -0,0 +1,375 @@
+use std::fmt;
+use std::iter::IntoIterator;
+
+use num_traits::ToPrimitive;
+
+use {Commute, MergeError};
+use unsorted::Unsorted;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A commutative histogram with a fixed number of equal width bins.
+///
+/// Samples are tallied into bins spanning `[min, max]`. Each bin is
+/// half-open, except for the last bin, which also includes `max`. Samples
+/// outside of the range are counted separately.
+///
+/// Since a histogram is just a vector of counts, adding a sample is `O(1)`
+/// and merging two histograms is a matter of adding their counts.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Histogram {
+    min: f64,
+    max: f64,
+    counts: Vec<u64>,
+    underflow: u64,
+    overflow: u64,
+}
+
+impl Histogram {
+    /// Create an empty histogram with `nbins` bins spanning `[min, max]`.
+    ///
+    /// This panics if `nbins` is `0` or if `min` is not less than `max`.
+    pub fn new(min: f64, max: f64, nbins: usize) -> Histogram {
+        assert!(nbins > 0, "a histogram must have at least one bin");
+        assert!(min < max, "histogram range must satisfy min < max");
+        Histogram {
+            min,
+            max,
+            counts: vec![0; nbins],
+            underflow: 0,
+            overflow: 0,
+        }
+    }
+
+    /// Create a histogram from a sample, choosing the number of bins
+    /// automatically.
+    ///
+    /// The histogram spans the range of the sample. The bin width is chosen
+    /// with the Freedman-Diaconis rule, `2 * IQR / n^(1/3)`, which is robust
+    /// to outliers. If the interquartile range is zero, then Sturges' rule,
+    /// `ceil(log2(n)) + 1` bins, is used instead. Either way, there are never
+    /// more bins than samples, so a distant outlier can't blow up the number
+    /// of bins.
+    ///
+    /// `NaN` and infinite samples are ignored. If there are no other samples,
+    /// or if every sample is equal, then the histogram has a single bin.
+    pub fn from_sample<T, I>(it: I) -> Histogram
+            where T: ToPrimitive, I: IntoIterator<Item=T> {
+        let data: Vec<f64> = it.into_iter()
+            .map(|x| x.to_f64().unwrap())
+            .filter(|x| x.is_finite())
+            .collect();
+        let mut sorted: Unsorted<f64> = data.iter().cloned().collect();
+        let (min, max) = match (sorted.quantile(0.0), sorted.quantile(1.0)) {
+            (Some(min), Some(max)) => (min, max),
+            _ => return Histogram::new(0.0, 1.0, 1),
+        };
+        if min == max {
+            let mut hist = Histogram::new(min - 0.5, max + 0.5, 1);
+            hist.counts[0] = data.len() as u64;
+            return hist;
+        }
+        let n = data.len() as f64;
+        let iqr = sorted.iqr().unwrap();
+        let nbins = if iqr > 0.0 {
+            let width = 2.0 * iqr / n.cbrt();
+            ((max - min) / width).ceil()
+        } else {
+            n.log2().ceil() + 1.0
+        };
+        let nbins = (nbins.min(n) as usize).max(1);
+        let mut hist = Histogram::new(min, max, nbins);
+        hist.extend(data);
+        hist
+    }
+
+    /// Add a sample to the histogram.
+    ///
+    /// `NaN` samples are ignored.
+    pub fn add<T: ToPrimitive>(&mut self, sample: T) {
+        let x = sample.to_f64().unwrap();
+        if x.is_nan() {
+            return;
+        }
+        if x < self.min {
+            self.underflow += 1;
+        } else if x > self.max {
+            self.overflow += 1;
+        } else {
+            let nbins = self.counts.len();
+            let bin = ((x - self.min) / self.width()) as usize;
+            self.counts[bin.min(nbins - 1)] += 1;
+        }
+    }
+
+    /// Returns the counts of each bin, in ascending order.
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    /// Returns the `nbins + 1` edges of the bins, in ascending order.
+    ///
+    /// Bin `i` spans `[edges[i], edges[i + 1])`.
+    pub fn bin_edges(&self) -> Vec<f64> {
+        let nbins = self.counts.len();
+        let width = self.width();
+        (0..nbins + 1).map(|i| {
+            if i == nbins { self.max } else { self.min + (i as f64) * width }
+        }).collect()
+    }
+
+    /// Returns the number of samples less than `min`.
+    pub fn underflow(&self) -> u64 {
+        self.underflow
+    }
+
+    /// Returns the number of samples greater than `max`.
+    pub fn overflow(&self) -> u64 {
+        self.overflow
+    }
+
+    /// Returns the width of every bin.
+    pub fn width(&self) -> f64 {
+        (self.max - self.min) / (self.counts.len() as f64)
+    }
+
+    /// Returns an approximation of the `q`th quantile, where `q` is in
+    /// `[0, 1]`.
+    ///
+    /// The bin containing the quantile is found from the cumulative bin
+    /// counts, and samples are assumed to be uniformly distributed within
+    /// that bin. For samples within the histogram's range, the result is
+    /// therefore within one bin width of the exact quantile. Quantiles that
+    /// fall among the samples outside of the range are reported as `min` or
+    /// `max`.
+    ///
+    /// If the histogram is empty or `q` is not in `[0, 1]`, then `NaN` is
+    /// returned.
+    pub fn approx_quantile(&self, q: f64) -> f64 {
+        if self.is_empty() || !(0.0..=1.0).contains(&q) {
+            return f64::NAN;
+        }
+        let rank = q * (self.len() as f64);
+        let mut cumulative = self.underflow as f64;
+        if self.underflow > 0 && rank <= cumulative {
+            return self.min;
+        }
+        let width = self.width();
+        for (i, &count) in self.counts.iter().enumerate() {
+            let count = count as f64;
+            if count > 0.0 && rank <= cumulative + count {
+                let lo = self.min + (i as f64) * width;
+                return lo + width * ((rank - cumulative) / count);
+            }
+            cumulative += count;
+        }
+        self.max
+    }
+
+    /// Returns an approximation of the `q`th quantile, where `q` is in
+    /// `[0, 1]`.
+    ///
+    /// This is like `approx_quantile`, except `None` is returned instead of
+    /// `NaN` when the histogram is empty or `q` is not in `[0, 1]`.
+    pub fn try_approx_quantile(&self, q: f64) -> Option<f64> {
+        let x = self.approx_quantile(q);
+        if x.is_nan() { None } else { Some(x) }
+    }
+
+    /// Returns the number of samples, including those outside of the range.
+    pub fn len(&self) -> usize {
+        let inside: u64 = self.counts.iter().sum();
+        (inside + self.underflow + self.overflow) as usize
+    }
+
+    /// Returns true if there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Commute for Histogram {
+    fn merge(&mut self, v: Histogram) {
+        assert!(self.min == v.min && self.max == v.max
+                && self.counts.len() == v.counts.len(),
+                "cannot merge histograms with different bins");
+        for (c1, c2) in self.counts.iter_mut().zip(v.counts) {
+            *c1 += c2;
+        }
+        self.underflow += v.underflow;
+        self.overflow += v.overflow;
+    }
+
+    fn check_merge(&self, v: &Histogram) -> Result<(), MergeError> {
+        if self.min != v.min || self.max != v.max
+           || self.counts.len() != v.counts.len() {
+            return Err(MergeError::parameter_mismatch("histograms"));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Histogram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{:?}, {:?}] {:?}", self.min, self.max, self.counts)
+    }
+}
+
+impl<T: ToPrimitive> Extend<T> for Histogram {
+    fn extend<I: IntoIterator<Item=T>>(&mut self, it: I) {
+        for sample in it {
+            self.add(sample);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use Commute;
+    use unsorted::median;
+    use super::Histogram;
+
+    #[test]
+    fn approx_median() {
+        let data: Vec<f64> = (0..1000)
+            .map(|i| ((i * 7919) % 1000) as f64 / 10.0)
+            .map(|x| x * x / 100.0)
+            .collect();
+        let mut hist = Histogram::new(0.0, 100.0, 50);
+        hist.extend(data.iter().cloned());
+        let exact = median(data.into_iter()).unwrap();
+        let approx = hist.approx_quantile(0.5);
+        assert!((exact - approx).abs() <= hist.width(),
+                "exact: {}, approx: {}", exact, approx);
+    }
+
+    #[test]
+    fn approx_quantile_bounds() {
+        let mut hist = Histogram::new(0.0, 10.0, 10);
+        assert!(hist.approx_quantile(0.5).is_nan());
+        hist.extend(vec![-1.0, 2.5, 11.0]);
+        assert_eq!(hist.approx_quantile(0.0), 0.0);
+        assert_eq!(hist.approx_quantile(0.5), 2.5);
+        assert_eq!(hist.approx_quantile(1.0), 10.0);
+        assert!(hist.approx_quantile(1.5).is_nan());
+    }
+
+    #[test]
+    fn try_approx_quantile() {
+        let mut hist = Histogram::new(0.0, 10.0, 10);
+        assert_eq!(hist.try_approx_quantile(0.5), None);
+        hist.add(2.5);
+        assert_eq!(hist.try_approx_quantile(0.5), Some(2.5));
+        assert_eq!(hist.try_approx_quantile(-0.5), None);
+    }
+
+    #[test]
+    fn from_sample() {
+        // A sum of uniform variates is approximately normal.
+        let mut rng = StdRng::seed_from_u64(0);
+        let data: Vec<f64> = (0..1000)
+            .map(|_| (0..12).map(|_| rng.gen::<f64>()).sum::<f64>() - 6.0)
+            .collect();
+        let hist = Histogram::from_sample(data.iter().cloned());
+        let nbins = hist.counts().len();
+        assert!((10..=40).contains(&nbins), "nbins: {}", nbins);
+        assert_eq!(hist.len(), 1000);
+        assert_eq!(hist.underflow(), 0);
+        assert_eq!(hist.overflow(), 0);
+    }
+
+    #[test]
+    fn from_sample_degenerate() {
+        let hist = Histogram::from_sample(vec![3.0; 10]);
+        assert_eq!(hist.counts(), &[10]);
+        let hist = Histogram::from_sample(vec![1.0, 2.0, 2.0, 2.0, 2.0, 3.0]);
+        assert_eq!(hist.counts().len(), 4);
+        assert_eq!(hist.len(), 6);
+        assert!(Histogram::from_sample(Vec::<f64>::new()).is_empty());
+    }
+
+    #[test]
+    fn from_sample_outlier() {
+        let mut data: Vec<f64> =
+            (0..1000).map(|i| ((i * 7919) % 1000) as f64 / 1000.0).collect();
+        data.push(1e7);
+        let hist = Histogram::from_sample(data.iter().cloned());
+        assert!(hist.counts().len() <= data.len(),
+                "nbins: {}", hist.counts().len());
+        assert_eq!(hist.len(), 1001);
+        assert_eq!(hist.counts()[hist.counts().len() - 1], 1);
+    }
+
+    #[test]
+    fn from_sample_infinite() {
+        let data = vec![
+            1.0, f64::INFINITY, 2.0, 3.0, f64::NEG_INFINITY, 4.0, f64::NAN,
+        ];
+        let hist = Histogram::from_sample(data);
+        assert_eq!(hist.len(), 4);
+        assert_eq!(hist.bin_edges()[0], 1.0);
+        assert_eq!(hist.bin_edges()[hist.counts().len()], 4.0);
+        let hist = Histogram::from_sample(vec![f64::INFINITY; 3]);
+        assert!(hist.is_empty());
+    }
+
+    #[test]
+    fn bin_boundaries() {
+        let mut hist = Histogram::new(0.0, 4.0, 4);
+        assert_eq!(hist.bin_edges(), vec![0.0, 1.0, 2.0, 3.0, 4.0]);
+        hist.extend(vec![0.0, 1.0, 2.0, 2.999, 3.0, 4.0]);
+        assert_eq!(hist.counts(), &[1, 1, 2, 2]);
+        assert_eq!(hist.underflow(), 0);
+        assert_eq!(hist.overflow(), 0);
+    }
+
+    #[test]
+    fn out_of_range() {
+        let mut hist = Histogram::new(0.0, 4.0, 4);
+        hist.extend(vec![-0.001, -10.0, 4.001, 2.0, 100.0, 7.0]);
+        assert_eq!(hist.counts(), &[0, 0, 1, 0]);
+        assert_eq!(hist.underflow(), 2);
+        assert_eq!(hist.overflow(), 3);
+        assert_eq!(hist.len(), 6);
+    }
+
+    #[test]
+    fn merge() {
+        let mut h1 = Histogram::new(0.0, 4.0, 4);
+        h1.extend(vec![0.5, 1.5, 1.5]);
+        let mut h2 = Histogram::new(0.0, 4.0, 4);
+        h2.extend(vec![3.5, 1.5]);
+        h1.merge(h2);
+        assert_eq!(h1.counts(), &[1, 3, 0, 1]);
+        assert_eq!(h1.len(), 5);
+    }
+
+    #[test]
+    fn try_merge_different_bins() {
+        let mut h1 = Histogram::new(0.0, 4.0, 4);
+        h1.add(1.0);
+        for h2 in [
+            Histogram::new(0.0, 4.0, 5),
+            Histogram::new(0.0, 5.0, 4),
+            Histogram::new(-1.0, 4.0, 4),
+        ] {
+            let err = h1.try_merge(h2).unwrap_err();
+            assert_eq!(err.to_string(),
+                       "cannot merge histograms with different parameters");
+        }
+        assert_eq!(h1.len(), 1);
+        assert_eq!(h1.try_merge(Histogram::new(0.0, 4.0, 4)), Ok(()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn merge_different_bins() {
+        let mut h1 = Histogram::new(0.0, 4.0, 4);
+        h1.merge(Histogram::new(0.0, 4.0, 5));
+    }
+}
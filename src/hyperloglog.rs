@@ -0,0 +1,194 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use {Commute, MergeError};
+use stable_hash::StableHasher;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A commutative estimator of the number of distinct elements in a stream.
+///
+/// This is the HyperLogLog algorithm of Flajolet et al. It uses `2^p`
+/// one-byte registers, where `p` is the precision, no matter how many
+/// distinct elements there are. The relative standard error of the estimate
+/// is about `1.04 / sqrt(2^p)`, e.g., about `0.8%` for a precision of `14`.
+///
+/// Elements are hashed with an algorithm that is fixed by this crate, not
+/// with the standard library's `DefaultHasher`, which may change between
+/// Rust releases. So estimators with the same precision can be merged even
+/// if they were built in different places, by different builds or on
+/// different platforms, as long as the elements' `Hash` implementations
+/// agree.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HyperLogLog {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    /// Create an empty estimator with `2^precision` registers.
+    ///
+    /// This panics if `precision` is not in `[4, 16]`.
+    pub fn new(precision: u8) -> HyperLogLog {
+        assert!((4..=16).contains(&precision),
+                "precision must be in [4, 16], got {}", precision);
+        HyperLogLog {
+            precision,
+            registers: vec![0; 1 << precision],
+        }
+    }
+
+    /// Add an element.
+    pub fn add<T: Hash + ?Sized>(&mut self, v: &T) {
+        let mut hasher = StableHasher::new();
+        v.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let p = self.precision as u32;
+        let index = (hash >> (64 - p)) as usize;
+        // The guard bit caps the rank when the remaining bits are all zero.
+        let rest = (hash << p) | (1 << (p - 1));
+        let rank = (rest.leading_zeros() + 1) as u8;
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+        }
+    }
+
+    /// Returns the estimated number of distinct elements.
+    pub fn estimate(&self) -> f64 {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let mut sum = 0.0;
+        let mut zeros = 0;
+        for &r in &self.registers {
+            sum += 1.0 / ((1u64 << r) as f64);
+            if r == 0 {
+                zeros += 1;
+            }
+        }
+        let estimate = alpha * m * m / sum;
+        // The raw estimate is biased for small cardinalities, where linear
+        // counting of the empty registers does much better.
+        if estimate <= 2.5 * m && zeros > 0 {
+            m * (m / (zeros as f64)).ln()
+        } else {
+            estimate
+        }
+    }
+
+    /// Returns the precision, i.e., the base 2 logarithm of the number of
+    /// registers.
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+}
+
+impl Commute for HyperLogLog {
+    fn merge(&mut self, v: HyperLogLog) {
+        assert_eq!(self.precision, v.precision,
+                   "cannot merge estimators with different precisions");
+        for (r1, r2) in self.registers.iter_mut().zip(v.registers) {
+            if r2 > *r1 {
+                *r1 = r2;
+            }
+        }
+    }
+
+    fn check_merge(&self, v: &HyperLogLog) -> Result<(), MergeError> {
+        if self.precision != v.precision {
+            return Err(MergeError::parameter_mismatch("estimators"));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for HyperLogLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "~{:.0}", self.estimate())
+    }
+}
+
+impl<'a, T: 'a + Hash + ?Sized> Extend<&'a T> for HyperLogLog {
+    fn extend<I: IntoIterator<Item=&'a T>>(&mut self, it: I) {
+        for v in it {
+            self.add(v);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {Commute, MergeErrorKind};
+    use super::HyperLogLog;
+
+    fn assert_close(estimate: f64, actual: f64, tolerance: f64) {
+        let err = (estimate - actual).abs() / actual;
+        assert!(err < tolerance,
+                "estimate {} is not within {} of {}",
+                estimate, tolerance, actual);
+    }
+
+    #[test]
+    fn large_stream() {
+        let mut hll = HyperLogLog::new(14);
+        for i in 0..200_000u64 {
+            // Every element is added twice.
+            hll.add(&(i % 100_000));
+        }
+        assert_close(hll.estimate(), 100_000.0, 0.03);
+    }
+
+    #[test]
+    fn small_cardinality() {
+        let mut hll = HyperLogLog::new(14);
+        assert_eq!(hll.estimate(), 0.0);
+        hll.extend(&["a", "b", "c", "a", "b"]);
+        assert_close(hll.estimate(), 3.0, 0.01);
+    }
+
+    #[test]
+    fn stable_registers() {
+        // The registers an element updates are part of the serialized
+        // format, so they must not change between builds.
+        let mut hll = HyperLogLog::new(4);
+        hll.extend(&[1u32, 2, 3, 4, 5, 6, 7, 8]);
+        hll.add("a");
+        assert_eq!(hll.registers,
+                   vec![0, 1, 3, 3, 0, 0, 0, 0, 0, 2, 0, 0, 0, 1, 1, 0]);
+    }
+
+    #[test]
+    fn merge() {
+        let mut h1 = HyperLogLog::new(12);
+        h1.extend(&(0..30_000u32).collect::<Vec<_>>());
+        let mut h2 = HyperLogLog::new(12);
+        h2.extend(&(20_000..50_000u32).collect::<Vec<_>>());
+        h1.merge(h2);
+        assert_close(h1.estimate(), 50_000.0, 0.05);
+    }
+
+    #[test]
+    fn try_merge_mismatched() {
+        let mut h1 = HyperLogLog::new(12);
+        h1.add("a");
+        let err = h1.try_merge(HyperLogLog::new(10)).unwrap_err();
+        assert_eq!(err.kind(),
+                   MergeErrorKind::ParameterMismatch("estimators"));
+        assert!(h1.estimate() > 0.0);
+        assert_eq!(h1.try_merge(HyperLogLog::new(12)), Ok(()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn merge_mismatched() {
+        let mut h1 = HyperLogLog::new(12);
+        h1.merge(HyperLogLog::new(10));
+    }
+}
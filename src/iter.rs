@@ -0,0 +1,244 @@
+use std::hash::Hash;
+
+use num_traits::ToPrimitive;
+
+use {Frequencies, MinMax, OnlineStats, Unsorted};
+use minmax;
+
+/// An extension trait for computing statistics directly from iterators.
+///
+/// Each method consumes the iterator and returns the corresponding
+/// accumulator. This is equivalent to calling `collect` with the accumulator
+/// type, but reads better at the end of a chain of iterator adapters.
+///
+/// This trait is implemented for all iterators.
+pub trait StatsIterator: Iterator + Sized {
+    /// Compute the mean, variance and higher moments of the items.
+    ///
+    /// ```
+    /// use stats::StatsIterator;
+    ///
+    /// let stats = vec![1, 2, 3, 4].into_iter().online_stats();
+    /// assert_eq!(stats.mean(), 2.5);
+    /// ```
+    fn online_stats(self) -> OnlineStats where Self::Item: ToPrimitive {
+        self.collect()
+    }
+
+    /// Compute the minimum and maximum of the items.
+    ///
+    /// ```
+    /// use stats::StatsIterator;
+    ///
+    /// let minmax = "stream".chars().minmax();
+    /// assert_eq!(minmax.min(), Some(&'a'));
+    /// assert_eq!(minmax.max(), Some(&'t'));
+    /// ```
+    fn minmax(self) -> MinMax<Self::Item>
+            where Self::Item: PartialOrd + Clone {
+        self.collect()
+    }
+
+    /// Compute the exact frequency of each distinct item.
+    ///
+    /// ```
+    /// use stats::StatsIterator;
+    ///
+    /// let freqs = "a b a c a".split(' ').frequencies();
+    /// assert_eq!(freqs.count(&"a"), 3);
+    /// assert_eq!(freqs.mode(), Some(&"a"));
+    /// ```
+    fn frequencies(self) -> Frequencies<Self::Item>
+            where Self::Item: Eq + Hash {
+        self.collect()
+    }
+
+    /// Collect the items for computing order statistics.
+    ///
+    /// ```
+    /// use stats::StatsIterator;
+    ///
+    /// let mut data = vec![5.0, 1.0, 3.0].into_iter().unsorted();
+    /// assert_eq!(data.median(), Some(3.0));
+    /// ```
+    fn unsorted(self) -> Unsorted<Self::Item> where Self::Item: PartialOrd {
+        self.collect()
+    }
+
+    /// Normalize the items to z-scores with the mean and standard deviation
+    /// of `stats`.
+    ///
+    /// Each item `x` is mapped to `stats.zscore(x)`. Typically, `stats` is
+    /// computed in a first pass over the same data.
+    ///
+    /// ```
+    /// use stats::{OnlineStats, StatsIterator};
+    ///
+    /// let data = vec![2, 4, 4, 4, 5, 5, 7, 9];
+    /// let stats = OnlineStats::from_slice(&data);
+    /// let z: Vec<f64> = data.into_iter().zscores(&stats).collect();
+    /// assert_eq!(z[0], -1.5);
+    /// ```
+    fn zscores(self, stats: &OnlineStats) -> ZScores<Self>
+            where Self::Item: ToPrimitive {
+        ZScores { it: self, stats: *stats }
+    }
+
+    /// Scale the items to `[0, 1]` with the range of `minmax`.
+    ///
+    /// Each item `x` is mapped to `minmax.normalize(x)`. Typically, `minmax`
+    /// is computed in a first pass over the same data.
+    ///
+    /// ```
+    /// use stats::{MinMax, StatsIterator};
+    ///
+    /// let data = vec![2, 4, 6, 10];
+    /// let minmax: MinMax<i32> = data.iter().cloned().collect();
+    /// let scaled: Vec<f64> = data.into_iter().normalize(&minmax).collect();
+    /// assert_eq!(scaled, vec![0.0, 0.25, 0.5, 1.0]);
+    /// ```
+    fn normalize<T>(self, minmax: &MinMax<T>) -> Normalize<Self>
+            where Self::Item: ToPrimitive, T: PartialOrd + ToPrimitive {
+        Normalize { it: self, bounds: minmax::bounds(minmax) }
+    }
+
+    /// Yield the count, mean and standard deviation of the items seen so
+    /// far, after each item.
+    ///
+    /// The `n`th snapshot is `(n, mean, stddev)` of an `OnlineStats` that
+    /// has been fed the first `n` items, which is useful for watching the
+    /// statistics converge.
+    ///
+    /// ```
+    /// use stats::StatsIterator;
+    ///
+    /// let snaps: Vec<_> = vec![1, 3].into_iter().running_stats().collect();
+    /// assert_eq!(snaps, vec![(1, 1.0, 0.0), (2, 2.0, 1.0)]);
+    /// ```
+    fn running_stats(self) -> RunningStats<Self>
+            where Self::Item: ToPrimitive {
+        RunningStats { it: self, stats: OnlineStats::new() }
+    }
+}
+
+impl<I: Iterator> StatsIterator for I {}
+
+/// An iterator that normalizes items to z-scores.
+///
+/// This is created by `StatsIterator::zscores`.
+#[derive(Clone, Debug)]
+pub struct ZScores<I> {
+    it: I,
+    stats: OnlineStats,
+}
+
+impl<I> Iterator for ZScores<I> where I: Iterator, I::Item: ToPrimitive {
+    type Item = f64;
+
+    fn next(&mut self) -> Option<f64> {
+        self.it.next().map(|x| self.stats.zscore(x.to_f64().unwrap()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.it.size_hint()
+    }
+}
+
+/// An iterator that scales items to `[0, 1]`.
+///
+/// This is created by `StatsIterator::normalize`.
+#[derive(Clone, Debug)]
+pub struct Normalize<I> {
+    it: I,
+    bounds: Option<(f64, f64)>,
+}
+
+impl<I> Iterator for Normalize<I> where I: Iterator, I::Item: ToPrimitive {
+    type Item = f64;
+
+    fn next(&mut self) -> Option<f64> {
+        let bounds = self.bounds;
+        self.it.next().map(|x| minmax::scale(bounds, x.to_f64().unwrap()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.it.size_hint()
+    }
+}
+
+/// An iterator that yields running statistics.
+///
+/// This is created by `StatsIterator::running_stats`.
+#[derive(Clone, Debug)]
+pub struct RunningStats<I> {
+    it: I,
+    stats: OnlineStats,
+}
+
+impl<I> Iterator for RunningStats<I>
+        where I: Iterator, I::Item: ToPrimitive {
+    type Item = (usize, f64, f64);
+
+    fn next(&mut self) -> Option<(usize, f64, f64)> {
+        let x = self.it.next()?;
+        self.stats.add(x);
+        Some((self.stats.len(), self.stats.mean(), self.stats.stddev()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.it.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {MinMax, OnlineStats};
+    use super::StatsIterator;
+
+    #[test]
+    fn zscores() {
+        let data: Vec<f64> = (0..200)
+            .map(|i| ((i * 7919) % 113) as f64 * 0.37 + 12.0)
+            .collect();
+        let stats = OnlineStats::from_slice(&data);
+        let normalized = data.iter().cloned().zscores(&stats).online_stats();
+        assert_eq!(normalized.len(), data.len());
+        assert!(normalized.mean().abs() < 1e-9, "{}", normalized.mean());
+        assert!((normalized.stddev() - 1.0).abs() < 1e-9,
+                "{}", normalized.stddev());
+    }
+
+    #[test]
+    fn zscores_constant() {
+        let stats = OnlineStats::from_slice(&[4, 4, 4]);
+        assert!(vec![4, 4, 4].into_iter().zscores(&stats).all(f64::is_nan));
+    }
+
+    #[test]
+    fn normalize() {
+        let data = vec![-1.5, 3.0, 0.75, 1.5];
+        let minmax: MinMax<f64> = data.iter().cloned().collect();
+        let scaled: Vec<f64> = data.into_iter().normalize(&minmax).collect();
+        assert_eq!(scaled, vec![0.0, 1.0, 0.5, 2.0 / 3.0]);
+
+        let empty = MinMax::<u8>::new();
+        assert!(vec![1, 2].into_iter().normalize(&empty).all(f64::is_nan));
+    }
+
+    #[test]
+    fn running_stats() {
+        let data: Vec<f64> =
+            (0..100).map(|i| ((i * 7919) % 61) as f64 * 0.5 - 3.0).collect();
+        let snaps: Vec<(usize, f64, f64)> =
+            data.iter().cloned().running_stats().collect();
+        assert_eq!(snaps.len(), data.len());
+        let mut stats = OnlineStats::new();
+        for (x, &snap) in data.iter().zip(&snaps) {
+            stats.add(*x);
+            assert_eq!(snap, (stats.len(), stats.mean(), stats.stddev()));
+        }
+        let total = OnlineStats::from_slice(&data);
+        assert_eq!(snaps[99], (total.len(), total.mean(), total.stddev()));
+        assert_eq!(Vec::<u8>::new().into_iter().running_stats().next(), None);
+    }
+}
@@ -1,26 +1,130 @@
 extern crate num_traits;
+#[cfg(any(feature = "rand", test))]
+extern crate rand;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
 
 use std::cmp::Ordering;
+use std::collections::hash_map::{HashMap, Entry};
+use std::error;
+use std::fmt;
 use std::hash;
 use num_traits::ToPrimitive;
+#[cfg(feature = "rayon")]
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
-pub use frequency::Frequencies;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+pub use binary::{BinaryKey, DecodeError};
+pub use bloom::BloomFilter;
+pub use covariance::Covariance;
+pub use distinct::DistinctSet;
+pub use ewma::Ewma;
+pub use frequency::{
+    Frequencies, JointFrequencies, SumByKey, WeightedFrequencies,
+};
+pub use gk::GKSummary;
+pub use heavyhitters::HeavyHitters;
+pub use histogram::Histogram;
+pub use hyperloglog::HyperLogLog;
+pub use iter::{Normalize, RunningStats, StatsIterator, ZScores};
+pub use means::{AllMeans, GeometricMean, HarmonicMean};
 pub use minmax::MinMax;
-pub use online::{OnlineStats, stddev, variance, mean};
-pub use unsorted::{Unsorted, median, mode, modes};
+pub use online::{
+    GenericOnlineStats, OnlineStats, OnlineStatsF32, stddev, variance, mean,
+    mean_absolute_deviation,
+};
+pub use p2::P2Quantile;
+#[cfg(feature = "rand")]
+pub use permutation::permutation_test;
+pub use product::Product;
+pub use regression::LinearRegression;
+#[cfg(feature = "rand")]
+pub use reservoir::Reservoir;
+pub use rolling::{RollingMedian, RollingStats};
+pub use sorted::Sorted;
+pub use sum::Sum;
+pub use summary::Summary;
+pub use tdigest::TDigest;
+pub use timeseries::TimeSeries;
+pub use unsorted::{Unsorted, WeightedUnsorted, median, mode, modes};
+pub use weighted::{WeightedCount, WeightedMean, WeightedVariance};
 
 /// Partial wraps a type that satisfies `PartialOrd` and implements `Ord`.
 ///
 /// This allows types like `f64` to be used in data structures that require
-/// `Ord`. When an ordering is not defined, an arbitrary order is returned.
-#[derive(Clone, PartialEq, PartialOrd)]
-struct Partial<T>(pub T);
+/// `Ord`, and is what `Unsorted` and `Sorted` use to sort their data.
+///
+/// Values that are comparable are ordered by `PartialOrd`. A value that is
+/// not comparable with itself, like `NaN`, sorts after every comparable
+/// value, and all such values are equal to each other. Any other pair of
+/// incomparable values is treated as equal. `PartialEq` agrees with this
+/// ordering, so unlike `f64`, `Partial(NaN) == Partial(NaN)`.
+///
+/// ```
+/// use stats::Partial;
+///
+/// let mut xs: Vec<Partial<f64>> = vec![2.0, f64::NAN, -1.0, 0.5]
+///     .into_iter().map(Partial::new).collect();
+/// xs.sort();
+/// assert_eq!(xs[0].into_inner(), -1.0);
+/// assert!(xs[3].into_inner().is_nan());
+/// ```
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Partial<T>(pub T);
 
-impl<T: PartialEq> Eq for Partial<T> {}
+impl<T> Partial<T> {
+    /// Wrap a value.
+    pub fn new(v: T) -> Partial<T> {
+        Partial(v)
+    }
+
+    /// Returns a reference to the wrapped value.
+    pub fn get(&self) -> &T {
+        &self.0
+    }
+
+    /// Unwrap the value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+/// Equality is consistent with `Ord`, so `NaN` is equal to `NaN`.
+impl<T: PartialOrd> PartialEq for Partial<T> {
+    fn eq(&self, other: &Partial<T>) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T: PartialOrd> Eq for Partial<T> {}
+
+/// Unlike the wrapped type's `partial_cmp`, this never returns `None`: it
+/// is always `Some(self.cmp(other))`, so `Partial(NaN) < Partial(1.0)` is
+/// `false` and `Partial(NaN) > Partial(1.0)` is `true`.
+impl<T: PartialOrd> PartialOrd for Partial<T> {
+    fn partial_cmp(&self, other: &Partial<T>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
 
 impl<T: PartialOrd> Ord for Partial<T> {
     fn cmp(&self, other: &Partial<T>) -> Ordering {
-        self.partial_cmp(other).unwrap_or(Ordering::Less)
+        match self.0.partial_cmp(&other.0) {
+            Some(ord) => ord,
+            None => {
+                // Only values like `NaN` are incomparable with themselves.
+                let nan1 = self.0.partial_cmp(&self.0).is_none();
+                let nan2 = other.0.partial_cmp(&other.0).is_none();
+                nan1.cmp(&nan2)
+            }
+        }
     }
 }
 
@@ -53,11 +157,44 @@ pub trait Commute : Sized {
     /// Merges the value `other` into `self`.
     fn merge(&mut self, other: Self);
 
+    /// Merges the value `other` into `self`, returning an error instead of
+    /// panicking if the two values cannot be merged.
+    ///
+    /// This calls `check_merge` before `merge`, so if an error is returned,
+    /// then `self` is unchanged.
+    fn try_merge(&mut self, other: Self) -> Result<(), MergeError> {
+        self.check_merge(&other)?;
+        self.merge(other);
+        Ok(())
+    }
+
+    /// Returns an error if merging `other` into `self` would panic.
+    ///
+    /// By default, every pair of values can be merged. Implementations whose
+    /// `merge` panics on incompatible values, like sketches built with
+    /// different parameters or `Vec`s of different lengths, override this.
+    /// Containers check each pair of elements they would merge.
+    fn check_merge(&self, _other: &Self) -> Result<(), MergeError> {
+        Ok(())
+    }
+
     /// Merges the values in the iterator into `self`.
     fn consume<I: Iterator<Item=Self>>(&mut self, other: I) {
+        self.consume_counted(other);
+    }
+
+    /// Merges the values in the iterator into `self`, and returns how many
+    /// values were merged.
+    ///
+    /// A return value of `0` means the iterator was empty and `self` is
+    /// unchanged.
+    fn consume_counted<I: Iterator<Item=Self>>(&mut self, other: I) -> usize {
+        let mut count = 0;
         for v in other {
             self.merge(v);
+            count += 1;
         }
+        count
     }
 }
 
@@ -71,11 +208,33 @@ pub fn merge_all<T: Commute, I: Iterator<Item=T>>(mut it: I) -> Option<T> {
     }
 }
 
+/// Merges all items in the parallel stream.
+///
+/// Since merging is associative and commutative, the items are combined
+/// with a parallel reduction over rayon's thread pool. The result is the
+/// same as `merge_all`, up to floating point rounding.
+///
+/// If the stream is empty, `None` is returned.
+#[cfg(feature = "rayon")]
+pub fn merge_all_par<T, I>(it: I) -> Option<T>
+        where T: Commute + Send, I: IntoParallelIterator<Item=T> {
+    it.into_par_iter().reduce_with(|mut v1, v2| { v1.merge(v2); v1 })
+}
+
 impl<T: Commute> Commute for Option<T> {
     fn merge(&mut self, other: Option<T>) {
         match *self {
             None => { *self = other; }
-            Some(ref mut v1) => { other.map(|v2| v1.merge(v2)); }
+            Some(ref mut v1) => {
+                if let Some(v2) = other { v1.merge(v2); }
+            }
+        }
+    }
+
+    fn check_merge(&self, other: &Option<T>) -> Result<(), MergeError> {
+        match (self, other) {
+            (Some(v1), Some(v2)) => v1.check_merge(v2),
+            _ => Ok(()),
         }
     }
 }
@@ -101,27 +260,240 @@ impl<T: Commute, E> Commute for Result<T, E> {
             }
         }
     }
+
+    fn check_merge(&self, other: &Result<T, E>) -> Result<(), MergeError> {
+        match (self, other) {
+            (Ok(v1), Ok(v2)) => v1.check_merge(v2),
+            _ => Ok(()),
+        }
+    }
 }
 
 impl<T: Commute> Commute for Vec<T> {
     fn merge(&mut self, other: Vec<T>) {
         assert_eq!(self.len(), other.len());
-        for (v1, v2) in self.iter_mut().zip(other.into_iter()) {
+        for (v1, v2) in self.iter_mut().zip(other) {
             v1.merge(v2);
         }
     }
+
+    fn check_merge(&self, other: &Vec<T>) -> Result<(), MergeError> {
+        if self.len() != other.len() {
+            return Err(MergeError::length_mismatch(self.len(), other.len()));
+        }
+        self.iter().zip(other).try_for_each(|(v1, v2)| v1.check_merge(v2))
+    }
 }
 
+impl<T: Commute, const N: usize> Commute for [T; N] {
+    fn merge(&mut self, other: [T; N]) {
+        for (v1, v2) in self.iter_mut().zip(other) {
+            v1.merge(v2);
+        }
+    }
+
+    fn check_merge(&self, other: &[T; N]) -> Result<(), MergeError> {
+        self.iter().zip(other).try_for_each(|(v1, v2)| v1.check_merge(v2))
+    }
+}
+
+impl<K, V, S> Commute for HashMap<K, V, S>
+        where K: Eq + hash::Hash, V: Commute, S: hash::BuildHasher {
+    fn merge(&mut self, other: HashMap<K, V, S>) {
+        for (k, v2) in other {
+            match self.entry(k) {
+                Entry::Vacant(v1) => { v1.insert(v2); }
+                Entry::Occupied(mut v1) => { v1.get_mut().merge(v2); }
+            }
+        }
+    }
+
+    fn check_merge(
+        &self,
+        other: &HashMap<K, V, S>,
+    ) -> Result<(), MergeError> {
+        other.iter().try_for_each(|(k, v2)| match self.get(k) {
+            None => Ok(()),
+            Some(v1) => v1.check_merge(v2),
+        })
+    }
+}
+
+macro_rules! impl_commute_tuple {
+    ($($name:ident: $idx:tt),+) => {
+        impl<$($name: Commute),+> Commute for ($($name,)+) {
+            fn merge(&mut self, other: ($($name,)+)) {
+                $(self.$idx.merge(other.$idx);)+
+            }
+
+            fn check_merge(
+                &self,
+                other: &($($name,)+),
+            ) -> Result<(), MergeError> {
+                $(self.$idx.check_merge(&other.$idx)?;)+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl_commute_tuple!(A: 0, B: 1);
+impl_commute_tuple!(A: 0, B: 1, C: 2);
+impl_commute_tuple!(A: 0, B: 1, C: 2, D: 3);
+
+/// An error that occurs when a sample cannot be converted to a `f64`.
+///
+/// This is returned by fallible methods like `OnlineStats::try_add` when a
+/// sample's `ToPrimitive::to_f64` returns `None`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConversionError {
+    _priv: (),
+}
+
+impl ConversionError {
+    fn new() -> ConversionError {
+        ConversionError { _priv: () }
+    }
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "could not convert sample to f64")
+    }
+}
+
+impl error::Error for ConversionError {}
+
+/// An error that occurs when two values cannot be merged.
+///
+/// This is returned by `Commute::try_merge`, for example when merging two
+/// `Vec`s of different lengths, or two sketches that were created with
+/// different parameters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MergeError {
+    kind: MergeErrorKind,
+}
+
+/// The reason two values cannot be merged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeErrorKind {
+    /// Two sequences have different lengths, which are given in the order
+    /// `(self, other)`.
+    LengthMismatch(usize, usize),
+    /// Two values of the named type were created with different
+    /// parameters, like the number of bins of a histogram.
+    ParameterMismatch(&'static str),
+}
+
+impl MergeError {
+    fn length_mismatch(len1: usize, len2: usize) -> MergeError {
+        MergeError { kind: MergeErrorKind::LengthMismatch(len1, len2) }
+    }
+
+    fn parameter_mismatch(name: &'static str) -> MergeError {
+        MergeError { kind: MergeErrorKind::ParameterMismatch(name) }
+    }
+
+    /// Returns the reason the values cannot be merged.
+    pub fn kind(&self) -> MergeErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            MergeErrorKind::LengthMismatch(len1, len2) => write!(
+                f, "cannot merge values of different lengths ({} and {})",
+                len1, len2),
+            MergeErrorKind::ParameterMismatch(name) => write!(
+                f, "cannot merge {} with different parameters", name),
+        }
+    }
+}
+
+impl error::Error for MergeError {}
+
+mod binary;
+mod bloom;
+mod covariance;
+mod distinct;
+mod ewma;
 mod frequency;
+mod gk;
+mod heavyhitters;
+mod histogram;
+mod hyperloglog;
+mod iter;
+mod means;
 mod minmax;
 mod online;
+mod p2;
+#[cfg(feature = "rand")]
+mod permutation;
+mod product;
+mod regression;
+#[cfg(feature = "rand")]
+mod reservoir;
+mod rolling;
+#[cfg(feature = "serde")]
+mod serde_helpers;
+mod sorted;
+mod stable_hash;
+mod sum;
+mod summary;
+mod tdigest;
+mod timeseries;
 mod unsorted;
+mod weighted;
 
 #[cfg(test)]
 mod test {
-    use Commute;
+    use std::cmp::Ordering;
+    use std::collections::HashMap;
+
+    use {Commute, MergeError, Partial, merge_all};
+    use minmax::MinMax;
+    use online::OnlineStats;
     use unsorted::Unsorted;
 
+    #[test]
+    fn partial_sort_nan() {
+        let nan = f64::NAN;
+        let mut xs: Vec<Partial<f64>> = vec![3.0, nan, 1.0, nan, -2.0, 0.0]
+            .into_iter().map(Partial::new).collect();
+        xs.sort();
+        let got: Vec<f64> = xs.iter().map(|p| *p.get()).collect();
+        assert_eq!(&got[..4], &[-2.0, 0.0, 1.0, 3.0]);
+        assert!(got[4].is_nan() && got[5].is_nan());
+    }
+
+    #[test]
+    fn partial_cmp_nan() {
+        let (nan, one) = (Partial(f64::NAN), Partial(1.0));
+        assert_eq!(nan.cmp(&one), Ordering::Greater);
+        assert_eq!(one.cmp(&nan), Ordering::Less);
+        assert_eq!(nan.cmp(&nan), Ordering::Equal);
+        assert_eq!(nan, nan);
+        assert_ne!(nan, one);
+        assert_eq!(Partial(f64::INFINITY).cmp(&nan), Ordering::Less);
+        assert_eq!(Partial(2.0).cmp(&one), Ordering::Greater);
+    }
+
+    #[test]
+    fn partial_cmp_total() {
+        let (nan, one) = (Partial(f64::NAN), Partial(1.0));
+        assert_eq!(f64::NAN.partial_cmp(&1.0), None);
+        assert_eq!(nan.partial_cmp(&one), Some(Ordering::Greater));
+        assert_eq!(one.partial_cmp(&nan), Some(Ordering::Less));
+        assert_eq!(nan.partial_cmp(&nan), Some(Ordering::Equal));
+        assert!(nan > one);
+        for &(a, b) in &[(1.0, 2.0), (2.0, 1.0), (0.0, -0.0)] {
+            let (a, b) = (Partial(a), Partial(b));
+            assert_eq!(a.partial_cmp(&b), Some(a.cmp(&b)));
+        }
+    }
+
     #[test]
     fn options() {
         let v1: Unsorted<usize> = vec![2, 1, 3, 2].into_iter().collect();
@@ -130,4 +502,163 @@ mod test {
         merged.merge(Some(v2));
         assert_eq!(merged.unwrap().mode(), Some(5));
     }
+
+    #[test]
+    fn arrays() {
+        let mut a1 = [
+            OnlineStats::from_slice(&[1.0, 3.0]),
+            OnlineStats::new(),
+            OnlineStats::from_slice(&[10.0]),
+        ];
+        let a2 = [
+            OnlineStats::from_slice(&[5.0]),
+            OnlineStats::from_slice(&[2.0, 4.0]),
+            OnlineStats::new(),
+        ];
+        a1.merge(a2);
+        assert_eq!(a1[0].mean(), 3.0);
+        assert_eq!(a1[0].len(), 3);
+        assert_eq!(a1[1].mean(), 3.0);
+        assert_eq!(a1[2].mean(), 10.0);
+        assert_eq!(a1[2].len(), 1);
+    }
+
+    #[test]
+    fn try_merge_vecs() {
+        let mut v1 = vec![
+            OnlineStats::from_slice(&[1.0]),
+            OnlineStats::from_slice(&[2.0]),
+        ];
+        let v2 = vec![OnlineStats::from_slice(&[3.0])];
+        let err = v1.try_merge(v2).unwrap_err();
+        assert_eq!(err.to_string(),
+                   "cannot merge values of different lengths (2 and 1)");
+        assert_eq!(v1[0].len(), 1);
+
+        let v2 = vec![OnlineStats::new(), OnlineStats::from_slice(&[4.0])];
+        assert_eq!(v1.try_merge(v2), Ok(()));
+        assert_eq!(v1[1].mean(), 3.0);
+
+        // Types that can always be merged use the default.
+        let mut stats = OnlineStats::from_slice(&[1.0]);
+        assert_eq!(stats.try_merge(OnlineStats::from_slice(&[3.0])), Ok(()));
+        assert_eq!(stats.mean(), 2.0);
+    }
+
+    #[test]
+    fn try_merge_nested() {
+        let mismatch = Err(MergeError::length_mismatch(2, 1));
+        let one = || OnlineStats::from_slice(&[1.0]);
+
+        // A later mismatch must not leave earlier elements merged.
+        let mut v1 = vec![vec![one()], vec![one(), one()]];
+        let v2 = vec![vec![one()], vec![one()]];
+        assert_eq!(v1.try_merge(v2), mismatch);
+        assert_eq!(v1[0][0].len(), 1);
+
+        let mut t1 = (one(), vec![one(), one()]);
+        assert_eq!(t1.try_merge((one(), vec![one()])), mismatch);
+        assert_eq!(t1.0.len(), 1);
+
+        let mut a1 = [vec![one()], vec![one(), one()]];
+        assert_eq!(a1.try_merge([vec![one()], vec![one()]]), mismatch);
+        assert_eq!(a1[0][0].len(), 1);
+
+        let mut m1: HashMap<&str, Vec<OnlineStats>> = HashMap::new();
+        m1.insert("a", vec![one()]);
+        m1.insert("b", vec![one(), one()]);
+        let mut m2 = HashMap::new();
+        m2.insert("a", vec![one()]);
+        m2.insert("b", vec![one()]);
+        m2.insert("c", vec![]);
+        assert_eq!(m1.try_merge(m2), mismatch);
+        assert_eq!(m1.len(), 2);
+        assert_eq!(m1["a"][0].len(), 1);
+
+        let mut o1 = Some(vec![one(), one()]);
+        assert_eq!(o1.try_merge(Some(vec![one()])), mismatch);
+        assert_eq!(o1.try_merge(None), Ok(()));
+        let mut none: Option<Vec<OnlineStats>> = None;
+        assert_eq!(none.try_merge(Some(vec![one()])), Ok(()));
+
+        let mut r1: Result<Vec<OnlineStats>, ()> = Ok(vec![one(), one()]);
+        assert_eq!(r1.try_merge(Ok(vec![one()])), mismatch);
+        assert_eq!(r1.try_merge(Err(())), Ok(()));
+
+        let mut ok = (vec![one()], Some(vec![one()]));
+        assert_eq!(ok.try_merge((vec![one()], Some(vec![one()]))), Ok(()));
+        assert_eq!((ok.0)[0].len(), 2);
+    }
+
+    #[test]
+    fn hashmaps() {
+        let group = |pairs: Vec<(&'static str, f64)>| {
+            let mut map: HashMap<&str, OnlineStats> = HashMap::new();
+            for (k, x) in pairs {
+                map.entry(k).or_default().add(x);
+            }
+            map
+        };
+        let mut m1 = group(vec![("a", 1.0), ("b", 2.0), ("a", 3.0)]);
+        let m2 = group(vec![("a", 5.0), ("c", 7.0)]);
+        m1.merge(m2);
+        assert_eq!(m1.len(), 3);
+        assert_eq!(m1["a"].len(), 3);
+        assert_eq!(m1["a"].mean(), 3.0);
+        assert_eq!(m1["b"].mean(), 2.0);
+        assert_eq!(m1["c"].mean(), 7.0);
+    }
+
+    #[test]
+    fn tuples() {
+        let pair = |xs: Vec<u32>| -> (OnlineStats, MinMax<u32>) {
+            (xs.iter().cloned().collect(), xs.into_iter().collect())
+        };
+        let mut merged = pair(vec![4, 2, 9]);
+        merged.merge(pair(vec![1, 4]));
+        assert_eq!(merged.0.len(), 5);
+        assert_eq!(merged.0.mean(), 4.0);
+        assert_eq!(merged.1.min(), Some(&1));
+        assert_eq!(merged.1.max(), Some(&9));
+
+        let parts = vec![pair(vec![3]), pair(vec![5, 7]), pair(vec![0])];
+        let (stats, minmax) = merge_all(parts.into_iter()).unwrap();
+        assert_eq!(stats.mean(), 3.75);
+        assert_eq!(minmax.range(), Some(7));
+
+        let mut triple = (vec![1u32].into_iter().collect::<MinMax<u32>>(),
+                          Unsorted::new(), OnlineStats::new());
+        triple.merge((vec![5].into_iter().collect(),
+                      vec![2.0].into_iter().collect(),
+                      OnlineStats::from_slice(&[2.0])));
+        assert_eq!(triple.0.max(), Some(&5));
+        assert_eq!(triple.1.median(), Some(2.0));
+        assert_eq!(triple.2.mean(), 2.0);
+    }
+
+    #[test]
+    fn consume_counted() {
+        let parts: Vec<OnlineStats> =
+            (1..6).map(|i| OnlineStats::from_slice(&[i, i * 2])).collect();
+        let mut stats = OnlineStats::new();
+        assert_eq!(stats.consume_counted(parts.into_iter()), 5);
+        assert_eq!(stats.len(), 10);
+        assert_eq!(stats.mean(), 4.5);
+        assert_eq!(stats.consume_counted(Vec::new().into_iter()), 0);
+        assert_eq!(stats.len(), 10);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn merge_all_par() {
+        let parts: Vec<OnlineStats> = (0..1000).map(|i| {
+            (0..(i % 17)).map(|j| ((i * 31 + j * 7) % 101) as f64).collect()
+        }).collect();
+        let seq = merge_all(parts.clone().into_iter()).unwrap();
+        let par = ::merge_all_par(parts).unwrap();
+        assert_eq!(par.len(), seq.len());
+        assert!((par.mean() - seq.mean()).abs() < 1e-9);
+        assert!((par.variance() - seq.variance()).abs() < 1e-9);
+        assert!(::merge_all_par(Vec::<OnlineStats>::new()).is_none());
+    }
 }
@@ -0,0 +1,429 @@
+use std::default::Default;
+use std::fmt;
+use std::iter::{FromIterator, IntoIterator};
+
+use num_traits::ToPrimitive;
+
+use Commute;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A commutative data structure for computing the geometric mean.
+///
+/// The sum of the natural logarithms of each sample is tracked, and the mean
+/// is exponentiated on demand. This stays numerically stable on long streams,
+/// where a running product would overflow or underflow.
+///
+/// The geometric mean is only defined for positive samples. If any sample is
+/// zero or negative, then the geometric mean is `NaN`.
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GeometricMean {
+    len: u64,
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    log_sum: f64,
+    nonpositive: bool,
+}
+
+impl GeometricMean {
+    /// Create initial state with no samples.
+    pub fn new() -> GeometricMean {
+        Default::default()
+    }
+
+    /// Add a sample.
+    pub fn add<T: ToPrimitive>(&mut self, sample: T) {
+        let x = sample.to_f64().unwrap();
+        self.len += 1;
+        if x > 0.0 {
+            self.log_sum += x.ln();
+        } else {
+            self.nonpositive = true;
+        }
+    }
+
+    /// Returns the geometric mean of the samples.
+    ///
+    /// If there are no samples or if any sample is zero or negative, then
+    /// `NaN` is returned.
+    pub fn geometric_mean(&self) -> f64 {
+        if self.len == 0 || self.nonpositive {
+            return f64::NAN;
+        }
+        (self.log_sum / (self.len as f64)).exp()
+    }
+
+    /// Returns the number of samples.
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Returns true if there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Commute for GeometricMean {
+    fn merge(&mut self, v: GeometricMean) {
+        self.len += v.len;
+        self.log_sum += v.log_sum;
+        self.nonpositive = self.nonpositive || v.nonpositive;
+    }
+}
+
+impl Default for GeometricMean {
+    fn default() -> GeometricMean {
+        GeometricMean { len: 0, log_sum: 0.0, nonpositive: false }
+    }
+}
+
+impl fmt::Debug for GeometricMean {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.geometric_mean())
+    }
+}
+
+impl<T: ToPrimitive> FromIterator<T> for GeometricMean {
+    fn from_iter<I: IntoIterator<Item=T>>(it: I) -> GeometricMean {
+        let mut v = GeometricMean::new();
+        v.extend(it);
+        v
+    }
+}
+
+impl<T: ToPrimitive> Extend<T> for GeometricMean {
+    fn extend<I: IntoIterator<Item=T>>(&mut self, it: I) {
+        for sample in it {
+            self.add(sample);
+        }
+    }
+}
+
+/// A commutative data structure for computing the harmonic mean.
+///
+/// The sum of the reciprocals of each sample is tracked, and the harmonic
+/// mean is `n / sum(1 / x)`.
+///
+/// If any sample is zero, then the harmonic mean is defined to be `0`, which
+/// is its limit as a sample approaches zero.
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HarmonicMean {
+    len: u64,
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    recip_sum: f64,
+    zero: bool,
+}
+
+impl HarmonicMean {
+    /// Create initial state with no samples.
+    pub fn new() -> HarmonicMean {
+        Default::default()
+    }
+
+    /// Add a sample.
+    pub fn add<T: ToPrimitive>(&mut self, sample: T) {
+        let x = sample.to_f64().unwrap();
+        self.len += 1;
+        if x == 0.0 {
+            self.zero = true;
+        } else {
+            self.recip_sum += 1.0 / x;
+        }
+    }
+
+    /// Returns the harmonic mean of the samples.
+    ///
+    /// If any sample is zero, then `0` is returned. If there are no samples,
+    /// then `NaN` is returned.
+    pub fn harmonic_mean(&self) -> f64 {
+        if self.len == 0 {
+            return f64::NAN;
+        } else if self.zero {
+            return 0.0;
+        }
+        (self.len as f64) / self.recip_sum
+    }
+
+    /// Returns the number of samples.
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Returns true if there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Commute for HarmonicMean {
+    fn merge(&mut self, v: HarmonicMean) {
+        self.len += v.len;
+        self.recip_sum += v.recip_sum;
+        self.zero = self.zero || v.zero;
+    }
+}
+
+impl Default for HarmonicMean {
+    fn default() -> HarmonicMean {
+        HarmonicMean { len: 0, recip_sum: 0.0, zero: false }
+    }
+}
+
+impl fmt::Debug for HarmonicMean {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.harmonic_mean())
+    }
+}
+
+impl<T: ToPrimitive> FromIterator<T> for HarmonicMean {
+    fn from_iter<I: IntoIterator<Item=T>>(it: I) -> HarmonicMean {
+        let mut v = HarmonicMean::new();
+        v.extend(it);
+        v
+    }
+}
+
+impl<T: ToPrimitive> Extend<T> for HarmonicMean {
+    fn extend<I: IntoIterator<Item=T>>(&mut self, it: I) {
+        for sample in it {
+            self.add(sample);
+        }
+    }
+}
+
+/// A commutative data structure for computing the arithmetic, geometric and
+/// harmonic means in a single pass.
+///
+/// The sum, the sum of the natural logarithms and the sum of the reciprocals
+/// of each sample are tracked together, so all three means merge correctly.
+///
+/// The geometric and harmonic means are only defined here for positive
+/// samples. If any sample is zero or negative, then both are `NaN`. (This
+/// differs from `HarmonicMean`, which returns `0` when a sample is zero.)
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AllMeans {
+    len: u64,
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    sum: f64,
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    log_sum: f64,
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    recip_sum: f64,
+    nonpositive: bool,
+}
+
+impl AllMeans {
+    /// Create initial state with no samples.
+    pub fn new() -> AllMeans {
+        Default::default()
+    }
+
+    /// Add a sample.
+    pub fn add<T: ToPrimitive>(&mut self, sample: T) {
+        let x = sample.to_f64().unwrap();
+        self.len += 1;
+        self.sum += x;
+        if x > 0.0 {
+            self.log_sum += x.ln();
+            self.recip_sum += 1.0 / x;
+        } else {
+            self.nonpositive = true;
+        }
+    }
+
+    /// Returns the arithmetic mean of the samples.
+    ///
+    /// If there are no samples, then `NaN` is returned.
+    pub fn arithmetic_mean(&self) -> f64 {
+        self.sum / (self.len as f64)
+    }
+
+    /// Returns the geometric mean of the samples.
+    ///
+    /// If there are no samples or if any sample is zero or negative, then
+    /// `NaN` is returned.
+    pub fn geometric_mean(&self) -> f64 {
+        if self.len == 0 || self.nonpositive {
+            return f64::NAN;
+        }
+        (self.log_sum / (self.len as f64)).exp()
+    }
+
+    /// Returns the harmonic mean of the samples.
+    ///
+    /// If there are no samples or if any sample is zero or negative, then
+    /// `NaN` is returned.
+    pub fn harmonic_mean(&self) -> f64 {
+        if self.len == 0 || self.nonpositive {
+            return f64::NAN;
+        }
+        (self.len as f64) / self.recip_sum
+    }
+
+    /// Returns the number of samples.
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Returns true if there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Commute for AllMeans {
+    fn merge(&mut self, v: AllMeans) {
+        self.len += v.len;
+        self.sum += v.sum;
+        self.log_sum += v.log_sum;
+        self.recip_sum += v.recip_sum;
+        self.nonpositive = self.nonpositive || v.nonpositive;
+    }
+}
+
+impl Default for AllMeans {
+    fn default() -> AllMeans {
+        AllMeans {
+            len: 0,
+            sum: 0.0,
+            log_sum: 0.0,
+            recip_sum: 0.0,
+            nonpositive: false,
+        }
+    }
+}
+
+impl fmt::Debug for AllMeans {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "arithmetic: {:?}, geometric: {:?}, harmonic: {:?}",
+               self.arithmetic_mean(), self.geometric_mean(),
+               self.harmonic_mean())
+    }
+}
+
+impl<T: ToPrimitive> FromIterator<T> for AllMeans {
+    fn from_iter<I: IntoIterator<Item=T>>(it: I) -> AllMeans {
+        let mut v = AllMeans::new();
+        v.extend(it);
+        v
+    }
+}
+
+impl<T: ToPrimitive> Extend<T> for AllMeans {
+    fn extend<I: IntoIterator<Item=T>>(&mut self, it: I) {
+        for sample in it {
+            self.add(sample);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {Commute, OnlineStats, merge_all};
+    use super::{AllMeans, GeometricMean, HarmonicMean};
+
+    fn log_mean(xs: &[f64]) -> f64 {
+        (xs.iter().map(|x| x.ln()).sum::<f64>() / xs.len() as f64).exp()
+    }
+
+    #[test]
+    fn geometric_mean() {
+        let xs = [1.5, 2.0, 8.0, 0.25, 30.0, 7.75];
+        let gm: GeometricMean = xs.iter().cloned().collect();
+        assert!((gm.geometric_mean() - log_mean(&xs)).abs() < 1e-12);
+
+        let gm: GeometricMean = vec![2u32, 8].into_iter().collect();
+        assert!((gm.geometric_mean() - 4.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn geometric_mean_merge() {
+        let xs = [1.5, 2.0, 8.0, 0.25, 30.0, 7.75];
+        let mut gm: GeometricMean = xs[..2].iter().cloned().collect();
+        gm.merge(xs[2..].iter().cloned().collect());
+        assert!((gm.geometric_mean() - log_mean(&xs)).abs() < 1e-12);
+        assert_eq!(gm.len(), 6);
+    }
+
+    #[test]
+    fn geometric_mean_nonpositive() {
+        assert!(GeometricMean::new().geometric_mean().is_nan());
+        let gm: GeometricMean = vec![1.0, 0.0].into_iter().collect();
+        assert!(gm.geometric_mean().is_nan());
+        let mut gm: GeometricMean = vec![1.0, 4.0].into_iter().collect();
+        gm.merge(vec![-2.0].into_iter().collect());
+        assert!(gm.geometric_mean().is_nan());
+    }
+
+    #[test]
+    fn harmonic_mean() {
+        let hm: HarmonicMean = vec![1u32, 2, 4].into_iter().collect();
+        assert!((hm.harmonic_mean() - 12.0 / 7.0).abs() < 1e-12);
+        assert!(HarmonicMean::new().harmonic_mean().is_nan());
+        let hm: HarmonicMean = vec![1.0, 0.0, 4.0].into_iter().collect();
+        assert_eq!(hm.harmonic_mean(), 0.0);
+    }
+
+    #[test]
+    fn harmonic_mean_merge() {
+        let xs = [1.5, 2.0, 8.0, 0.25, 30.0, 7.75, 3.0];
+        let expected: HarmonicMean = xs.iter().cloned().collect();
+        let parts = xs.chunks(2).map(|c| c.iter().cloned().collect());
+        let got: HarmonicMean = merge_all(parts).unwrap();
+        assert_eq!(got.len(), xs.len());
+        assert!((got.harmonic_mean() - expected.harmonic_mean()).abs()
+                < 1e-12);
+    }
+
+    #[test]
+    fn all_means() {
+        let xs = [1.5, 2.0, 8.0, 0.25, 30.0, 7.75, 3.0];
+        let all: AllMeans = xs.iter().cloned().collect();
+        let am = OnlineStats::from_slice(&xs).mean();
+        let gm: GeometricMean = xs.iter().cloned().collect();
+        let hm: HarmonicMean = xs.iter().cloned().collect();
+        assert_eq!(all.len(), xs.len());
+        assert!((all.arithmetic_mean() - am).abs() < 1e-12);
+        assert!((all.geometric_mean() - gm.geometric_mean()).abs() < 1e-12);
+        assert!((all.harmonic_mean() - hm.harmonic_mean()).abs() < 1e-12);
+        // The means are always ordered for positive samples.
+        assert!(all.harmonic_mean() < all.geometric_mean());
+        assert!(all.geometric_mean() < all.arithmetic_mean());
+    }
+
+    #[test]
+    fn all_means_merge() {
+        let xs = [1.5, 2.0, 8.0, 0.25, 30.0, 7.75, 3.0];
+        let expected: AllMeans = xs.iter().cloned().collect();
+        let parts = xs.chunks(3).map(|c| c.iter().cloned().collect());
+        let got: AllMeans = merge_all(parts).unwrap();
+        assert_eq!(got.len(), expected.len());
+        assert!((got.arithmetic_mean() - expected.arithmetic_mean()).abs()
+                < 1e-12);
+        assert!((got.geometric_mean() - expected.geometric_mean()).abs()
+                < 1e-12);
+        assert!((got.harmonic_mean() - expected.harmonic_mean()).abs()
+                < 1e-12);
+    }
+
+    #[test]
+    fn all_means_nonpositive() {
+        let empty = AllMeans::new();
+        assert!(empty.arithmetic_mean().is_nan());
+        assert!(empty.geometric_mean().is_nan());
+        assert!(empty.harmonic_mean().is_nan());
+
+        let mut all: AllMeans = vec![1, 2, 6].into_iter().collect();
+        all.merge(vec![0].into_iter().collect());
+        assert_eq!(all.arithmetic_mean(), 2.25);
+        assert!(all.geometric_mean().is_nan());
+        assert!(all.harmonic_mean().is_nan());
+        let neg: AllMeans = vec![4.0, -1.0].into_iter().collect();
+        assert_eq!(neg.arithmetic_mean(), 1.5);
+        assert!(neg.harmonic_mean().is_nan());
+    }
+}
@@ -1,17 +1,31 @@
+use std::cmp::Ordering;
 use std::default::Default;
 use std::fmt;
 use std::iter::{FromIterator, IntoIterator};
+use std::ops::Sub;
+
+use num_traits::ToPrimitive;
 
 use Commute;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 /// A commutative data structure for tracking minimum and maximum values.
 ///
-/// This also stores the number of samples.
+/// This also stores the number of samples, the second smallest and second
+/// largest values, and the positions at which the minimum and maximum first
+/// occurred.
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct MinMax<T> {
     len: u64,
     min: Option<T>,
     max: Option<T>,
+    min2: Option<T>,
+    max2: Option<T>,
+    argmin: u64,
+    argmax: u64,
 }
 
 impl<T: PartialOrd + Clone> MinMax<T> {
@@ -21,13 +35,69 @@ impl<T: PartialOrd + Clone> MinMax<T> {
     }
 
     /// Add a sample to the data.
+    ///
+    /// Samples that are not comparable with themselves, such as `NaN`, are
+    /// ignored entirely. They are not counted by `len` and do not advance
+    /// the indices reported by `argmin` and `argmax`.
     pub fn add(&mut self, sample: T) {
+        if sample.partial_cmp(&sample).is_none() {
+            return;
+        }
+        let index = self.len;
         self.len += 1;
         if self.min.as_ref().map(|v| &sample < v).unwrap_or(true) {
-            self.min = Some(sample.clone());
+            self.min2 = self.min.replace(sample.clone());
+            self.argmin = index;
+        } else if self.min2.as_ref().map(|v| &sample < v).unwrap_or(true) {
+            self.min2 = Some(sample.clone());
         }
         if self.max.as_ref().map(|v| &sample > v).unwrap_or(true) {
-            self.max = Some(sample);
+            self.max2 = self.max.replace(sample);
+            self.argmax = index;
+        } else if self.max2.as_ref().map(|v| &sample > v).unwrap_or(true) {
+            self.max2 = Some(sample);
+        }
+    }
+
+    /// Add two consecutive samples with fewer comparisons than calling `add`
+    /// twice.
+    ///
+    /// The pair is ordered first, so only the smaller sample needs to be
+    /// compared with the minimum and only the larger with the maximum.
+    fn add_pair(&mut self, a: T, b: T) {
+        let (i, j) = (self.len, self.len + 1);
+        let (lo, hi, argmin, argmax) = match a.partial_cmp(&b) {
+            Some(Ordering::Less) => (a, b, i, j),
+            Some(Ordering::Greater) => (b, a, j, i),
+            // The first occurrence of a tie wins, like in `add`.
+            Some(Ordering::Equal) => (a, b, i, i),
+            None => {
+                self.add(a);
+                self.add(b);
+                return;
+            }
+        };
+        self.len += 2;
+        if self.min.as_ref().map(|v| &lo < v).unwrap_or(true) {
+            // The second smallest is either the old minimum or `hi`.
+            let old = self.min.replace(lo.clone());
+            self.min2 = match old {
+                Some(v) if v <= hi => Some(v),
+                _ => Some(hi.clone()),
+            };
+            self.argmin = argmin;
+        } else if self.min2.as_ref().map(|v| &lo < v).unwrap_or(true) {
+            self.min2 = Some(lo.clone());
+        }
+        if self.max.as_ref().map(|v| &hi > v).unwrap_or(true) {
+            let old = self.max.replace(hi);
+            self.max2 = match old {
+                Some(v) if v >= lo => Some(v),
+                _ => Some(lo),
+            };
+            self.argmax = argmax;
+        } else if self.max2.as_ref().map(|v| &hi > v).unwrap_or(true) {
+            self.max2 = Some(hi);
         }
     }
 
@@ -45,41 +115,167 @@ impl<T: PartialOrd + Clone> MinMax<T> {
         self.max.as_ref()
     }
 
+    /// Returns the second smallest value of the data set.
+    ///
+    /// This is the second order statistic, so it is equal to the minimum if
+    /// the minimum occurs more than once. `None` is returned if there are
+    /// fewer than `2` samples.
+    pub fn second_min(&self) -> Option<&T> {
+        self.min2.as_ref()
+    }
+
+    /// Returns the second largest value of the data set.
+    ///
+    /// This is equal to the maximum if the maximum occurs more than once.
+    /// `None` is returned if there are fewer than `2` samples.
+    pub fn second_max(&self) -> Option<&T> {
+        self.max2.as_ref()
+    }
+
+    /// Returns the index of the first occurrence of the minimum, where the
+    /// first sample added has index `0`.
+    ///
+    /// `None` is returned if and only if the number of samples is `0`.
+    pub fn argmin(&self) -> Option<usize> {
+        self.min.as_ref().map(|_| self.argmin as usize)
+    }
+
+    /// Returns the index of the first occurrence of the maximum, where the
+    /// first sample added has index `0`.
+    ///
+    /// `None` is returned if and only if the number of samples is `0`.
+    pub fn argmax(&self) -> Option<usize> {
+        self.max.as_ref().map(|_| self.argmax as usize)
+    }
+
     /// Returns the number of data point.
     pub fn len(&self) -> usize {
         self.len as usize
     }
+
+    /// Returns true if there are no data points.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: PartialOrd + Clone + Sub<Output=T>> MinMax<T> {
+    /// Returns the range of the data set, i.e., `max - min`.
+    ///
+    /// `None` is returned if and only if the number of samples is `0`.
+    pub fn range(&self) -> Option<T> {
+        match (&self.min, &self.max) {
+            (Some(min), Some(max)) => Some(max.clone() - min.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl<T: PartialOrd + ToPrimitive> MinMax<T> {
+    /// Scales `x` to `[0, 1]` relative to the range of the data set, i.e.,
+    /// `(x - min) / (max - min)`.
+    ///
+    /// Values outside of the range are scaled proportionally, so they fall
+    /// outside of `[0, 1]`. If every sample is equal, then the range is `0`
+    /// and `0.0` is returned. If there are no samples, then `NaN` is
+    /// returned.
+    pub fn normalize(&self, x: T) -> f64 {
+        scale(bounds(self), x.to_f64().unwrap())
+    }
+}
+
+/// Returns the minimum and maximum of `minmax` converted to `f64`.
+pub fn bounds<T: ToPrimitive>(minmax: &MinMax<T>) -> Option<(f64, f64)> {
+    match (&minmax.min, &minmax.max) {
+        (Some(min), Some(max)) => {
+            Some((min.to_f64().unwrap(), max.to_f64().unwrap()))
+        }
+        _ => None,
+    }
+}
+
+/// Scales `x` to `[0, 1]` relative to `bounds`, as in `MinMax::normalize`.
+pub fn scale(bounds: Option<(f64, f64)>, x: f64) -> f64 {
+    match bounds {
+        None => f64::NAN,
+        Some((min, max)) if min == max => 0.0,
+        Some((min, max)) => (x - min) / (max - min),
+    }
 }
 
+/// Indices in `v` are treated as following the samples in `self`, so they
+/// are offset by the number of samples in `self`.
 impl<T: PartialOrd> Commute for MinMax<T> {
     fn merge(&mut self, v: MinMax<T>) {
+        let offset = self.len;
         self.len += v.len;
-        if self.min.is_none() || (!v.min.is_none() && v.min < self.min) {
+        if self.min.is_none() || (v.min.is_some() && v.min < self.min) {
+            self.min2 = pick(self.min.take(), v.min2, |a, b| a < b);
             self.min = v.min;
+            self.argmin = offset + v.argmin;
+        } else {
+            self.min2 = pick(self.min2.take(), v.min, |a, b| a < b);
         }
-        if self.max.is_none() || (!v.max.is_none() && v.max > self.max) {
+        if self.max.is_none() || (v.max.is_some() && v.max > self.max) {
+            self.max2 = pick(self.max.take(), v.max2, |a, b| a > b);
             self.max = v.max;
+            self.argmax = offset + v.argmax;
+        } else {
+            self.max2 = pick(self.max2.take(), v.max, |a, b| a > b);
         }
     }
 }
 
+/// Returns whichever of `a` and `b` exists, preferring `b` only if
+/// `first(b, a)` holds when both exist.
+fn pick<T, F>(a: Option<T>, b: Option<T>, first: F) -> Option<T>
+        where F: Fn(&T, &T) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if first(&b, &a) { b } else { a }),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
 impl<T: PartialOrd> Default for MinMax<T> {
     fn default() -> MinMax<T> {
         MinMax {
             len: 0,
             min: None,
             max: None,
+            min2: None,
+            max2: None,
+            argmin: 0,
+            argmax: 0,
         }
     }
 }
 
 impl<T: fmt::Debug> fmt::Debug for MinMax<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match (&self.min, &self.max) {
-            (&Some(ref min), &Some(ref max)) => {
+        match (&self.min, &self.max, f.precision()) {
+            (Some(min), Some(max), Some(prec)) => {
+                write!(f, "[{:.*?}, {:.*?}]", prec, min, prec, max)
+            }
+            (Some(min), Some(max), None) => {
                 write!(f, "[{:?}, {:?}]", min, max)
             }
-            (&None, &None) => write!(f, "N/A"),
+            (None, None, _) => write!(f, "N/A"),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for MinMax<T> {
+    /// Writes `[min, max]`, passing the formatter's precision, if any, on to
+    /// both values.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.min, &self.max, f.precision()) {
+            (Some(min), Some(max), Some(prec)) => {
+                write!(f, "[{:.*}, {:.*}]", prec, min, prec, max)
+            }
+            (Some(min), Some(max), None) => write!(f, "[{}, {}]", min, max),
+            (None, None, _) => write!(f, "N/A"),
             _ => unreachable!(),
         }
     }
@@ -94,18 +290,103 @@ impl<T: PartialOrd + Clone> FromIterator<T> for MinMax<T> {
 }
 
 impl<T: PartialOrd + Clone> Extend<T> for MinMax<T> {
+    /// Samples are added in pairs, which takes about 5 comparisons per pair
+    /// instead of up to 10 when adding them one at a time.
     fn extend<I: IntoIterator<Item=T>>(&mut self, it: I) {
-        for sample in it {
-            self.add(sample);
+        let mut it = it.into_iter();
+        while let Some(a) = it.next() {
+            match it.next() {
+                Some(b) => self.add_pair(a, b),
+                None => self.add(a),
+            }
         }
     }
 }
 
 #[cfg(test)]
 mod test {
+    #[cfg(feature = "serde")]
+    use serde_json;
+
+    use std::cell::Cell;
+    use std::cmp::Ordering;
+
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
     use super::MinMax;
     use Commute;
 
+    /// Adds samples one at a time, bypassing the pairwise `extend`.
+    fn naive<T: PartialOrd + Clone>(data: &[T]) -> MinMax<T> {
+        let mut minmax = MinMax::new();
+        for x in data {
+            minmax.add(x.clone());
+        }
+        minmax
+    }
+
+    fn assert_same<T: PartialOrd + Clone + ::std::fmt::Debug>(data: &[T]) {
+        let (got, expected) = (data.iter().cloned().collect(), naive(data));
+        let got: MinMax<T> = got;
+        assert_eq!(got.len(), expected.len());
+        assert_eq!(got.min(), expected.min(), "{:?}", data);
+        assert_eq!(got.max(), expected.max(), "{:?}", data);
+        assert_eq!(got.second_min(), expected.second_min(), "{:?}", data);
+        assert_eq!(got.second_max(), expected.second_max(), "{:?}", data);
+        assert_eq!(got.argmin(), expected.argmin(), "{:?}", data);
+        assert_eq!(got.argmax(), expected.argmax(), "{:?}", data);
+    }
+
+    #[test]
+    fn extend_matches_add() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for len in 0..40 {
+            for _ in 0..20 {
+                // A small range makes ties common.
+                let data: Vec<i32> =
+                    (0..len).map(|_| rng.gen_range(0..8)).collect();
+                assert_same(&data);
+            }
+        }
+        let data: Vec<f64> = (0..10_000).map(|_| rng.gen()).collect();
+        assert_same(&data);
+        assert_same(&[2.0, f64::NAN, 1.0, 3.0, f64::NAN, f64::NAN, 0.5]);
+    }
+
+    thread_local!(static COMPARISONS: Cell<usize> = const { Cell::new(0) });
+
+    /// A sample that counts how many times it is compared.
+    #[derive(Clone, Debug, PartialEq)]
+    struct Counted(u32);
+
+    impl PartialOrd for Counted {
+        fn partial_cmp(&self, other: &Counted) -> Option<Ordering> {
+            COMPARISONS.with(|c| c.set(c.get() + 1));
+            self.0.partial_cmp(&other.0)
+        }
+    }
+
+    #[test]
+    fn extend_fewer_comparisons() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let data: Vec<Counted> =
+            (0..100_000).map(|_| Counted(rng.gen())).collect();
+        let count = |f: &dyn Fn() -> MinMax<Counted>| {
+            COMPARISONS.with(|c| c.set(0));
+            let minmax = f();
+            (minmax, COMPARISONS.with(|c| c.get()))
+        };
+        let (expected, naive_count) = count(&|| naive(&data));
+        let (got, pair_count) = count(&|| data.iter().cloned().collect());
+        assert_eq!(got.min(), expected.min());
+        assert_eq!(got.max(), expected.max());
+        assert_eq!(got.second_min(), expected.second_min());
+        assert_eq!(got.second_max(), expected.second_max());
+        assert!(pair_count * 3 < naive_count * 2,
+                "pairs: {}, naive: {}", pair_count, naive_count);
+    }
+
     #[test]
     fn minmax() {
         let minmax: MinMax<u32> =
@@ -114,6 +395,125 @@ mod test {
         assert_eq!(minmax.max(), Some(&10u32));
     }
 
+    #[test]
+    fn range() {
+        let minmax: MinMax<i32> = vec![3, -4, 7, 0].into_iter().collect();
+        assert_eq!(minmax.range(), Some(11));
+        assert_eq!(MinMax::<i32>::new().range(), None);
+    }
+
+    #[test]
+    fn format_precision() {
+        let minmax: MinMax<f64> = vec![0.5, 2.0 / 3.0, -1.25].into_iter()
+            .collect();
+        assert_eq!(format!("{}", minmax), "[-1.25, 0.6666666666666666]");
+        assert_eq!(format!("{:.3}", minmax), "[-1.250, 0.667]");
+        assert_eq!(format!("{:.0}", minmax), "[-1, 1]");
+        assert_eq!(format!("{:.1?}", minmax), "[-1.2, 0.7]");
+        assert_eq!(format!("{:.2}", MinMax::<f64>::new()), "N/A");
+    }
+
+    #[test]
+    fn normalize() {
+        let minmax: MinMax<i32> = vec![4, -2, 10, 7].into_iter().collect();
+        assert_eq!(minmax.normalize(-2), 0.0);
+        assert_eq!(minmax.normalize(10), 1.0);
+        assert_eq!(minmax.normalize(4), 0.5);
+        assert_eq!(minmax.normalize(16), 1.5);
+
+        let minmax: MinMax<f64> = vec![3.0, 3.0].into_iter().collect();
+        assert_eq!(minmax.normalize(3.0), 0.0);
+        assert!(MinMax::<f64>::new().normalize(1.0).is_nan());
+    }
+
+    #[test]
+    fn argminmax() {
+        let minmax: MinMax<u32> =
+            vec![4u32, 1, 10, 1, 3, 10].into_iter().collect();
+        assert_eq!(minmax.argmin(), Some(1));
+        assert_eq!(minmax.argmax(), Some(2));
+        assert_eq!(MinMax::<u32>::new().argmin(), None);
+    }
+
+    #[test]
+    fn argminmax_merge() {
+        let mut mx1: MinMax<u32> = vec![5, 3, 8].into_iter().collect();
+        let mx2: MinMax<u32> = vec![4, 9, 1, 9].into_iter().collect();
+        mx1.merge(mx2);
+        assert_eq!(mx1.argmin(), Some(5));
+        assert_eq!(mx1.argmax(), Some(4));
+
+        let mut mx1: MinMax<u32> = vec![5, 1, 9].into_iter().collect();
+        mx1.merge(vec![1, 9].into_iter().collect());
+        assert_eq!(mx1.argmin(), Some(1));
+        assert_eq!(mx1.argmax(), Some(2));
+
+        let mut mx1: MinMax<u32> = MinMax::new();
+        mx1.merge(vec![2, 1].into_iter().collect());
+        assert_eq!(mx1.argmin(), Some(1));
+        assert_eq!(mx1.argmax(), Some(0));
+    }
+
+    #[test]
+    fn second_minmax() {
+        let minmax: MinMax<u32> = vec![5u32, 2, 9, 7, 1].into_iter().collect();
+        assert_eq!(minmax.second_min(), Some(&2));
+        assert_eq!(minmax.second_max(), Some(&7));
+
+        let minmax: MinMax<u32> = vec![3u32, 1, 3, 1].into_iter().collect();
+        assert_eq!(minmax.second_min(), Some(&1));
+        assert_eq!(minmax.second_max(), Some(&3));
+
+        let minmax: MinMax<u32> = vec![3u32].into_iter().collect();
+        assert_eq!(minmax.second_min(), None);
+        assert_eq!(minmax.second_max(), None);
+    }
+
+    #[test]
+    fn second_minmax_merge() {
+        let mut mx1: MinMax<u32> = vec![4, 10, 2, 8].into_iter().collect();
+        let mx2: MinMax<u32> = vec![3, 9, 1, 12].into_iter().collect();
+        mx1.merge(mx2);
+        assert_eq!(mx1.second_min(), Some(&2));
+        assert_eq!(mx1.second_max(), Some(&10));
+
+        // Overlapping extremes tie for first place.
+        let mut mx1: MinMax<u32> = vec![1, 5, 9].into_iter().collect();
+        mx1.merge(vec![9, 1].into_iter().collect());
+        assert_eq!(mx1.second_min(), Some(&1));
+        assert_eq!(mx1.second_max(), Some(&9));
+
+        // Single samples on each side combine into two.
+        let mut mx1: MinMax<u32> = vec![6].into_iter().collect();
+        mx1.merge(vec![2].into_iter().collect());
+        assert_eq!(mx1.second_min(), Some(&6));
+        assert_eq!(mx1.second_max(), Some(&2));
+
+        let mut mx1: MinMax<u32> = MinMax::new();
+        mx1.merge(vec![6].into_iter().collect());
+        mx1.merge(MinMax::new());
+        assert_eq!(mx1.second_min(), None);
+        assert_eq!(mx1.second_max(), None);
+    }
+
+    #[test]
+    fn skip_nan() {
+        let nan = f64::NAN;
+        let minmax: MinMax<f64> =
+            vec![nan, 3.0, nan, -1.0, 5.0, nan].into_iter().collect();
+        assert_eq!(minmax.min(), Some(&-1.0));
+        assert_eq!(minmax.max(), Some(&5.0));
+        assert_eq!(minmax.argmin(), Some(1));
+        assert_eq!(minmax.len(), 3);
+
+        let mut mx1: MinMax<f64> = vec![nan].into_iter().collect();
+        assert!(mx1.is_empty());
+        assert_eq!(mx1.min(), None);
+        mx1.merge(minmax);
+        assert_eq!(mx1.min(), Some(&-1.0));
+        assert_eq!(mx1.max(), Some(&5.0));
+    }
+
     #[test]
     fn minmax_merge_empty() {
         let mut mx1: MinMax<u32> = vec![1, 4, 2, 3, 10].into_iter().collect();
@@ -124,4 +524,22 @@ mod test {
         assert_eq!(mx1.min(), Some(&1u32));
         assert_eq!(mx1.max(), Some(&10u32));
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_roundtrip() {
+        let minmax: MinMax<i32> = vec![4, -2, 9, 3].into_iter().collect();
+        let json = serde_json::to_string(&minmax).unwrap();
+        let mut got: MinMax<i32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(got.min(), Some(&-2));
+        assert_eq!(got.max(), Some(&9));
+        assert_eq!(got.second_max(), Some(&4));
+        assert_eq!(got.argmax(), Some(2));
+        assert_eq!(got.len(), 4);
+
+        got.merge(vec![12, -1].into_iter().collect());
+        assert_eq!(got.max(), Some(&12));
+        assert_eq!(got.argmax(), Some(4));
+        assert_eq!(got.second_min(), Some(&-1));
+    }
 }
@@ -2,9 +2,13 @@ use std::default::Default;
 use std::fmt;
 use std::iter::{FromIterator, IntoIterator};
 
-use num_traits::ToPrimitive;
+use num_traits::{Float, ToPrimitive};
 
-use Commute;
+use {Commute, ConversionError};
+use binary::{self, DecodeError, Kind};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 /// Compute the standard deviation of a stream in constant space.
 pub fn stddev<I>(it: I) -> f64
@@ -24,110 +28,557 @@ pub fn mean<I>(it: I) -> f64
     it.collect::<OnlineStats>().mean()
 }
 
+/// Compute the mean absolute deviation about the mean of a slice.
+///
+/// This is the average of `|x - mean|` over all samples. Unlike the variance,
+/// it cannot be computed exactly in a single pass: every deviation depends on
+/// the final mean, which isn't known until all samples have been seen. For
+/// that reason it isn't tracked by `OnlineStats`, and this function makes two
+/// passes over the data instead.
+///
+/// If the slice is empty, then `NaN` is returned.
+pub fn mean_absolute_deviation(data: &[f64]) -> f64 {
+    let n = data.len() as f64;
+    let mean = data.iter().sum::<f64>() / n;
+    data.iter().map(|x| (x - mean).abs()).sum::<f64>() / n
+}
+
+/// Online state for computing mean, variance and standard deviation of
+/// `f64` samples.
+///
+/// This is the most common instantiation of `GenericOnlineStats`, which has
+/// all of its documentation.
+pub type OnlineStats = GenericOnlineStats<f64>;
+
+/// Online state for computing mean, variance and standard deviation, which
+/// computes and stores every moment as an `f32`.
+///
+/// This halves the size of the state compared to `OnlineStats`, at the cost
+/// of precision.
+pub type OnlineStatsF32 = GenericOnlineStats<f32>;
+
 /// Online state for computing mean, variance and standard deviation.
+///
+/// Skewness and kurtosis are also available, which are computed from the
+/// third and fourth central moments. Every moment is computed and stored as
+/// the floating point type `F`. Usually, this is used through the
+/// `OnlineStats` (`f64`) or `OnlineStatsF32` aliases.
+///
+/// Missing values can be recorded with `add_null`. They are counted by `len`,
+/// but every statistic is computed over the non-null samples only, as if the
+/// nulls had never been added.
 #[derive(Clone, Copy)]
-pub struct OnlineStats {
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound = "F: Float"))]
+pub struct GenericOnlineStats<F> {
+    /// The number of non-null samples.
     size: u64,
-    mean: f64,
-    variance: f64,
+    /// The number of nulls, which aren't included in any statistic.
+    #[cfg_attr(feature = "serde", serde(default))]
+    null_count: u64,
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    mean: F,
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    variance: F,
+    /// The sum of cubed differences from the mean.
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    m3: F,
+    /// The sum of differences from the mean raised to the fourth power.
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    m4: F,
 }
 
-impl OnlineStats {
+/// Converts a constant to the float type `F`.
+fn lit<F: Float>(x: f64) -> F {
+    F::from(x).unwrap()
+}
+
+impl<F: Float> GenericOnlineStats<F> {
     /// Create initial state.
     ///
     /// Population size, variance and mean are set to `0`.
-    pub fn new() -> OnlineStats {
+    pub fn new() -> GenericOnlineStats<F> {
         Default::default()
     }
 
     /// Initializes variance from a sample.
-    pub fn from_slice<T: ToPrimitive>(samples: &[T]) -> OnlineStats {
+    pub fn from_slice<T: ToPrimitive>(
+        samples: &[T],
+    ) -> GenericOnlineStats<F> {
         samples.iter().map(|n| n.to_f64().unwrap()).collect()
     }
 
+    /// Create state from a count, mean and population variance that were
+    /// computed elsewhere.
+    ///
+    /// The result can be merged like any other state. Since the third and
+    /// fourth moments aren't known, `skewness` and `kurtosis` return `NaN`
+    /// for the result and for anything it is merged into. If `count` is
+    /// `0`, then this is the same as `new`.
+    pub fn from_parts(
+        count: u64,
+        mean: F,
+        variance: F,
+    ) -> GenericOnlineStats<F> {
+        if count == 0 {
+            return GenericOnlineStats::new();
+        }
+        GenericOnlineStats {
+            size: count,
+            null_count: 0,
+            mean,
+            variance,
+            m3: F::nan(),
+            m4: F::nan(),
+        }
+    }
+
+    /// Returns the number of non-null samples, the mean and the population
+    /// variance.
+    ///
+    /// This is the inverse of `from_parts`.
+    pub fn parts(&self) -> (u64, F, F) {
+        (self.size, self.mean, self.variance)
+    }
+
     /// Return the current mean.
-    pub fn mean(&self) -> f64 {
+    pub fn mean(&self) -> F {
         self.mean
     }
 
     /// Return the current standard deviation.
-    pub fn stddev(&self) -> f64 {
+    pub fn stddev(&self) -> F {
         self.variance.sqrt()
     }
 
     /// Return the current variance.
-    pub fn variance(&self) -> f64 {
+    pub fn variance(&self) -> F {
         self.variance
     }
 
+    /// Return the current sample standard deviation.
+    ///
+    /// This is the square root of `sample_variance`.
+    pub fn sample_stddev(&self) -> F {
+        self.sample_variance().sqrt()
+    }
+
+    /// Return the current sample variance.
+    ///
+    /// Unlike `variance`, this applies Bessel's correction by dividing by
+    /// `N - 1` instead of `N`, which makes it an unbiased estimate of the
+    /// population variance.
+    ///
+    /// If there are fewer than two data points, then `NaN` is returned.
+    pub fn sample_variance(&self) -> F {
+        if self.size <= 1 {
+            return F::nan();
+        }
+        let n = self.n();
+        self.variance * n / (n - F::one())
+    }
+
+    /// Return the standard error of the mean.
+    ///
+    /// This is the sample standard deviation divided by `sqrt(N)`.
+    ///
+    /// If there are fewer than two data points, then `NaN` is returned.
+    pub fn stderr(&self) -> F {
+        self.sample_stddev() / self.n().sqrt()
+    }
+
+    /// Return a confidence interval for the mean as `(lower, upper)`.
+    ///
+    /// The interval is `mean +/- z * stderr`, where `z` is the z-score for
+    /// the desired confidence level (e.g., `1.96` for 95%).
+    ///
+    /// If there are fewer than two data points, then `(NaN, NaN)` is
+    /// returned.
+    pub fn confidence_interval(&self, z: F) -> (F, F) {
+        let margin = z * self.stderr();
+        (self.mean - margin, self.mean + margin)
+    }
+
+    /// Return the coefficient of variation, i.e., the population standard
+    /// deviation relative to the mean.
+    ///
+    /// If the mean is `0` (including when there is no data), then `NaN` is
+    /// returned.
+    pub fn coeff_variation(&self) -> F {
+        if self.mean == F::zero() {
+            return F::nan();
+        }
+        self.stddev() / self.mean
+    }
+
+    /// Return the z-score of `x`, i.e., the number of standard deviations
+    /// it lies from the mean: `(x - mean) / stddev`.
+    ///
+    /// This uses the population standard deviation. If the standard
+    /// deviation is `0` (including when there is no data), then `NaN` is
+    /// returned.
+    pub fn zscore(&self, x: F) -> F {
+        let stddev = self.stddev();
+        if stddev == F::zero() {
+            return F::nan();
+        }
+        (x - self.mean) / stddev
+    }
+
+    /// Return the current population skewness.
+    ///
+    /// If there is no data or the variance is `0`, then `NaN` is returned.
+    pub fn skewness(&self) -> F {
+        let n = self.n();
+        let m2 = self.variance * n;
+        if self.size == 0 || m2 == F::zero() {
+            return F::nan();
+        }
+        n.sqrt() * self.m3 / m2.powf(lit(1.5))
+    }
+
+    /// Return the current population excess kurtosis.
+    ///
+    /// This uses Fisher's definition, so the kurtosis of a normal
+    /// distribution is `0`.
+    ///
+    /// If there is no data or the variance is `0`, then `NaN` is returned.
+    pub fn kurtosis(&self) -> F {
+        let n = self.n();
+        let m2 = self.variance * n;
+        if self.size == 0 || m2 == F::zero() {
+            return F::nan();
+        }
+        n * self.m4 / (m2 * m2) - lit(3.0)
+    }
+
+    /// Return the `k`th population central moment, `E[(X - mean)^k]`.
+    ///
+    /// Only moments up to the fourth are tracked. The zeroth moment is `1`,
+    /// the first is `0` and the second is the population variance.
+    ///
+    /// If there is no data or `k` is greater than `4`, then `NaN` is
+    /// returned.
+    pub fn central_moment(&self, k: u32) -> F {
+        if self.size == 0 {
+            return F::nan();
+        }
+        let n = self.n();
+        match k {
+            0 => F::one(),
+            1 => F::zero(),
+            2 => self.variance,
+            3 => self.m3 / n,
+            4 => self.m4 / n,
+            _ => F::nan(),
+        }
+    }
+
+    /// Return the `k`th raw moment, `E[X^k]`.
+    ///
+    /// This is computed from the central moments with the binomial
+    /// expansion of `(X - mean + mean)^k`, so only moments up to the fourth
+    /// are available.
+    ///
+    /// If there is no data or `k` is greater than `4`, then `NaN` is
+    /// returned.
+    pub fn raw_moment(&self, k: u32) -> F {
+        if self.size == 0 || k > 4 {
+            return F::nan();
+        }
+        let mut binomial = F::one();
+        let mut sum = F::zero();
+        for j in 0..(k + 1) {
+            sum = sum + binomial * self.central_moment(j)
+                        * self.mean.powi((k - j) as i32);
+            binomial = binomial * lit((k - j) as f64) / lit((j + 1) as f64);
+        }
+        sum
+    }
+
+    /// Return the current mean, or `None` if there are no non-null samples.
+    pub fn try_mean(&self) -> Option<F> {
+        if self.size == 0 { None } else { Some(self.mean()) }
+    }
+
+    /// Return the current standard deviation, or `None` if there are no
+    /// non-null samples.
+    pub fn try_stddev(&self) -> Option<F> {
+        if self.size == 0 { None } else { Some(self.stddev()) }
+    }
+
+    /// Return the current variance, or `None` if there are no non-null
+    /// samples.
+    pub fn try_variance(&self) -> Option<F> {
+        if self.size == 0 { None } else { Some(self.variance()) }
+    }
+
+    /// Return the current sample variance, or `None` if there are fewer
+    /// than two non-null samples.
+    pub fn try_sample_variance(&self) -> Option<F> {
+        if self.size <= 1 { None } else { Some(self.sample_variance()) }
+    }
+
+    /// Return the standard error of the mean, or `None` if there are fewer
+    /// than two non-null samples.
+    pub fn try_stderr(&self) -> Option<F> {
+        if self.size <= 1 { None } else { Some(self.stderr()) }
+    }
+
+    /// Return the current population skewness, or `None` if there are no
+    /// non-null samples or the variance is `0`.
+    pub fn try_skewness(&self) -> Option<F> {
+        if self.size == 0 || self.variance == F::zero() {
+            None
+        } else {
+            Some(self.skewness())
+        }
+    }
+
+    /// Return the current population excess kurtosis, or `None` if there
+    /// are no non-null samples or the variance is `0`.
+    pub fn try_kurtosis(&self) -> Option<F> {
+        if self.size == 0 || self.variance == F::zero() {
+            None
+        } else {
+            Some(self.kurtosis())
+        }
+    }
+
     /// Add a new sample.
     pub fn add<T: ToPrimitive>(&mut self, sample: T) {
-        let sample = sample.to_f64().unwrap();
+        let sample: F = F::from(sample).unwrap();
         // Taken from: http://goo.gl/JKeqvj
         // See also: http://goo.gl/qTtI3V
         let oldmean = self.mean;
-        let prevq = self.variance * (self.size as f64);
+        let prevq = self.variance * self.n();
 
         self.size += 1;
-        self.mean += (sample - oldmean) / (self.size as f64);
+        let n = self.n();
+        self.mean = self.mean + (sample - oldmean) / n;
         self.variance = (prevq + (sample - oldmean) * (sample - self.mean))
-                        / (self.size as f64);
+                        / n;
+
+        // Higher moments are from Terriberry's extension of Welford's method.
+        // See: https://en.wikipedia.org/wiki/Algorithms_for_calculating_variance#Higher-order_statistics
+        let delta = sample - oldmean;
+        let delta_n = delta / n;
+        let term1 = delta * delta_n * (n - F::one());
+        let (two, three) = (lit::<F>(2.0), lit::<F>(3.0));
+        let (four, six) = (lit::<F>(4.0), lit::<F>(6.0));
+        self.m4 = self.m4
+                  + (term1 * delta_n * delta_n * (n * n - three * n + three)
+                     + six * delta_n * delta_n * prevq
+                     - four * delta_n * self.m3);
+        self.m3 = self.m3
+                  + (term1 * delta_n * (n - two) - three * delta_n * prevq);
+    }
+
+    /// Add a new sample, returning an error if it cannot be converted to the
+    /// float type.
+    ///
+    /// This is like `add`, except it does not panic. If an error is
+    /// returned, then the state is unchanged.
+    pub fn try_add<T: ToPrimitive>(
+        &mut self,
+        sample: T,
+    ) -> Result<(), ConversionError> {
+        let sample: F = F::from(sample).ok_or_else(ConversionError::new)?;
+        self.add(sample);
+        Ok(())
+    }
+
+    /// Add every sample in a slice.
+    ///
+    /// This gives the same statistics as calling `add` on each sample, up
+    /// to floating point rounding, but is faster on large slices. Instead
+    /// of updating every moment with several divisions per sample, the
+    /// moments of the whole slice are summed in two passes and then merged
+    /// into this state with the same formulas as `merge`.
+    pub fn extend_slice(&mut self, samples: &[F]) {
+        if samples.is_empty() {
+            return;
+        }
+        let n = F::from(samples.len()).unwrap();
+        let mean = samples.iter().fold(F::zero(), |sum, &x| sum + x) / n;
+        let (mut m2, mut m3, mut m4) = (F::zero(), F::zero(), F::zero());
+        for &x in samples {
+            let d = x - mean;
+            let d2 = d * d;
+            m2 = m2 + d2;
+            m3 = m3 + d2 * d;
+            m4 = m4 + d2 * d2;
+        }
+        self.merge(GenericOnlineStats {
+            size: samples.len() as u64,
+            null_count: 0,
+            mean,
+            variance: m2 / n,
+            m3,
+            m4,
+        });
     }
 
     /// Add a new NULL value to the population.
     ///
-    /// This increases the population size by `1`.
+    /// A null is a missing value, not a zero. It increases `len` and
+    /// `null_count` by `1`, but leaves every statistic unchanged, since they
+    /// are all computed over the non-null samples.
     pub fn add_null(&mut self) {
-        self.add(0usize);
+        self.null_count += 1;
     }
 
-    /// Returns the number of data points.
+    /// Returns the number of data points, including nulls.
     pub fn len(&self) -> usize {
+        (self.size + self.null_count) as usize
+    }
+
+    /// Returns the number of non-null data points.
+    ///
+    /// This is the `N` used to compute every statistic.
+    pub fn nonnull_len(&self) -> usize {
         self.size as usize
     }
+
+    /// Returns the number of nulls added with `add_null`.
+    pub fn null_count(&self) -> usize {
+        self.null_count as usize
+    }
+
+    /// Returns true if there are no data points, including nulls.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of non-null samples as a float.
+    fn n(&self) -> F {
+        F::from(self.size).unwrap()
+    }
+}
+
+impl OnlineStats {
+    /// Encode this state in a compact, portable binary format.
+    ///
+    /// The encoding begins with a versioned header and stores every field
+    /// in little endian byte order. Use `from_bytes` to decode it.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut dst = binary::write_header(Kind::OnlineStats);
+        binary::write_u64(&mut dst, self.size);
+        binary::write_u64(&mut dst, self.null_count);
+        for &x in &[self.mean, self.variance, self.m3, self.m4] {
+            binary::write_f64(&mut dst, x);
+        }
+        dst
+    }
+
+    /// Decode state that was encoded with `to_bytes`.
+    ///
+    /// An error is returned if `bytes` is not a complete encoding of
+    /// `OnlineStats`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<OnlineStats, DecodeError> {
+        let mut src = bytes;
+        let version = binary::read_header(&mut src, Kind::OnlineStats)?;
+        let size = binary::read_u64(&mut src)?;
+        // Version 1 didn't track nulls.
+        let null_count =
+            if version >= 2 { binary::read_u64(&mut src)? } else { 0 };
+        let stats = OnlineStats {
+            size,
+            null_count,
+            mean: binary::read_f64(&mut src)?,
+            variance: binary::read_f64(&mut src)?,
+            m3: binary::read_f64(&mut src)?,
+            m4: binary::read_f64(&mut src)?,
+        };
+        binary::read_end(src)?;
+        Ok(stats)
+    }
 }
 
-impl Commute for OnlineStats {
-    fn merge(&mut self, v: OnlineStats) {
+impl<F: Float> Commute for GenericOnlineStats<F> {
+    fn merge(&mut self, v: GenericOnlineStats<F>) {
+        self.null_count += v.null_count;
+        // Merging with an empty state would otherwise divide by zero.
+        if v.size == 0 {
+            return;
+        } else if self.size == 0 {
+            *self = GenericOnlineStats { null_count: self.null_count, ..v };
+            return;
+        }
         // Taken from: http://goo.gl/iODi28
-        let (s1, s2) = (self.size as f64, v.size as f64);
+        let (s1, s2) = (self.n(), v.n());
         let meandiffsq = (self.mean - v.mean) * (self.mean - v.mean);
         let mean = ((s1 * self.mean) + (s2 * v.mean)) / (s1 + s2);
         let var = (((s1 * self.variance) + (s2 * v.variance))
                    / (s1 + s2))
                   +
                   ((s1 * s2 * meandiffsq) / ((s1 + s2) * (s1 + s2)));
+
+        // The higher moments use the pairwise update formulas from:
+        // Pébay, "Formulas for Robust, One-Pass Parallel Computation of
+        // Covariances and Arbitrary-Order Statistical Moments", 2008.
+        let n = s1 + s2;
+        let delta = v.mean - self.mean;
+        let (m2a, m2b) = (s1 * self.variance, s2 * v.variance);
+        let delta2 = delta * delta;
+        self.m4 = self.m4 + v.m4
+                  + delta2 * delta2 * s1 * s2 * (s1 * s1 - s1 * s2 + s2 * s2)
+                    / (n * n * n)
+                  + lit::<F>(6.0) * delta2 * (s1 * s1 * m2b + s2 * s2 * m2a)
+                    / (n * n)
+                  + lit::<F>(4.0) * delta * (s1 * v.m3 - s2 * self.m3) / n;
+        self.m3 = self.m3 + v.m3
+                  + delta * delta * delta * s1 * s2 * (s1 - s2) / (n * n)
+                  + lit::<F>(3.0) * delta * (s1 * m2b - s2 * m2a) / n;
         self.size += v.size;
         self.mean = mean;
         self.variance = var;
     }
 }
 
-impl Default for OnlineStats {
-    fn default() -> OnlineStats {
-        OnlineStats {
+impl<F: Float> Default for GenericOnlineStats<F> {
+    fn default() -> GenericOnlineStats<F> {
+        GenericOnlineStats {
             size: 0,
-            mean: 0.0,
-            variance: 0.0,
+            null_count: 0,
+            mean: F::zero(),
+            variance: F::zero(),
+            m3: F::zero(),
+            m4: F::zero(),
         }
     }
 }
 
-impl fmt::Debug for OnlineStats {
+/// The number of decimals printed when the formatter has no precision.
+const DEFAULT_PRECISION: usize = 10;
+
+impl<F: Float + fmt::Display> fmt::Display for GenericOnlineStats<F> {
+    /// Writes `mean +/- stddev`, using the formatter's precision if one is
+    /// given and 10 decimals otherwise.
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:.10} +/- {:.10}", self.mean(), self.stddev())
+        let prec = f.precision().unwrap_or(DEFAULT_PRECISION);
+        write!(f, "{:.*} +/- {:.*}", prec, self.mean(), prec, self.stddev())
     }
 }
 
-impl<T: ToPrimitive> FromIterator<T> for OnlineStats {
-    fn from_iter<I: IntoIterator<Item=T>>(it: I) -> OnlineStats {
-        let mut v = OnlineStats::new();
+impl<F: Float + fmt::Display> fmt::Debug for GenericOnlineStats<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Label the convention explicitly so that the population standard
+        // deviation (dividing by `N`) isn't mistaken for the sample one.
+        fmt::Display::fmt(self, f)?;
+        write!(f, " (population stddev)")
+    }
+}
+
+impl<F: Float, T: ToPrimitive> FromIterator<T> for GenericOnlineStats<F> {
+    fn from_iter<I: IntoIterator<Item=T>>(it: I) -> GenericOnlineStats<F> {
+        let mut v = GenericOnlineStats::new();
         v.extend(it);
         v
     }
 }
 
-impl<T: ToPrimitive> Extend<T> for OnlineStats {
+impl<F: Float, T: ToPrimitive> Extend<T> for GenericOnlineStats<F> {
     fn extend<I: IntoIterator<Item=T>>(&mut self, it: I) {
         for sample in it {
             self.add(sample)
@@ -137,8 +588,260 @@ impl<T: ToPrimitive> Extend<T> for OnlineStats {
 
 #[cfg(test)]
 mod test {
-    use {Commute, merge_all};
-    use super::OnlineStats;
+    #[cfg(feature = "serde")]
+    use serde_json;
+
+    use num_traits::ToPrimitive;
+
+    use {Commute, ConversionError, DecodeError, merge_all};
+    use super::{OnlineStats, OnlineStatsF32, mean_absolute_deviation};
+
+    #[test]
+    fn empty() {
+        let stats = OnlineStats::new();
+        assert_eq!(stats.try_mean(), None);
+        assert_eq!(stats.try_variance(), None);
+        assert_eq!(stats.try_stddev(), None);
+        assert_eq!(stats.try_sample_variance(), None);
+        assert_eq!(stats.try_stderr(), None);
+        assert_eq!(stats.try_skewness(), None);
+        assert_eq!(stats.try_kurtosis(), None);
+
+        let stats = OnlineStats::from_slice(&[5usize]);
+        assert_eq!(stats.try_sample_variance(), None);
+        assert_eq!(stats.try_stderr(), None);
+        assert_eq!(stats.try_skewness(), None);
+        assert_eq!(stats.try_kurtosis(), None);
+
+        let stats = OnlineStats::from_slice(&[2usize, 4]);
+        assert_eq!(stats.try_mean(), Some(3.0));
+        assert_eq!(stats.try_variance(), Some(1.0));
+        assert_eq!(stats.try_stddev(), Some(1.0));
+        assert_eq!(stats.try_sample_variance(), Some(2.0));
+        assert_eq!(stats.try_stderr(), Some(1.0));
+        assert_eq!(stats.try_skewness(), Some(0.0));
+        assert_eq!(stats.try_kurtosis(), Some(-2.0));
+    }
+
+    #[test]
+    fn mean_absolute_deviation_slice() {
+        // mean = 5, deviations = 3, 1, 1, 1, 0, 0, 2, 4
+        let data = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        assert_eq!(mean_absolute_deviation(&data), 1.5);
+        assert!(mean_absolute_deviation(&[]).is_nan());
+    }
+
+    #[test]
+    fn debug_labels_population() {
+        let stats = OnlineStats::from_slice(&[1usize, 2, 3]);
+        let out = format!("{:?}", stats);
+        assert_eq!(out, "2.0000000000 +/- 0.8164965809 (population stddev)");
+        let out = format!("{:.2?}", stats);
+        assert_eq!(out, "2.00 +/- 0.82 (population stddev)");
+    }
+
+    #[test]
+    fn display_precision() {
+        let stats = OnlineStats::from_slice(&[1usize, 2, 3]);
+        assert_eq!(format!("{}", stats), "2.0000000000 +/- 0.8164965809");
+        assert_eq!(format!("{:.3}", stats), "2.000 +/- 0.816");
+        assert_eq!(format!("{:.1}", stats), "2.0 +/- 0.8");
+        assert_eq!(format!("{:.0}", stats), "2 +/- 1");
+    }
+
+    /// Computes the population skewness with two passes over `xs`.
+    fn naive_skewness(xs: &[f64]) -> f64 {
+        let n = xs.len() as f64;
+        let mean = xs.iter().sum::<f64>() / n;
+        let m2 = xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+        let m3 = xs.iter().map(|x| (x - mean).powi(3)).sum::<f64>() / n;
+        m3 / m2.powf(1.5)
+    }
+
+    /// Computes the population excess kurtosis with two passes over `xs`.
+    fn naive_kurtosis(xs: &[f64]) -> f64 {
+        let n = xs.len() as f64;
+        let mean = xs.iter().sum::<f64>() / n;
+        let m2 = xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+        let m4 = xs.iter().map(|x| (x - mean).powi(4)).sum::<f64>() / n;
+        m4 / (m2 * m2) - 3.0
+    }
+
+    fn close(x: f64, y: f64) -> bool {
+        (x - y).abs() <= 1e-9 * (1.0 + x.abs().max(y.abs()))
+    }
+
+    /// A handful of deterministic, skewed datasets of varying sizes.
+    fn datasets() -> Vec<Vec<f64>> {
+        (1..8).map(|k| {
+            (0..(k * 13)).map(|i| {
+                let x = ((i * 7919 + k * 31) % 101) as f64 / 10.0;
+                x * x / (k as f64) - 3.0
+            }).collect()
+        }).collect()
+    }
+
+    #[test]
+    fn skewness() {
+        for xs in datasets() {
+            let got = OnlineStats::from_slice(&xs).skewness();
+            assert!(close(got, naive_skewness(&xs)),
+                    "got {}, expected {}", got, naive_skewness(&xs));
+        }
+        assert!(OnlineStats::new().skewness().is_nan());
+        assert!(OnlineStats::from_slice(&[2.0, 2.0]).skewness().is_nan());
+    }
+
+    #[test]
+    fn skewness_merge() {
+        for xs in datasets() {
+            for split in &[0, 1, xs.len() / 3, xs.len() / 2, xs.len()] {
+                let (a, b) = xs.split_at(*split);
+                let mut got = OnlineStats::from_slice(a);
+                got.merge(OnlineStats::from_slice(b));
+                assert!(close(got.skewness(), naive_skewness(&xs)),
+                        "got {}, expected {}",
+                        got.skewness(), naive_skewness(&xs));
+            }
+        }
+    }
+
+    #[test]
+    fn kurtosis() {
+        for xs in datasets() {
+            let got = OnlineStats::from_slice(&xs).kurtosis();
+            assert!(close(got, naive_kurtosis(&xs)),
+                    "got {}, expected {}", got, naive_kurtosis(&xs));
+        }
+        assert!(OnlineStats::new().kurtosis().is_nan());
+        assert!(OnlineStats::from_slice(&[2.0, 2.0]).kurtosis().is_nan());
+    }
+
+    #[test]
+    fn kurtosis_merge_many() {
+        for xs in datasets() {
+            let expected = OnlineStats::from_slice(&xs);
+            let parts = xs.chunks(5).map(OnlineStats::from_slice);
+            let got = merge_all(parts).unwrap();
+            assert!(close(got.kurtosis(), expected.kurtosis()),
+                    "got {}, expected {}",
+                    got.kurtosis(), expected.kurtosis());
+            assert!(close(got.skewness(), expected.skewness()));
+        }
+    }
+
+    #[test]
+    fn extend_slice() {
+        for xs in datasets() {
+            let expected = OnlineStats::from_slice(&xs);
+            let mut got = OnlineStats::new();
+            got.extend_slice(&xs);
+            assert_eq!(got.len(), expected.len());
+            assert!(close(got.mean(), expected.mean()));
+            assert!(close(got.variance(), expected.variance()));
+            assert!(close(got.skewness(), expected.skewness()));
+            assert!(close(got.kurtosis(), expected.kurtosis()));
+
+            // Extending existing state merges the batch into it.
+            let (a, b) = xs.split_at(xs.len() / 3);
+            let mut got = OnlineStats::from_slice(a);
+            got.add_null();
+            got.extend_slice(b);
+            got.extend_slice(&[]);
+            assert_eq!(got.nonnull_len(), expected.len());
+            assert_eq!(got.null_count(), 1);
+            assert!(close(got.mean(), expected.mean()));
+            assert!(close(got.variance(), expected.variance()));
+            assert!(close(got.kurtosis(), expected.kurtosis()));
+        }
+    }
+
+    #[test]
+    fn parts_roundtrip() {
+        for xs in datasets() {
+            let stats = OnlineStats::from_slice(&xs);
+            let (count, mean, variance) = stats.parts();
+            assert_eq!(count, xs.len() as u64);
+            let got = OnlineStats::from_parts(count, mean, variance);
+            assert_eq!(got.parts(), stats.parts());
+            assert_eq!(got.stddev(), stats.stddev());
+            assert!(got.skewness().is_nan() && got.kurtosis().is_nan());
+
+            // Reconstructed state merges like the original.
+            let (a, b) = xs.split_at(xs.len() / 2);
+            let mut merged = OnlineStats::from_slice(a);
+            let (n, m, v) = OnlineStats::from_slice(b).parts();
+            merged.merge(OnlineStats::from_parts(n, m, v));
+            assert_eq!(merged.len(), xs.len());
+            assert!(close(merged.mean(), stats.mean()));
+            assert!(close(merged.variance(), stats.variance()));
+        }
+    }
+
+    #[test]
+    fn parts_empty() {
+        let empty = OnlineStats::from_parts(0, 5.0, 2.0);
+        assert_eq!(empty.parts(), OnlineStats::new().parts());
+        assert!(empty.is_empty());
+        assert_eq!(empty.try_mean(), None);
+        let mut stats = OnlineStats::from_slice(&[1.0, 3.0]);
+        stats.merge(empty);
+        assert_eq!(stats.parts(), (2, 2.0, 1.0));
+        assert_eq!(stats.skewness(), 0.0);
+    }
+
+    #[test]
+    fn zscore() {
+        let stats = OnlineStats::from_slice(&[2usize, 4, 4, 4, 5, 5, 7, 9]);
+        assert_eq!(stats.zscore(5.0), 0.0);
+        assert_eq!(stats.zscore(9.0), 2.0);
+        assert_eq!(stats.zscore(4.0), -0.5);
+        assert!(OnlineStats::from_slice(&[3, 3]).zscore(3.0).is_nan());
+        assert!(OnlineStats::new().zscore(1.0).is_nan());
+    }
+
+    #[test]
+    fn sample_variance() {
+        let stats = OnlineStats::from_slice(&[2usize, 4, 4, 4, 5, 5, 7, 9]);
+        assert_eq!(stats.variance(), 4.0);
+        assert_eq!(stats.stddev(), 2.0);
+        assert!(close(stats.sample_variance(), 32.0 / 7.0));
+        assert!(close(stats.sample_stddev(), (32.0f64 / 7.0).sqrt()));
+
+        let stats = OnlineStats::from_slice(&[1.5, 2.5]);
+        assert_eq!(stats.sample_variance(), 0.5);
+
+        assert!(OnlineStats::new().sample_variance().is_nan());
+        assert!(OnlineStats::from_slice(&[3usize]).sample_stddev().is_nan());
+    }
+
+    #[test]
+    fn stderr() {
+        let stats = OnlineStats::from_slice(&[2usize, 4, 4, 4, 5, 5, 7, 9]);
+        // sqrt(32 / 7) / sqrt(8) = sqrt(4 / 7)
+        assert!(close(stats.stderr(), (4.0f64 / 7.0).sqrt()));
+        assert!(OnlineStats::new().stderr().is_nan());
+    }
+
+    #[test]
+    fn confidence_interval() {
+        let stats = OnlineStats::from_slice(&[2usize, 4, 4, 4, 5, 5, 7, 9]);
+        let margin = 1.96 * (4.0f64 / 7.0).sqrt();
+        let (lo, hi) = stats.confidence_interval(1.96);
+        assert!(close(lo, 5.0 - margin));
+        assert!(close(hi, 5.0 + margin));
+
+        let (lo, hi) = OnlineStats::new().confidence_interval(1.96);
+        assert!(lo.is_nan() && hi.is_nan());
+    }
+
+    #[test]
+    fn coeff_variation() {
+        let stats = OnlineStats::from_slice(&[2usize, 4, 4, 4, 5, 5, 7, 9]);
+        assert_eq!(stats.coeff_variation(), 0.4);
+        assert!(OnlineStats::from_slice(&[-1, 1]).coeff_variation().is_nan());
+        assert!(OnlineStats::new().coeff_variation().is_nan());
+    }
 
     #[test]
     fn stddev() {
@@ -166,4 +869,197 @@ mod test {
         assert_eq!(expected.stddev(),
                    merge_all(vars.into_iter()).unwrap().stddev());
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_roundtrip() {
+        let stats = OnlineStats::from_slice(&[1.5, 2.0, 8.25, -3.0, 4.0]);
+        let json = serde_json::to_string(&stats).unwrap();
+        let got: OnlineStats = serde_json::from_str(&json).unwrap();
+        assert_eq!(got.len(), stats.len());
+        assert_eq!(got.mean(), stats.mean());
+        assert_eq!(got.variance(), stats.variance());
+        assert_eq!(got.skewness(), stats.skewness());
+        assert_eq!(got.kurtosis(), stats.kurtosis());
+
+        let fresh = OnlineStats::from_slice(&[10.0, 0.5, 7.0]);
+        let (mut expected, mut got) = (stats, got);
+        expected.merge(fresh);
+        got.merge(fresh);
+        assert_eq!(got.mean(), expected.mean());
+        assert_eq!(got.variance(), expected.variance());
+        assert_eq!(got.kurtosis(), expected.kurtosis());
+    }
+
+    #[test]
+    fn bytes_roundtrip() {
+        let stats = OnlineStats::from_slice(&[1.5, 2.0, 8.25, -3.0, 4.0]);
+        let got = OnlineStats::from_bytes(&stats.to_bytes()).unwrap();
+        assert_eq!(got.len(), stats.len());
+        assert_eq!(got.mean(), stats.mean());
+        assert_eq!(got.variance(), stats.variance());
+        assert_eq!(got.skewness(), stats.skewness());
+        assert_eq!(got.kurtosis(), stats.kurtosis());
+    }
+
+    #[test]
+    fn bytes_invalid() {
+        let bytes = OnlineStats::from_slice(&[1, 2, 3]).to_bytes();
+        for i in 0..bytes.len() {
+            assert_eq!(OnlineStats::from_bytes(&bytes[..i]).unwrap_err(),
+                       DecodeError::UnexpectedEof);
+        }
+        let mut long = bytes.clone();
+        long.push(0);
+        assert_eq!(OnlineStats::from_bytes(&long).unwrap_err(),
+                   DecodeError::TrailingBytes);
+        let mut bad = bytes.clone();
+        bad[0] = b'X';
+        assert_eq!(OnlineStats::from_bytes(&bad).unwrap_err(),
+                   DecodeError::InvalidHeader);
+        let mut future = bytes.clone();
+        future[4] = 255;
+        assert_eq!(OnlineStats::from_bytes(&future).unwrap_err(),
+                   DecodeError::UnsupportedVersion(255));
+    }
+
+    /// A sample that can't be represented as a number.
+    struct Opaque;
+
+    impl ToPrimitive for Opaque {
+        fn to_i64(&self) -> Option<i64> { None }
+        fn to_u64(&self) -> Option<u64> { None }
+        fn to_f64(&self) -> Option<f64> { None }
+    }
+
+    #[test]
+    fn try_add() {
+        let mut stats = OnlineStats::new();
+        assert_eq!(stats.try_add(2.0), Ok(()));
+        assert_eq!(stats.try_add(4u8), Ok(()));
+        assert_eq!(stats.try_add(Opaque), Err(ConversionError::new()));
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats.mean(), 3.0);
+    }
+
+    #[test]
+    fn nulls() {
+        for data in datasets() {
+            let expected = OnlineStats::from_slice(&data);
+            let mut got = OnlineStats::new();
+            for (i, &x) in data.iter().enumerate() {
+                got.add(x);
+                if i % 3 == 0 {
+                    got.add_null();
+                }
+            }
+            let nulls = data.len().div_ceil(3);
+            assert_eq!(got.len(), data.len() + nulls);
+            assert_eq!(got.nonnull_len(), data.len());
+            assert_eq!(got.null_count(), nulls);
+            assert_eq!(got.mean(), expected.mean());
+            assert_eq!(got.variance(), expected.variance());
+            assert_eq!(got.sample_variance(), expected.sample_variance());
+            assert_eq!(got.stderr(), expected.stderr());
+            assert_eq!(got.kurtosis(), expected.kurtosis());
+        }
+    }
+
+    #[test]
+    fn nulls_only() {
+        let mut stats = OnlineStats::new();
+        stats.add_null();
+        stats.add_null();
+        assert_eq!(stats.len(), 2);
+        assert!(!stats.is_empty());
+        assert_eq!(stats.nonnull_len(), 0);
+        assert_eq!(stats.try_mean(), None);
+        assert_eq!(stats.try_variance(), None);
+    }
+
+    #[test]
+    fn nulls_merge() {
+        let mut a = OnlineStats::new();
+        a.add_null();
+        let mut b = OnlineStats::from_slice(&[1usize, 2, 3]);
+        b.add_null();
+        b.add_null();
+        a.merge(b);
+        assert_eq!((a.len(), a.nonnull_len(), a.null_count()), (6, 3, 3));
+        assert_eq!(a.mean(), 2.0);
+
+        let mut c = OnlineStats::from_slice(&[2usize, 4, 6]);
+        c.merge(a);
+        assert_eq!((c.len(), c.nonnull_len(), c.null_count()), (9, 6, 3));
+        let expected = OnlineStats::from_slice(&[2usize, 4, 6, 1, 2, 3]);
+        assert!(close(c.variance(), expected.variance()));
+    }
+
+    #[test]
+    fn bytes_nulls() {
+        let mut stats = OnlineStats::from_slice(&[1.5, 2.0]);
+        stats.add_null();
+        let got = OnlineStats::from_bytes(&stats.to_bytes()).unwrap();
+        assert_eq!((got.len(), got.null_count()), (3, 1));
+        assert_eq!(got.mean(), 1.75);
+
+        // Version 1 encodings have no null count.
+        let mut v1 = stats.to_bytes();
+        v1[4] = 1;
+        v1.drain(14..22);
+        let got = OnlineStats::from_bytes(&v1).unwrap();
+        assert_eq!((got.len(), got.null_count()), (2, 0));
+        assert_eq!(got.mean(), 1.75);
+    }
+
+    #[test]
+    fn f32_matches_f64() {
+        for data in datasets() {
+            let expected = OnlineStats::from_slice(&data);
+            let got = OnlineStatsF32::from_slice(&data);
+            let close32 = |x: f32, y: f64| {
+                ((x as f64) - y).abs() <= 1e-4 * (1.0 + y.abs())
+            };
+            assert_eq!(got.len(), expected.len());
+            assert!(close32(got.mean(), expected.mean()));
+            assert!(close32(got.variance(), expected.variance()));
+            assert!(close32(got.stddev(), expected.stddev()));
+            assert!(close32(got.skewness(), expected.skewness()));
+            assert!(close32(got.kurtosis(), expected.kurtosis()));
+
+            let (a, b) = data.split_at(data.len() / 3);
+            let mut merged = OnlineStatsF32::from_slice(a);
+            merged.merge(OnlineStatsF32::from_slice(b));
+            assert!(close32(merged.variance(), expected.variance()));
+        }
+        let stats: OnlineStatsF32 = vec![2u8, 4].into_iter().collect();
+        assert_eq!(stats.mean(), 3.0f32);
+        assert_eq!(format!("{:.2}", stats), "3.00 +/- 1.00");
+        assert!(OnlineStatsF32::new().sample_variance().is_nan());
+    }
+
+    #[test]
+    fn moments() {
+        for data in datasets() {
+            let stats = OnlineStats::from_slice(&data);
+            let n = data.len() as f64;
+            let mean = data.iter().sum::<f64>() / n;
+            for k in 0..5 {
+                let central = data.iter()
+                    .map(|x| (x - mean).powi(k as i32)).sum::<f64>() / n;
+                let raw = data.iter().map(|x| x.powi(k as i32)).sum::<f64>()
+                          / n;
+                assert!(close(stats.central_moment(k), central),
+                        "k: {}, {} != {}", k, stats.central_moment(k),
+                        central);
+                assert!(close(stats.raw_moment(k), raw),
+                        "k: {}, {} != {}", k, stats.raw_moment(k), raw);
+            }
+        }
+        let stats = OnlineStats::from_slice(&[1.0, 2.0]);
+        assert!(stats.central_moment(5).is_nan());
+        assert!(stats.raw_moment(5).is_nan());
+        assert!(OnlineStats::new().central_moment(2).is_nan());
+        assert!(OnlineStats::new().raw_moment(1).is_nan());
+    }
 }
@@ -0,0 +1,236 @@
+use std::fmt;
+use std::iter::IntoIterator;
+
+use num_traits::ToPrimitive;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Online state for estimating a single quantile in constant memory.
+///
+/// This is the P² algorithm, which maintains five markers whose heights
+/// approximate the minimum, the `p/2`th, `p`th and `(1+p)/2`th quantiles and
+/// the maximum. As each sample arrives, the markers are moved towards their
+/// desired positions and their heights are adjusted with a piecewise
+/// parabolic formula. Unlike `Unsorted`, the data itself is never stored.
+///
+/// Since the estimate depends on the order in which samples are added, this
+/// type does not implement `Commute`: there is no way to combine the
+/// markers of two partitions of a stream.
+///
+/// See: Jain and Chlamtac, "The P² algorithm for dynamic calculation of
+/// quantiles and histograms without storing observations", 1985.
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct P2Quantile {
+    p: f64,
+    len: u64,
+    /// Marker heights. Until five samples have been seen, these are just
+    /// the samples.
+    heights: [f64; 5],
+    /// Actual marker positions, starting at `1`.
+    positions: [f64; 5],
+    /// Desired marker positions.
+    desired: [f64; 5],
+    /// Increments to the desired marker positions for each sample.
+    increments: [f64; 5],
+}
+
+impl P2Quantile {
+    /// Create initial state for estimating the `p`th quantile.
+    ///
+    /// This panics if `p` is not in `(0, 1)`. (Use `MinMax` for the
+    /// extremes.)
+    pub fn new(p: f64) -> P2Quantile {
+        assert!(p > 0.0 && p < 1.0,
+                "quantile probability must be in (0, 1), got {}", p);
+        P2Quantile {
+            p,
+            len: 0,
+            heights: [0.0; 5],
+            positions: [1.0, 2.0, 3.0, 4.0, 5.0],
+            desired: [1.0, 1.0 + 2.0 * p, 1.0 + 4.0 * p, 3.0 + 2.0 * p, 5.0],
+            increments: [0.0, p / 2.0, p, (1.0 + p) / 2.0, 1.0],
+        }
+    }
+
+    /// Add a new sample.
+    ///
+    /// `NaN` samples are ignored.
+    pub fn add<T: ToPrimitive>(&mut self, sample: T) {
+        let x = sample.to_f64().unwrap();
+        if x.is_nan() {
+            return;
+        }
+        if self.len < 5 {
+            self.heights[self.len as usize] = x;
+            self.len += 1;
+            if self.len == 5 {
+                self.heights.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            }
+            return;
+        }
+        self.len += 1;
+
+        // Find the cell containing the sample, extending the extremes if
+        // necessary.
+        let q = &mut self.heights;
+        let k = if x < q[0] {
+            q[0] = x;
+            0
+        } else if x >= q[4] {
+            q[4] = x;
+            3
+        } else {
+            (1..5).find(|&i| x < q[i]).unwrap() - 1
+        };
+        for n in &mut self.positions[k + 1..] {
+            *n += 1.0;
+        }
+        for (np, dn) in self.desired.iter_mut().zip(&self.increments) {
+            *np += dn;
+        }
+
+        // Adjust the heights of the middle markers if they are off by more
+        // than one position.
+        let n = &mut self.positions;
+        for i in 1..4 {
+            let d = self.desired[i] - n[i];
+            if (d >= 1.0 && n[i + 1] - n[i] > 1.0)
+               || (d <= -1.0 && n[i - 1] - n[i] < -1.0) {
+                let d = d.signum();
+                let parabolic = q[i] + d / (n[i + 1] - n[i - 1]) * (
+                    (n[i] - n[i - 1] + d) * (q[i + 1] - q[i])
+                    / (n[i + 1] - n[i])
+                    + (n[i + 1] - n[i] - d) * (q[i] - q[i - 1])
+                    / (n[i] - n[i - 1])
+                );
+                q[i] = if q[i - 1] < parabolic && parabolic < q[i + 1] {
+                    parabolic
+                } else {
+                    let j = if d > 0.0 { i + 1 } else { i - 1 };
+                    q[i] + d * (q[j] - q[i]) / (n[j] - n[i])
+                };
+                n[i] += d;
+            }
+        }
+    }
+
+    /// Returns the estimate of the quantile.
+    ///
+    /// With fewer than five samples, the quantile is computed exactly by
+    /// linear interpolation between the closest order statistics. If there
+    /// are no samples, then `NaN` is returned.
+    pub fn quantile(&self) -> f64 {
+        match self.len {
+            0 => f64::NAN,
+            len if len < 5 => {
+                let mut data = self.heights[..len as usize].to_vec();
+                data.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                let h = self.p * ((data.len() - 1) as f64);
+                let (lo, hi) = (h.floor() as usize, h.ceil() as usize);
+                data[lo] + (h - (lo as f64)) * (data[hi] - data[lo])
+            }
+            _ => self.heights[2],
+        }
+    }
+
+    /// Returns the estimate of the quantile.
+    ///
+    /// This is like `quantile`, except `None` is returned instead of `NaN`
+    /// when there are no samples.
+    pub fn try_quantile(&self) -> Option<f64> {
+        let x = self.quantile();
+        if x.is_nan() { None } else { Some(x) }
+    }
+
+    /// Returns the probability of the quantile being estimated.
+    pub fn probability(&self) -> f64 {
+        self.p
+    }
+
+    /// Returns the number of samples.
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Returns true if there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl fmt::Debug for P2Quantile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "q({}) ~ {:.10}", self.p, self.quantile())
+    }
+}
+
+impl<T: ToPrimitive> Extend<T> for P2Quantile {
+    fn extend<I: IntoIterator<Item=T>>(&mut self, it: I) {
+        for sample in it {
+            self.add(sample);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use unsorted::Unsorted;
+    use super::P2Quantile;
+
+    fn check(p: f64, data: Vec<f64>, tolerance: f64) {
+        let mut est = P2Quantile::new(p);
+        est.extend(data.iter().cloned());
+        let exact = data.into_iter().collect::<Unsorted<f64>>()
+                        .quantile(p).unwrap();
+        let approx = est.quantile();
+        assert!((exact - approx).abs() <= tolerance,
+                "p: {}, exact: {}, approx: {}", p, exact, approx);
+    }
+
+    #[test]
+    fn uniform() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let data: Vec<f64> = (0..100_000).map(|_| rng.gen()).collect();
+        for &p in &[0.1, 0.5, 0.9, 0.99] {
+            check(p, data.clone(), 0.01);
+        }
+    }
+
+    #[test]
+    fn exponential() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let data: Vec<f64> = (0..100_000)
+            .map(|_| -(1.0 - rng.gen::<f64>()).ln())
+            .collect();
+        check(0.5, data.clone(), 0.02);
+        check(0.9, data, 0.05);
+    }
+
+    #[test]
+    fn few_samples() {
+        let mut est = P2Quantile::new(0.5);
+        assert!(est.quantile().is_nan());
+        est.extend(vec![3.0, 1.0, f64::NAN, 2.0, 10.0]);
+        assert_eq!(est.len(), 4);
+        assert_eq!(est.quantile(), 2.5);
+    }
+
+    #[test]
+    fn try_quantile() {
+        let mut est = P2Quantile::new(0.5);
+        assert_eq!(est.try_quantile(), None);
+        est.extend(vec![3.0, 1.0, 2.0]);
+        assert_eq!(est.try_quantile(), Some(2.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn bad_probability() {
+        P2Quantile::new(1.0);
+    }
+}
@@ -0,0 +1,83 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+/// Computes a two sided p-value for `stat` with a permutation test.
+///
+/// The statistic is first computed on the original labeling `(a, b)`. The
+/// pooled data is then shuffled `iters` times, and each shuffle is split back
+/// into two groups with the same sizes as `a` and `b`. The p-value returned is
+/// the fraction of shuffles whose statistic is at least as extreme (in
+/// absolute value) as the observed statistic.
+///
+/// The result is fully determined by the state of `rng`, so a seeded RNG
+/// yields reproducible p-values.
+///
+/// If `iters` is `0`, then `NaN` is returned.
+///
+/// This is only available with the `rand` feature.
+pub fn permutation_test<T, F, R>(
+    a: &[T],
+    b: &[T],
+    stat: F,
+    iters: usize,
+    rng: &mut R,
+) -> f64
+        where T: Clone, F: Fn(&[T], &[T]) -> f64, R: Rng + ?Sized {
+    if iters == 0 {
+        return f64::NAN;
+    }
+    let observed = stat(a, b).abs();
+    let mut pooled: Vec<T> = a.iter().chain(b.iter()).cloned().collect();
+    let mut extreme = 0usize;
+    for _ in 0..iters {
+        pooled.shuffle(rng);
+        let (pa, pb) = pooled.split_at(a.len());
+        if stat(pa, pb).abs() >= observed {
+            extreme += 1;
+        }
+    }
+    extreme as f64 / iters as f64
+}
+
+#[cfg(test)]
+mod test {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::permutation_test;
+
+    fn mean_diff(a: &[f64], b: &[f64]) -> f64 {
+        let ma = a.iter().sum::<f64>() / a.len() as f64;
+        let mb = b.iter().sum::<f64>() / b.len() as f64;
+        ma - mb
+    }
+
+    #[test]
+    fn identical_groups() {
+        let a = vec![1.0, 2.0, 3.0, 4.0, 5.0];
+        let b = a.clone();
+        let mut rng = StdRng::seed_from_u64(0);
+        let p = permutation_test(&a, &b, mean_diff, 1000, &mut rng);
+        assert!(p > 0.95, "p-value too small: {}", p);
+    }
+
+    #[test]
+    fn separated_groups() {
+        let a = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+        let b = vec![101.0, 102.0, 103.0, 104.0, 105.0, 106.0];
+        let mut rng = StdRng::seed_from_u64(0);
+        let p = permutation_test(&a, &b, mean_diff, 1000, &mut rng);
+        assert!(p < 0.01, "p-value too large: {}", p);
+    }
+
+    #[test]
+    fn deterministic() {
+        let a = vec![1.0, 5.0, 2.0, 8.0];
+        let b = vec![3.0, 9.0, 4.0, 7.0, 6.0];
+        let p1 = permutation_test(
+            &a, &b, mean_diff, 500, &mut StdRng::seed_from_u64(42));
+        let p2 = permutation_test(
+            &a, &b, mean_diff, 500, &mut StdRng::seed_from_u64(42));
+        assert_eq!(p1, p2);
+    }
+}
@@ -0,0 +1,144 @@
+use std::default::Default;
+use std::fmt;
+use std::iter::{FromIterator, IntoIterator};
+
+use num_traits::ToPrimitive;
+
+use Commute;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A commutative data structure for the product of a stream of numbers.
+///
+/// The product is tracked directly, which can overflow to infinity or
+/// underflow to zero on long streams. To guard against this, the sum of the
+/// natural logarithms of each sample is tracked as well, which is available
+/// via `log_product`. This is useful for computing likelihoods, for example.
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Product {
+    len: u64,
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    product: f64,
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    log_sum: f64,
+}
+
+impl Product {
+    /// Create initial state with a product of `1`.
+    pub fn new() -> Product {
+        Default::default()
+    }
+
+    /// Multiply a sample into the product.
+    pub fn add<T: ToPrimitive>(&mut self, sample: T) {
+        let x = sample.to_f64().unwrap();
+        self.len += 1;
+        self.product *= x;
+        self.log_sum += x.ln();
+    }
+
+    /// Returns the product of all samples.
+    ///
+    /// The product of no samples is `1`.
+    pub fn product(&self) -> f64 {
+        self.product
+    }
+
+    /// Returns the natural logarithm of the product of all samples.
+    ///
+    /// This is computed as the sum of the logarithms of each sample, so it
+    /// does not overflow or underflow like `product` does. If any sample is
+    /// `0`, then negative infinity is returned. If any sample is negative,
+    /// then `NaN` is returned.
+    pub fn log_product(&self) -> f64 {
+        self.log_sum
+    }
+
+    /// Returns the number of samples.
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Returns true if there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Commute for Product {
+    fn merge(&mut self, v: Product) {
+        self.len += v.len;
+        self.product *= v.product;
+        self.log_sum += v.log_sum;
+    }
+}
+
+impl Default for Product {
+    fn default() -> Product {
+        Product { len: 0, product: 1.0, log_sum: 0.0 }
+    }
+}
+
+impl fmt::Debug for Product {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.product)
+    }
+}
+
+impl<T: ToPrimitive> FromIterator<T> for Product {
+    fn from_iter<I: IntoIterator<Item=T>>(it: I) -> Product {
+        let mut v = Product::new();
+        v.extend(it);
+        v
+    }
+}
+
+impl<T: ToPrimitive> Extend<T> for Product {
+    fn extend<I: IntoIterator<Item=T>>(&mut self, it: I) {
+        for sample in it {
+            self.add(sample);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use Commute;
+    use super::Product;
+
+    #[test]
+    fn merge() {
+        let mut p1: Product = vec![2u32, 3, 4].into_iter().collect();
+        let p2: Product = vec![0.5, 10.0].into_iter().collect();
+        p1.merge(p2);
+        assert_eq!(p1.product(), 120.0);
+        assert_eq!(p1.len(), 5);
+        assert!((p1.log_product() - 120f64.ln()).abs() < 1e-12);
+
+        let mut p: Product = vec![7u32].into_iter().collect();
+        p.merge(Product::new());
+        assert_eq!(p.product(), 7.0);
+    }
+
+    #[test]
+    fn log_product_small() {
+        let xs = [1e-300; 10];
+        let mut p1: Product = xs[..4].iter().cloned().collect();
+        let p2: Product = xs[4..].iter().cloned().collect();
+        p1.merge(p2);
+        assert_eq!(p1.product(), 0.0);
+        let expected = 10.0 * 1e-300f64.ln();
+        assert!((p1.log_product() - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn log_product_degenerate() {
+        let p: Product = vec![2.0, 0.0].into_iter().collect();
+        assert_eq!(p.log_product(), f64::NEG_INFINITY);
+        let p: Product = vec![2.0, -1.0].into_iter().collect();
+        assert!(p.log_product().is_nan());
+        assert_eq!(Product::new().product(), 1.0);
+    }
+}
@@ -0,0 +1,160 @@
+use std::default::Default;
+use std::fmt;
+use std::iter::{FromIterator, IntoIterator};
+
+use num_traits::ToPrimitive;
+
+use Commute;
+use covariance::Covariance;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Online state for a simple linear regression of `y` on `x`.
+///
+/// The ordinary least squares fit is derived from the means, second moments
+/// and co-moment of the pairs, which are tracked by a `Covariance` in
+/// constant space. No points are stored.
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LinearRegression {
+    cov: Covariance,
+}
+
+impl LinearRegression {
+    /// Create initial state with no pairs.
+    pub fn new() -> LinearRegression {
+        Default::default()
+    }
+
+    /// Add a new `(x, y)` point.
+    pub fn add<T: ToPrimitive>(&mut self, x: T, y: T) {
+        self.cov.add(x, y);
+    }
+
+    /// Return the slope of the fitted line.
+    ///
+    /// If there are fewer than two distinct `x` values, then `NaN` is
+    /// returned.
+    pub fn slope(&self) -> f64 {
+        let var_x = self.cov.variance_x();
+        if var_x == 0.0 {
+            return f64::NAN;
+        }
+        self.cov.covariance() / var_x
+    }
+
+    /// Return the intercept of the fitted line.
+    ///
+    /// If there are fewer than two distinct `x` values, then `NaN` is
+    /// returned.
+    pub fn intercept(&self) -> f64 {
+        self.cov.mean_y() - self.slope() * self.cov.mean_x()
+    }
+
+    /// Return the coefficient of determination of the fit.
+    ///
+    /// For a simple linear regression, this is the square of Pearson's
+    /// correlation coefficient. If either variable has a variance of `0`,
+    /// then `NaN` is returned.
+    pub fn r_squared(&self) -> f64 {
+        let r = self.cov.correlation();
+        r * r
+    }
+
+    /// Returns the number of points.
+    pub fn len(&self) -> usize {
+        self.cov.len()
+    }
+
+    /// Returns true if there are no points.
+    pub fn is_empty(&self) -> bool {
+        self.cov.is_empty()
+    }
+}
+
+impl Commute for LinearRegression {
+    fn merge(&mut self, v: LinearRegression) {
+        self.cov.merge(v.cov);
+    }
+}
+
+impl fmt::Debug for LinearRegression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "y = {:.10} * x + {:.10}", self.slope(), self.intercept())
+    }
+}
+
+impl<T: ToPrimitive> FromIterator<(T, T)> for LinearRegression {
+    fn from_iter<I: IntoIterator<Item=(T, T)>>(it: I) -> LinearRegression {
+        let mut v = LinearRegression::new();
+        v.extend(it);
+        v
+    }
+}
+
+impl<T: ToPrimitive> Extend<(T, T)> for LinearRegression {
+    fn extend<I: IntoIterator<Item=(T, T)>>(&mut self, it: I) {
+        for (x, y) in it {
+            self.add(x, y);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {Commute, merge_all};
+    use super::LinearRegression;
+
+    fn close(x: f64, y: f64) -> bool {
+        (x - y).abs() < 1e-9
+    }
+
+    #[test]
+    fn exact_line() {
+        let fit: LinearRegression =
+            (0..10).map(|x| (x as f64, 3.0 * x as f64 + 2.0)).collect();
+        assert!(close(fit.slope(), 3.0));
+        assert!(close(fit.intercept(), 2.0));
+        assert!(close(fit.r_squared(), 1.0));
+    }
+
+    #[test]
+    fn noisy_line() {
+        // Symmetric noise that sums to zero at every pair of points.
+        let noise = [0.5, -0.5];
+        let fit: LinearRegression = (0..100).map(|i| {
+            let x = (i / 2) as f64;
+            (x, 3.0 * x + 2.0 + noise[i % 2])
+        }).collect();
+        assert!(close(fit.slope(), 3.0));
+        assert!(close(fit.intercept(), 2.0));
+        assert!(fit.r_squared() < 1.0 && fit.r_squared() > 0.99);
+    }
+
+    #[test]
+    fn merge() {
+        let points: Vec<(f64, f64)> = (0..40).map(|i| {
+            let x = ((i * 7) % 13) as f64;
+            (x, 3.0 * x + 2.0 + ((i * 5) % 3) as f64 - 1.0)
+        }).collect();
+        let expected: LinearRegression = points.iter().cloned().collect();
+        let parts = points.chunks(6).map(|c| c.iter().cloned().collect());
+        let got: LinearRegression = merge_all(parts).unwrap();
+        assert!(close(got.slope(), expected.slope()));
+        assert!(close(got.intercept(), expected.intercept()));
+        assert!(close(got.r_squared(), expected.r_squared()));
+
+        let mut got = expected;
+        got.merge(LinearRegression::new());
+        assert_eq!(got.len(), 40);
+    }
+
+    #[test]
+    fn degenerate() {
+        assert!(LinearRegression::new().slope().is_nan());
+        let fit: LinearRegression = vec![(1, 2), (1, 5)].into_iter().collect();
+        assert!(fit.slope().is_nan());
+        assert!(fit.intercept().is_nan());
+    }
+}
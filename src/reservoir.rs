@@ -0,0 +1,186 @@
+use std::fmt;
+use std::iter::IntoIterator;
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use Commute;
+
+/// A uniform random sample of fixed size from a stream of data.
+///
+/// This implements Vitter's Algorithm R: the first `k` samples fill the
+/// reservoir, and each subsequent sample replaces a random member of the
+/// reservoir with probability `k / n`, where `n` is the number of samples
+/// seen so far. Every sample in the stream is therefore equally likely to be
+/// in the reservoir.
+///
+/// The random number generator is pluggable, so a seeded generator makes
+/// the sample reproducible.
+///
+/// This is only available with the `rand` feature.
+#[derive(Clone)]
+pub struct Reservoir<T, R> {
+    k: usize,
+    seen: u64,
+    sample: Vec<T>,
+    rng: R,
+}
+
+impl<T, R: Rng> Reservoir<T, R> {
+    /// Create an empty reservoir that holds at most `k` samples.
+    pub fn new(k: usize, rng: R) -> Reservoir<T, R> {
+        Reservoir { k, seen: 0, sample: Vec::with_capacity(k), rng }
+    }
+
+    /// Add a sample from the stream.
+    pub fn add(&mut self, v: T) {
+        self.seen += 1;
+        if self.sample.len() < self.k {
+            self.sample.push(v);
+        } else {
+            let j = self.rng.gen_range(0..self.seen);
+            if j < self.k as u64 {
+                self.sample[j as usize] = v;
+            }
+        }
+    }
+
+    /// Returns the current sample.
+    ///
+    /// The order of the sample is arbitrary.
+    pub fn sample(&self) -> &[T] {
+        &self.sample
+    }
+
+    /// Returns the maximum size of the sample.
+    pub fn capacity(&self) -> usize {
+        self.k
+    }
+
+    /// Returns the number of samples seen in the stream.
+    pub fn len(&self) -> usize {
+        self.seen as usize
+    }
+
+    /// Returns true if no samples have been seen.
+    pub fn is_empty(&self) -> bool {
+        self.seen == 0
+    }
+}
+
+impl<T, R: Rng> Commute for Reservoir<T, R> {
+    /// Merges two reservoirs into a uniform sample of both streams.
+    ///
+    /// Each slot of the merged reservoir is drawn from one of the two
+    /// reservoirs with probability proportional to the number of stream
+    /// samples it still represents. The random number generator of `self`
+    /// is used, and the generator of `other` is dropped.
+    fn merge(&mut self, other: Reservoir<T, R>) {
+        let mut a = ::std::mem::take(&mut self.sample);
+        let mut b = other.sample;
+        a.shuffle(&mut self.rng);
+        b.shuffle(&mut self.rng);
+        let (mut na, mut nb) = (self.seen, other.seen);
+        let k = self.k.min(a.len() + b.len());
+        while self.sample.len() < k {
+            let from_a = if a.is_empty() {
+                false
+            } else if b.is_empty() {
+                true
+            } else {
+                self.rng.gen_range(0..na + nb) < na
+            };
+            if from_a {
+                self.sample.push(a.pop().unwrap());
+                na -= 1;
+            } else {
+                self.sample.push(b.pop().unwrap());
+                nb -= 1;
+            }
+        }
+        self.seen += other.seen;
+    }
+}
+
+impl<T: fmt::Debug, R> fmt::Debug for Reservoir<T, R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.sample)
+    }
+}
+
+impl<T, R: Rng> Extend<T> for Reservoir<T, R> {
+    fn extend<I: IntoIterator<Item=T>>(&mut self, it: I) {
+        for v in it {
+            self.add(v);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use Commute;
+    use super::Reservoir;
+
+    fn reservoir(seed: u64) -> Reservoir<u32, StdRng> {
+        let mut r = Reservoir::new(10, StdRng::seed_from_u64(seed));
+        r.extend(0..1000);
+        r
+    }
+
+    #[test]
+    fn deterministic() {
+        let (r1, r2) = (reservoir(7), reservoir(7));
+        assert_eq!(r1.sample(), r2.sample());
+        assert_eq!(r1.sample().len(), 10);
+        assert_eq!(r1.len(), 1000);
+        assert!(r1.sample().iter().all(|&x| x < 1000));
+        assert_ne!(r1.sample(), &(0..10).collect::<Vec<u32>>()[..]);
+    }
+
+    #[test]
+    fn small_stream() {
+        let mut r = Reservoir::new(10, StdRng::seed_from_u64(0));
+        r.extend(vec![3, 1, 2]);
+        assert_eq!(r.sample(), &[3, 1, 2]);
+    }
+
+    #[test]
+    fn merge() {
+        let mut r1 = Reservoir::new(10, StdRng::seed_from_u64(1));
+        r1.extend(0..500u32);
+        let mut r2 = Reservoir::new(10, StdRng::seed_from_u64(2));
+        r2.extend(500..2000u32);
+        r1.merge(r2);
+        assert_eq!(r1.len(), 2000);
+        assert_eq!(r1.sample().len(), 10);
+
+        let mut small = Reservoir::new(10, StdRng::seed_from_u64(3));
+        small.extend(vec![1, 2, 3]);
+        let mut other = Reservoir::new(10, StdRng::seed_from_u64(4));
+        other.extend(vec![4, 5]);
+        small.merge(other);
+        let mut got = small.sample().to_vec();
+        got.sort();
+        assert_eq!(got, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn merge_is_proportional() {
+        // The first stream is 9 times larger than the second, so it should
+        // contribute about 90% of the merged sample.
+        let mut from_first = 0;
+        for seed in 0..200 {
+            let mut r1 = Reservoir::new(10, StdRng::seed_from_u64(seed));
+            r1.extend(0..900u32);
+            let mut r2 = Reservoir::new(10, StdRng::seed_from_u64(!seed));
+            r2.extend(900..1000u32);
+            r1.merge(r2);
+            from_first += r1.sample().iter().filter(|&&x| x < 900).count();
+        }
+        let frac = from_first as f64 / 2000.0;
+        assert!(frac > 0.85 && frac < 0.95, "fraction: {}", frac);
+    }
+}
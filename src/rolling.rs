@@ -0,0 +1,399 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use std::fmt;
+use std::iter::IntoIterator;
+
+use num_traits::ToPrimitive;
+
+use Partial;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Statistics over a sliding window of the most recent samples.
+///
+/// The last `size` samples are kept in a ring buffer. The mean and variance
+/// are updated in `O(1)` as new samples push old ones out of the window, and
+/// the minimum and maximum are tracked with monotonic queues, so they are
+/// available in `O(1)` amortized time.
+///
+/// Since the window depends on the order in which samples are added, this
+/// type does not implement `Commute`.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RollingStats {
+    size: usize,
+    buf: VecDeque<f64>,
+    /// The number of samples ever added, used to expire extremes.
+    seen: u64,
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    mean: f64,
+    /// The sum of squared differences from the mean of the window.
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    m2: f64,
+    /// Candidates for the minimum, with increasing values.
+    mins: VecDeque<(u64, f64)>,
+    /// Candidates for the maximum, with decreasing values.
+    maxs: VecDeque<(u64, f64)>,
+}
+
+impl RollingStats {
+    /// Create an empty window that holds at most `size` samples.
+    ///
+    /// This panics if `size` is `0`.
+    pub fn new(size: usize) -> RollingStats {
+        assert!(size > 0, "window size must be positive");
+        RollingStats {
+            size,
+            buf: VecDeque::with_capacity(size),
+            seen: 0,
+            mean: 0.0,
+            m2: 0.0,
+            mins: VecDeque::new(),
+            maxs: VecDeque::new(),
+        }
+    }
+
+    /// Add a new sample, evicting the oldest sample if the window is full.
+    ///
+    /// `NaN` samples are not supported.
+    pub fn add<T: ToPrimitive>(&mut self, sample: T) {
+        let x = sample.to_f64().unwrap();
+        if self.buf.len() == self.size {
+            // Replace the oldest sample with the new one in a single step.
+            let old = self.buf.pop_front().unwrap();
+            let oldmean = self.mean;
+            self.mean += (x - old) / (self.size as f64);
+            self.m2 += (x - old) * (x - self.mean + old - oldmean);
+        } else {
+            let oldmean = self.mean;
+            self.mean += (x - oldmean) / ((self.buf.len() + 1) as f64);
+            self.m2 += (x - oldmean) * (x - self.mean);
+        }
+        self.buf.push_back(x);
+
+        let index = self.seen;
+        self.seen += 1;
+        while self.mins.back().map(|&(_, y)| y >= x).unwrap_or(false) {
+            self.mins.pop_back();
+        }
+        self.mins.push_back((index, x));
+        while self.maxs.back().map(|&(_, y)| y <= x).unwrap_or(false) {
+            self.maxs.pop_back();
+        }
+        self.maxs.push_back((index, x));
+
+        let size = self.size as u64;
+        if self.mins.front().map(|&(i, _)| i + size <= index).unwrap_or(false) {
+            self.mins.pop_front();
+        }
+        if self.maxs.front().map(|&(i, _)| i + size <= index).unwrap_or(false) {
+            self.maxs.pop_front();
+        }
+    }
+
+    /// Returns the mean of the window.
+    ///
+    /// If the window is empty, then `NaN` is returned.
+    pub fn mean(&self) -> f64 {
+        if self.buf.is_empty() { f64::NAN } else { self.mean }
+    }
+
+    /// Returns the population variance of the window.
+    ///
+    /// If the window is empty, then `NaN` is returned.
+    pub fn variance(&self) -> f64 {
+        if self.buf.is_empty() {
+            return f64::NAN;
+        }
+        // Rounding error from evictions can push this slightly below zero.
+        (self.m2 / (self.buf.len() as f64)).max(0.0)
+    }
+
+    /// Returns the population standard deviation of the window.
+    ///
+    /// If the window is empty, then `NaN` is returned.
+    pub fn stddev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    /// Returns the minimum of the window.
+    pub fn min(&self) -> Option<f64> {
+        self.mins.front().map(|&(_, x)| x)
+    }
+
+    /// Returns the maximum of the window.
+    pub fn max(&self) -> Option<f64> {
+        self.maxs.front().map(|&(_, x)| x)
+    }
+
+    /// Returns the maximum number of samples in the window.
+    pub fn window_size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the number of samples currently in the window.
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns true if the window is empty.
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+}
+
+impl fmt::Debug for RollingStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.buf)
+    }
+}
+
+impl<T: ToPrimitive> Extend<T> for RollingStats {
+    fn extend<I: IntoIterator<Item=T>>(&mut self, it: I) {
+        for sample in it {
+            self.add(sample);
+        }
+    }
+}
+
+/// A sample in a `RollingMedian` heap, tagged with its position in the
+/// stream so that it can be expired and so that ties are broken.
+type Entry = (Partial<f64>, u64);
+
+/// The median of a sliding window of the most recent samples.
+///
+/// The window is split into two heaps: a max-heap holding the lower half and
+/// a min-heap holding the upper half, so that the median is always at the
+/// top of one or both of them. Adding a sample takes `O(log N)` time and the
+/// median is available in `O(1)` time.
+///
+/// Samples that leave the window are removed lazily: they stay in their heap
+/// until they reach its top or the heap is compacted. The heaps never hold
+/// more than a constant multiple of the window size.
+///
+/// Since the window depends on the order in which samples are added, this
+/// type does not implement `Commute`.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RollingMedian {
+    size: usize,
+    buf: VecDeque<f64>,
+    /// The number of samples ever added, used to expire samples.
+    seen: u64,
+    /// The lower half of the window.
+    lower: BinaryHeap<Entry>,
+    /// The upper half of the window.
+    upper: BinaryHeap<Reverse<Entry>>,
+    /// The number of unexpired samples in `lower`.
+    lower_len: usize,
+    /// The number of unexpired samples in `upper`.
+    upper_len: usize,
+}
+
+impl RollingMedian {
+    /// Create an empty window that holds at most `size` samples.
+    ///
+    /// This panics if `size` is `0`.
+    pub fn new(size: usize) -> RollingMedian {
+        assert!(size > 0, "window size must be positive");
+        RollingMedian {
+            size,
+            buf: VecDeque::with_capacity(size),
+            seen: 0,
+            lower: BinaryHeap::new(),
+            upper: BinaryHeap::new(),
+            lower_len: 0,
+            upper_len: 0,
+        }
+    }
+
+    /// Add a new sample, evicting the oldest sample if the window is full.
+    ///
+    /// `NaN` samples are not supported.
+    pub fn add<T: ToPrimitive>(&mut self, sample: T) {
+        let x = sample.to_f64().unwrap();
+        if self.buf.len() == self.size {
+            let old = (Partial(self.buf.pop_front().unwrap()),
+                       self.seen - self.size as u64);
+            // Every unexpired sample in `lower` is at most its top, which is
+            // itself unexpired.
+            if self.lower.peek().is_some_and(|top| old <= *top) {
+                self.lower_len -= 1;
+            } else {
+                self.upper_len -= 1;
+            }
+        }
+        self.buf.push_back(x);
+        let entry = (Partial(x), self.seen);
+        self.seen += 1;
+        self.prune();
+
+        // The lower half may be empty after an eviction, so compare with
+        // the smallest sample in the upper half instead.
+        if self.upper.peek().is_none_or(|Reverse(top)| entry <= *top) {
+            self.lower.push(entry);
+            self.lower_len += 1;
+        } else {
+            self.upper.push(Reverse(entry));
+            self.upper_len += 1;
+        }
+        if self.lower_len > self.upper_len + 1 {
+            let top = self.lower.pop().unwrap();
+            self.upper.push(Reverse(top));
+            self.lower_len -= 1;
+            self.upper_len += 1;
+        } else if self.upper_len > self.lower_len {
+            let Reverse(top) = self.upper.pop().unwrap();
+            self.lower.push(top);
+            self.upper_len -= 1;
+            self.lower_len += 1;
+        }
+        self.prune();
+        self.compact();
+    }
+
+    /// Returns the median of the window.
+    ///
+    /// When the window holds an even number of samples, this is the mean of
+    /// the two middle samples.
+    pub fn median(&self) -> Option<f64> {
+        let lo = (self.lower.peek()?.0).0;
+        if self.lower_len > self.upper_len {
+            return Some(lo);
+        }
+        let Reverse((Partial(hi), _)) = *self.upper.peek()?;
+        Some((lo + hi) / 2.0)
+    }
+
+    /// Returns the maximum number of samples in the window.
+    pub fn window_size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the number of samples currently in the window.
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns true if the window is empty.
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Returns the position of the oldest sample still in the window.
+    fn cutoff(&self) -> u64 {
+        self.seen - self.buf.len() as u64
+    }
+
+    /// Removes expired samples from the top of both heaps.
+    fn prune(&mut self) {
+        let cutoff = self.cutoff();
+        while self.lower.peek().is_some_and(|&(_, i)| i < cutoff) {
+            self.lower.pop();
+        }
+        while self.upper.peek().is_some_and(|&Reverse((_, i))| i < cutoff) {
+            self.upper.pop();
+        }
+    }
+
+    /// Removes every expired sample once a heap holds too many of them.
+    fn compact(&mut self) {
+        let cutoff = self.cutoff();
+        if self.lower.len() > 2 * self.size {
+            self.lower.retain(|&(_, i)| i >= cutoff);
+        }
+        if self.upper.len() > 2 * self.size {
+            self.upper.retain(|&Reverse((_, i))| i >= cutoff);
+        }
+    }
+}
+
+impl fmt::Debug for RollingMedian {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.buf)
+    }
+}
+
+impl<T: ToPrimitive> Extend<T> for RollingMedian {
+    fn extend<I: IntoIterator<Item=T>>(&mut self, it: I) {
+        for sample in it {
+            self.add(sample);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use OnlineStats;
+    use unsorted::median;
+    use super::{RollingMedian, RollingStats};
+
+    fn data() -> Vec<f64> {
+        (0..60).map(|i| ((i * 7919) % 97) as f64 / 4.0 - 10.0).collect()
+    }
+
+    #[test]
+    fn window() {
+        let data = data();
+        let size = 8;
+        let mut rolling = RollingStats::new(size);
+        for (i, &x) in data.iter().enumerate() {
+            rolling.add(x);
+            let window = &data[(i + 1).saturating_sub(size)..i + 1];
+            let expected = OnlineStats::from_slice(window);
+            assert_eq!(rolling.len(), window.len());
+            assert!((rolling.mean() - expected.mean()).abs() < 1e-9);
+            assert!((rolling.variance() - expected.variance()).abs() < 1e-9);
+            let min = window.iter().cloned().fold(f64::INFINITY, f64::min);
+            let max = window.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            assert_eq!(rolling.min(), Some(min));
+            assert_eq!(rolling.max(), Some(max));
+        }
+    }
+
+    #[test]
+    fn evicts_extremes() {
+        let mut rolling = RollingStats::new(3);
+        assert_eq!(rolling.min(), None);
+        assert!(rolling.mean().is_nan());
+        rolling.extend(vec![100, 1, 2, 3]);
+        assert_eq!(rolling.max(), Some(3.0));
+        assert_eq!(rolling.min(), Some(1.0));
+        assert!((rolling.mean() - 2.0).abs() < 1e-9);
+        rolling.add(4);
+        assert_eq!(rolling.min(), Some(2.0));
+        assert_eq!(rolling.len(), 3);
+    }
+
+    #[test]
+    fn median_window() {
+        let mut data = data();
+        // Add some ties.
+        data.extend(vec![1.0, 1.0, 1.0, 2.0, 2.0, 1.0, 1.0]);
+        for size in &[1, 2, 3, 5, 8, 13] {
+            let mut rolling = RollingMedian::new(*size);
+            assert_eq!(rolling.median(), None);
+            for (i, &x) in data.iter().enumerate() {
+                rolling.add(x);
+                let window = &data[(i + 1).saturating_sub(*size)..i + 1];
+                assert_eq!(rolling.len(), window.len());
+                assert_eq!(rolling.median(),
+                           median(window.iter().cloned()),
+                           "size: {}, window: {:?}", size, window);
+            }
+        }
+    }
+
+    #[test]
+    fn median_bounded_heaps() {
+        // Increasing samples always expire from the bottom of the lower
+        // heap, so they are only removed by compaction.
+        let mut rolling = RollingMedian::new(4);
+        for i in 0..1000 {
+            rolling.add(i);
+            assert!(rolling.lower.len() + rolling.upper.len() <= 4 * 4 + 2);
+        }
+        assert_eq!(rolling.median(), Some(997.5));
+    }
+}
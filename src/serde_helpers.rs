@@ -0,0 +1,333 @@
+/// Serializes a float so that non-finite values survive a round trip
+/// through formats like JSON.
+///
+/// JSON has no representation for `NaN` or the infinities, and
+/// `serde_json` writes them as `null`, which then fails to deserialize as a
+/// float. In human readable formats, non-finite values are written as the
+/// strings `"NaN"`, `"inf"` and `"-inf"` instead. Every other value, and
+/// every value in a binary format, is written as a plain `f64`, so state
+/// serialized before this was used still deserializes.
+pub mod float {
+    use std::fmt;
+
+    use num_traits::Float;
+    use serde::de::{self, Deserializer, Visitor};
+    use serde::Serializer;
+
+    pub fn serialize<F, S>(x: &F, s: S) -> Result<S::Ok, S::Error>
+            where F: Float, S: Serializer {
+        let x = x.to_f64().unwrap();
+        if x.is_finite() || !s.is_human_readable() {
+            s.serialize_f64(x)
+        } else if x.is_nan() {
+            s.serialize_str("NaN")
+        } else if x > 0.0 {
+            s.serialize_str("inf")
+        } else {
+            s.serialize_str("-inf")
+        }
+    }
+
+    pub fn deserialize<'de, F, D>(d: D) -> Result<F, D::Error>
+            where F: Float, D: Deserializer<'de> {
+        let x = if d.is_human_readable() {
+            d.deserialize_any(FloatVisitor)?
+        } else {
+            d.deserialize_f64(FloatVisitor)?
+        };
+        Ok(F::from(x).unwrap())
+    }
+
+    struct FloatVisitor;
+
+    impl<'de> Visitor<'de> for FloatVisitor {
+        type Value = f64;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "a number, \"NaN\", \"inf\" or \"-inf\"")
+        }
+
+        fn visit_f64<E: de::Error>(self, x: f64) -> Result<f64, E> {
+            Ok(x)
+        }
+
+        fn visit_i64<E: de::Error>(self, x: i64) -> Result<f64, E> {
+            Ok(x as f64)
+        }
+
+        fn visit_u64<E: de::Error>(self, x: u64) -> Result<f64, E> {
+            Ok(x as f64)
+        }
+
+        fn visit_str<E: de::Error>(self, s: &str) -> Result<f64, E> {
+            match s {
+                "NaN" => Ok(f64::NAN),
+                "inf" => Ok(f64::INFINITY),
+                "-inf" => Ok(f64::NEG_INFINITY),
+                _ => Err(E::invalid_value(de::Unexpected::Str(s), &self)),
+            }
+        }
+    }
+}
+
+/// Serializes a `HashMap` as a sequence of `(key, value)` entries.
+///
+/// Formats like JSON only allow strings as map keys, so a map keyed by
+/// tuples, floats or other compound values can't be written as a map.
+/// A sequence of entries works for any key type.
+pub mod entries {
+    use std::collections::HashMap;
+    use std::hash::{BuildHasher, Hash};
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<K, V, H, S>(
+        map: &HashMap<K, V, H>,
+        s: S,
+    ) -> Result<S::Ok, S::Error>
+            where K: Serialize, V: Serialize, S: Serializer {
+        s.collect_seq(map.iter())
+    }
+
+    pub fn deserialize<'de, K, V, H, D>(
+        d: D,
+    ) -> Result<HashMap<K, V, H>, D::Error>
+            where K: Deserialize<'de> + Eq + Hash,
+                  V: Deserialize<'de>,
+                  H: BuildHasher + Default,
+                  D: Deserializer<'de> {
+        let entries: Vec<(K, V)> = Vec::deserialize(d)?;
+        Ok(entries.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use serde_json;
+
+    use {
+        AllMeans, BloomFilter, Commute, Covariance, DistinctSet, Ewma,
+        Frequencies, GKSummary, GeometricMean, HarmonicMean, HeavyHitters,
+        Histogram, HyperLogLog, JointFrequencies, LinearRegression, MinMax,
+        OnlineStats, OnlineStatsF32, P2Quantile, Partial, Product,
+        RollingMedian, RollingStats, Sorted, Sum, SumByKey, Summary, TDigest,
+        TimeSeries, Unsorted, WeightedCount, WeightedFrequencies,
+        WeightedMean, WeightedUnsorted, WeightedVariance,
+    };
+
+    fn roundtrip<T: Serialize + DeserializeOwned>(v: &T) -> T {
+        let json = serde_json::to_string(v).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    /// Asserts that two floats are identical, including `NaN`.
+    fn same(x: f64, y: f64) {
+        assert!(x == y || (x.is_nan() && y.is_nan()), "{} != {}", x, y);
+    }
+
+    #[test]
+    fn non_finite_floats() {
+        let mut product = Product::new();
+        product.extend(vec![1e200, 1e200, 0.0]);
+        assert!(product.product().is_nan());
+        let got = roundtrip(&product);
+        same(got.product(), product.product());
+        same(got.log_product(), f64::NEG_INFINITY);
+
+        let mut overflow = Product::new();
+        overflow.extend(vec![1e200, 1e200]);
+        assert_eq!(roundtrip(&overflow).product(), f64::INFINITY);
+
+        let json = serde_json::to_string(&TDigest::new(100.0)).unwrap();
+        assert!(json.contains("\"inf\"") && json.contains("\"-inf\""));
+        let mut digest: TDigest = serde_json::from_str(&json).unwrap();
+        digest.extend(vec![3.0, 1.0, 2.0]);
+        assert_eq!(digest.quantile(0.0), 1.0);
+        assert_eq!(digest.quantile(1.0), 3.0);
+
+        let parts = OnlineStats::from_parts(3, 2.0, 1.5);
+        let got = roundtrip(&parts);
+        assert_eq!(got.parts(), parts.parts());
+        assert!(got.skewness().is_nan());
+    }
+
+    #[test]
+    fn float_formats() {
+        let stats = OnlineStats::from_slice(&[1, 2, 3]);
+        let json = serde_json::to_string(&stats).unwrap();
+        assert!(json.contains("\"mean\":2.0"), "{}", json);
+        let bad = json.replace("\"mean\":2.0", "\"mean\":\"two\"");
+        assert!(serde_json::from_str::<OnlineStats>(&bad).is_err());
+        let int = json.replace("\"mean\":2.0", "\"mean\":2");
+        let got: OnlineStats = serde_json::from_str(&int).unwrap();
+        assert_eq!(got.mean(), 2.0);
+    }
+
+    #[test]
+    fn moments() {
+        let data = [1.5, -2.0, 8.25, 3.0, 3.0, 0.5];
+        let stats = OnlineStats::from_slice(&data);
+        let mut got = roundtrip(&stats);
+        assert_eq!(got.parts(), stats.parts());
+        assert_eq!(got.kurtosis(), stats.kurtosis());
+        got.merge(OnlineStats::from_slice(&[4.0]));
+        assert_eq!(got.len(), 7);
+
+        let stats32: OnlineStatsF32 = data.iter().cloned().collect();
+        assert_eq!(roundtrip(&stats32).parts(), stats32.parts());
+
+        let summary: Summary = data.iter().cloned().collect();
+        let got = roundtrip(&summary);
+        assert_eq!(got.to_string(), summary.to_string());
+
+        let mut cov = Covariance::new();
+        let mut reg = LinearRegression::new();
+        for (i, &y) in data.iter().enumerate() {
+            cov.add(i as f64, y);
+            reg.add(i as f64, y);
+        }
+        assert_eq!(roundtrip(&cov).correlation(), cov.correlation());
+        assert_eq!(roundtrip(&reg).slope(), reg.slope());
+
+        let mut ewma = Ewma::new(0.3);
+        ewma.extend(data.iter().cloned());
+        let got = roundtrip(&ewma);
+        assert_eq!(got.mean(), ewma.mean());
+        assert_eq!(got.variance(), ewma.variance());
+        same(roundtrip(&Ewma::new(0.3)).mean(), f64::NAN);
+    }
+
+    #[test]
+    fn means_and_sums() {
+        let data = [1.5, 2.0, 8.25, 3.0];
+        let all: AllMeans = data.iter().cloned().collect();
+        let got = roundtrip(&all);
+        assert_eq!(got.harmonic_mean(), all.harmonic_mean());
+        assert_eq!(got.geometric_mean(), all.geometric_mean());
+        let gm: GeometricMean = data.iter().cloned().collect();
+        assert_eq!(roundtrip(&gm).geometric_mean(), gm.geometric_mean());
+        let hm: HarmonicMean = data.iter().cloned().collect();
+        assert_eq!(roundtrip(&hm).harmonic_mean(), hm.harmonic_mean());
+        let sum: Sum = data.iter().cloned().collect();
+        assert_eq!(roundtrip(&sum).total(), sum.total());
+
+        let mut wc = WeightedCount::new();
+        let mut wm = WeightedMean::new();
+        let mut wv = WeightedVariance::new();
+        for (i, &x) in data.iter().enumerate() {
+            wc.add(i + 1);
+            wm.add(x, i + 1);
+            wv.add(x, i + 1);
+        }
+        assert_eq!(roundtrip(&wc).total_weight(), wc.total_weight());
+        assert_eq!(roundtrip(&wm).mean(), wm.mean());
+        assert_eq!(roundtrip(&wv).variance(), wv.variance());
+    }
+
+    #[test]
+    fn order_statistics() {
+        let data = vec![5.0, 1.0, 3.0, 3.0, 9.0, 7.5];
+        let mut minmax: MinMax<f64> = data.iter().cloned().collect();
+        let mut got = roundtrip(&minmax);
+        assert_eq!((got.min(), got.max()), (minmax.min(), minmax.max()));
+        got.merge(vec![-1.0].into_iter().collect());
+        minmax.add(-1.0);
+        assert_eq!(got.min(), minmax.min());
+
+        let mut unsorted: Unsorted<f64> = data.iter().cloned().collect();
+        assert_eq!(roundtrip(&unsorted).median(), unsorted.median());
+        let sorted: Sorted<f64> = data.iter().cloned().collect();
+        assert_eq!(roundtrip(&sorted).median(), sorted.median());
+        let mut weighted: WeightedUnsorted<f64> =
+            data.iter().map(|&x| (x, x)).collect();
+        assert_eq!(roundtrip(&weighted).median(), weighted.median());
+
+        let mut p2 = P2Quantile::new(0.5);
+        let mut gk = GKSummary::new(0.01);
+        let mut digest = TDigest::new(100.0);
+        let mut hist = Histogram::new(0.0, 10.0, 5);
+        for &x in &data {
+            p2.add(x);
+            gk.add(x);
+            digest.add(x);
+            hist.add(x);
+        }
+        assert_eq!(roundtrip(&p2).quantile(), p2.quantile());
+        assert_eq!(roundtrip(&gk).query(0.5), gk.query(0.5));
+        assert_eq!(roundtrip(&digest).quantile(0.5), digest.quantile(0.5));
+        assert_eq!(roundtrip(&hist).counts(), hist.counts());
+
+        let mut rolling = RollingStats::new(3);
+        let mut rmedian = RollingMedian::new(3);
+        let mut series = TimeSeries::new();
+        for &x in &data {
+            rolling.add(x);
+            rmedian.add(x);
+            series.add(x);
+        }
+        let mut got = roundtrip(&rolling);
+        assert_eq!((got.mean(), got.min()), (rolling.mean(), rolling.min()));
+        got.add(0.0);
+        rolling.add(0.0);
+        assert_eq!((got.mean(), got.max()), (rolling.mean(), rolling.max()));
+        let mut got = roundtrip(&rmedian);
+        got.add(20.0);
+        rmedian.add(20.0);
+        assert_eq!(got.median(), rmedian.median());
+        assert_eq!(roundtrip(&series).as_slice(), series.as_slice());
+
+        let x: Partial<f64> = roundtrip(&Partial(2.5));
+        assert_eq!(x.into_inner(), 2.5);
+    }
+
+    #[test]
+    fn compound_keys() {
+        let pairs = vec![(1u32, 'a'), (1, 'b'), (2, 'a'), (1, 'a')];
+        let joint: JointFrequencies<u32, char> =
+            pairs.iter().cloned().collect();
+        let mut got = roundtrip(&joint);
+        assert_eq!(got.count(&1, &'a'), 2);
+        // The sums depend on the iteration order of the deserialized maps.
+        assert!((got.cramers_v() - joint.cramers_v()).abs() < 1e-12);
+        got.merge(joint);
+        assert_eq!(got.count(&2, &'a'), 2);
+
+        let freqs: Frequencies<(u32, char)> = pairs.iter().cloned().collect();
+        assert_eq!(roundtrip(&freqs).count(&(1, 'a')), 2);
+        let sums: SumByKey<(u32, char), f64> =
+            pairs.iter().map(|&p| (p, 0.5)).collect();
+        assert_eq!(roundtrip(&sums).sum(&(1, 'a')), 1.0);
+        let weighted: WeightedFrequencies<(u32, char)> =
+            pairs.iter().map(|&p| (p, 0.25)).collect();
+        assert_eq!(roundtrip(&weighted).weight(&(1, 'a')), 0.5);
+        let mut hitters = HeavyHitters::new(2);
+        for &p in &pairs {
+            hitters.add(p);
+        }
+        let got = roundtrip(&hitters);
+        assert_eq!(got.count(&(1, 'a')), hitters.count(&(1, 'a')));
+        assert_eq!(got.items(), hitters.items());
+    }
+
+    #[test]
+    fn sets_and_sketches() {
+        let set: DistinctSet<(u32, u32)> =
+            (0..10).map(|i| (i, i % 3)).collect();
+        let got = roundtrip(&set);
+        assert_eq!(got.len(), 10);
+        assert!(got.contains(&(4, 1)));
+
+        let mut bloom = BloomFilter::new(256, 3);
+        let mut hll = HyperLogLog::new(8);
+        for i in 0..100u32 {
+            bloom.add(&i);
+            hll.add(&i);
+        }
+        let mut got = roundtrip(&bloom);
+        assert!((0..100u32).all(|i| got.maybe_contains(&i)));
+        got.merge(bloom);
+        assert_eq!(roundtrip(&hll).estimate(), hll.estimate());
+    }
+}
@@ -1,11 +1,15 @@
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, HashMap};
 use std::default::Default;
+use std::hash::Hash;
 use std::iter::{FromIterator, IntoIterator};
 
 use num_traits::ToPrimitive;
 
 use {Commute, Partial};
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 pub fn median_on_sorted<T>(data: &[T]) -> Option<f64>
         where T: PartialOrd + ToPrimitive {
     Some(match data.len() {
@@ -26,43 +30,76 @@ pub fn mode_on_sorted<T, I>(it: I) -> Option<T>
         where T: PartialOrd, I: Iterator<Item=T> {
     // This approach to computing the mode works very nicely when the
     // number of samples is large and is close to its cardinality.
-    // In other cases, a hashmap would be much better.
-    // But really, how can we know this when given an arbitrary stream?
-    // Might just switch to a hashmap to track frequencies. That would also
-    // be generally useful for discovering the cardinality of a sample.
-    let (mut mode, mut next) = (None, None);
-    let (mut mode_count, mut next_count) = (0usize, 0usize);
+    // In other cases, a hashmap would be much better, which is what
+    // `Sorted::mode_fast` uses for types that can be hashed.
+    let (mut mode, mut mode_count, mut tied) = (None, 1usize, false);
+    let (mut run, mut run_count) = (None, 0usize);
     for x in it {
-        if mode.as_ref().map(|y| y == &x).unwrap_or(false) {
-            mode_count += 1;
-        } else if next.as_ref().map(|y| y == &x).unwrap_or(false) {
-            next_count += 1;
-        } else {
-            next = Some(x);
-            next_count = 0;
+        if run.as_ref().is_some_and(|y| y == &x) {
+            run_count += 1;
+            continue;
         }
-
-        if next_count > mode_count {
-            mode = next;
-            mode_count = next_count;
-            next = None;
-            next_count = 0;
-        } else if next_count == mode_count {
-            mode = None;
-            mode_count = 0usize;
+        if run_count > mode_count {
+            mode = run;
+            mode_count = run_count;
+            tied = false;
+        } else if run_count == mode_count {
+            tied = true;
         }
+        run = Some(x);
+        run_count = 1;
+    }
+    if run_count > mode_count {
+        mode = run;
+        tied = false;
+    } else if run_count == mode_count {
+        tied = true;
     }
-    mode
+    // A value that occurs only once is never the mode.
+    if tied { None } else { mode }
 }
 
 /// A commutative data structure for sorted sequences of data.
 ///
 /// Note that this works on types that do not define a total ordering like
-/// `f32` and `f64`. Then an ordering is not defined, an arbitrary order
-/// is returned.
+/// `f32` and `f64`. The data is sorted with the ordering of `Partial`, so
+/// `NaN` values sort after every other value.
+///
+/// The largest and smallest elements are available in `O(1)` time without
+/// sorting.
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(
+    bound(deserialize = "T: Deserialize<'de> + PartialOrd"),
+    from = "SortedRepr<T>",
+))]
 pub struct Sorted<T> {
+    /// Every element except for the smallest one.
     data: BinaryHeap<Partial<T>>,
+    /// The smallest element, which is kept out of the heap so that it can
+    /// be found without cloning each new minimum.
+    min: Option<Partial<T>>,
+}
+
+/// The serialized shape of `Sorted`.
+///
+/// Serialized data can't be trusted to keep the smallest element out of
+/// the heap, and data written before `min` existed has no `min` at all, so
+/// every element is added again instead.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+#[serde(bound = "T: Deserialize<'de>")]
+struct SortedRepr<T> {
+    data: Vec<Partial<T>>,
+    #[serde(default)]
+    min: Option<Partial<T>>,
+}
+
+#[cfg(feature = "serde")]
+impl<T: PartialOrd> From<SortedRepr<T>> for Sorted<T> {
+    fn from(repr: SortedRepr<T>) -> Sorted<T> {
+        repr.data.into_iter().chain(repr.min).map(|p| p.0).collect()
+    }
 }
 
 impl<T: PartialOrd> Sorted<T> {
@@ -71,46 +108,146 @@ impl<T: PartialOrd> Sorted<T> {
         Default::default()
     }
 
+    /// Create initial empty state with room for `capacity` data points
+    /// before reallocating.
+    pub fn with_capacity(capacity: usize) -> Sorted<T> {
+        Sorted { data: BinaryHeap::with_capacity(capacity), min: None }
+    }
+
     /// Add a new element to the set.
     pub fn add(&mut self, v: T) {
-        self.data.push(Partial(v))
+        let v = Partial(v);
+        match self.min {
+            Some(ref mut min) if v < *min => {
+                let old = ::std::mem::replace(min, v);
+                self.data.push(old);
+            }
+            Some(_) => self.data.push(v),
+            None => self.min = Some(v),
+        }
     }
 
     /// Returns the number of data points.
     pub fn len(&self) -> usize {
-        self.data.len()
+        self.data.len() + self.min.is_some() as usize
+    }
+
+    /// Returns true if there are no data points.
+    pub fn is_empty(&self) -> bool {
+        self.min.is_none()
+    }
+
+    /// Returns the largest element, in `O(1)` time.
+    pub fn peek_max(&self) -> Option<&T> {
+        self.data.peek().or(self.min.as_ref()).map(|p| &p.0)
+    }
+
+    /// Returns the smallest element, in `O(1)` time.
+    pub fn peek_min(&self) -> Option<&T> {
+        self.min.as_ref().map(|p| &p.0)
+    }
+
+    /// Returns an iterator over the data in ascending order.
+    ///
+    /// The data itself is kept in a heap, so this sorts references to it,
+    /// which takes `O(n log n)` time and `O(n)` extra memory. The elements
+    /// are not cloned.
+    pub fn iter_sorted(&self) -> impl Iterator<Item=&T> {
+        let mut refs: Vec<&Partial<T>> =
+            self.data.iter().chain(&self.min).collect();
+        refs.sort();
+        refs.into_iter().map(|p| &p.0)
+    }
+
+    /// Returns the mode of the data, consuming it.
+    ///
+    /// This is like `mode`, except it sorts the data in place instead of
+    /// sorting a copy, so `T` doesn't need to be `Clone`.
+    pub fn into_mode(self) -> Option<T> {
+        mode_on_sorted(self.into_sorted_vec().into_iter()).map(|p| p.0)
+    }
+
+    /// Returns every element in ascending order.
+    fn into_sorted_vec(self) -> Vec<Partial<T>> {
+        let mut sorted = self.data.into_sorted_vec();
+        if let Some(min) = self.min {
+            sorted.insert(0, min);
+        }
+        sorted
+    }
+}
+
+impl<T: PartialOrd + ToPrimitive> Sorted<T> {
+    /// Returns the median of the data, consuming it.
+    ///
+    /// This is like `median`, except it sorts the data in place instead of
+    /// sorting a copy, so `T` doesn't need to be `Clone`.
+    pub fn into_median(self) -> Option<f64> {
+        median_on_sorted(&self.into_sorted_vec())
     }
 }
 
 impl<T: PartialOrd + Clone> Sorted<T> {
     /// Returns the mode of the data.
+    ///
+    /// This sorts a copy of the data, which takes `O(n log n)` time. If there
+    /// is no unique mode, then `None` is returned.
     pub fn mode(&self) -> Option<T> {
-        let p = mode_on_sorted(self.data.clone().into_sorted_vec().into_iter());
-        p.map(|p| p.0)
+        self.clone().into_mode()
+    }
+}
+
+impl<T: PartialOrd + Clone + Eq + Hash> Sorted<T> {
+    /// Returns the mode of the data using a frequency table.
+    ///
+    /// This returns the same result as `mode`, but takes `O(n)` time instead
+    /// of sorting, at the cost of `O(d)` memory, where `d` is the number of
+    /// distinct values. It is a good choice when there are many samples
+    /// relative to their cardinality.
+    pub fn mode_fast(&self) -> Option<T> {
+        let mut counts: HashMap<&T, u64> = HashMap::new();
+        for p in self.data.iter().chain(&self.min) {
+            *counts.entry(&p.0).or_insert(0) += 1;
+        }
+        // Like `mode`, a value that occurs only once is never the mode.
+        let (mut mode, mut mode_count, mut tied) = (None, 1, false);
+        for (x, count) in counts {
+            if count > mode_count {
+                mode = Some(x);
+                mode_count = count;
+                tied = false;
+            } else if count == mode_count {
+                tied = true;
+            }
+        }
+        if tied { None } else { mode.cloned() }
     }
 }
 
 impl<T: PartialOrd + ToPrimitive + Clone> Sorted<T> {
     /// Returns the median of the data.
     pub fn median(&self) -> Option<f64> {
-        // Grr. The only way to avoid the alloc here is to take `self` by
-        // value. Could return `(f64, Sorted<T>)`, but that seems a bit weird.
-        //
-        // NOTE: Can `std::mem::swap` help us here?
-        let data = self.data.clone().into_sorted_vec();
-        median_on_sorted(&*data)
+        // The only way to avoid the alloc here is to take `self` by value,
+        // which is what `into_median` does.
+        self.clone().into_median()
     }
 }
 
 impl<T: PartialOrd> Commute for Sorted<T> {
     fn merge(&mut self, v: Sorted<T>) {
-        // should this be `into_sorted_vec`?
-        self.data.extend(v.data.into_vec().into_iter());
+        // Every element in `v.data` is at least `v.min`, so only `v.min`
+        // can become the new minimum.
+        if let Some(min) = v.min {
+            self.add(min.0);
+        }
+        self.data.extend(v.data.into_vec());
     }
 }
 
 impl<T: PartialOrd> Default for Sorted<T> {
-    fn default() -> Sorted<T> { Sorted { data: BinaryHeap::new() } }
+    fn default() -> Sorted<T> {
+        Sorted { data: BinaryHeap::new(), min: None }
+    }
 }
 
 impl<T: PartialOrd> FromIterator<T> for Sorted<T> {
@@ -123,13 +260,19 @@ impl<T: PartialOrd> FromIterator<T> for Sorted<T> {
 
 impl<T: PartialOrd> Extend<T> for Sorted<T> {
     fn extend<I: IntoIterator<Item=T>>(&mut self, it: I) {
-        self.data.extend(it.into_iter().map(Partial))
+        for v in it {
+            self.add(v);
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use num::ToPrimitive;
+    use num_traits::ToPrimitive;
+    #[cfg(feature = "serde")]
+    use serde_json;
+
+    use Commute;
     use super::Sorted;
 
     fn median<T, I>(it: I) -> Option<f64>
@@ -142,6 +285,14 @@ mod test {
         it.collect::<Sorted<T>>().mode()
     }
 
+    #[test]
+    fn with_capacity() {
+        let mut sorted = Sorted::with_capacity(3);
+        sorted.extend(vec![7usize, 3, 5, 9]);
+        assert_eq!(sorted.len(), 4);
+        assert_eq!(sorted.median(), Some(6.0));
+    }
+
     #[test]
     fn median_stream() {
         assert_eq!(median(vec![3usize, 5, 7, 9].into_iter()), Some(6.0));
@@ -155,6 +306,8 @@ mod test {
         assert_eq!(mode(vec![3usize, 3, 3, 4].into_iter()), Some(3));
         assert_eq!(mode(vec![4usize, 3, 3, 3].into_iter()), Some(3));
         assert_eq!(mode(vec![1usize, 1, 2, 3, 3].into_iter()), None);
+        assert_eq!(mode(vec![1usize, 1, 2, 2, 3, 3].into_iter()), None);
+        assert_eq!(mode(vec![1usize, 1, 2, 2, 3, 3, 3].into_iter()), Some(3));
     }
 
     #[test]
@@ -163,6 +316,100 @@ mod test {
         assert_eq!(median(vec![3.0f64, 5.0, 7.0].into_iter()), Some(5.0));
     }
 
+    /// A sample that can't be cloned.
+    #[derive(Debug, PartialEq, PartialOrd)]
+    struct NoClone(u32);
+
+    impl ToPrimitive for NoClone {
+        fn to_i64(&self) -> Option<i64> { self.0.to_i64() }
+        fn to_u64(&self) -> Option<u64> { self.0.to_u64() }
+    }
+
+    #[test]
+    fn into_mode_median() {
+        let datasets: Vec<Vec<u32>> = vec![
+            vec![],
+            vec![4],
+            vec![3, 5, 7, 9],
+            vec![4, 3, 3, 3],
+            vec![1, 1, 2, 2, 3, 3],
+            (0..101).map(|i| (i * 7919) % 31).collect(),
+        ];
+        for data in datasets {
+            let sorted: Sorted<u32> = data.iter().cloned().collect();
+            assert_eq!(sorted.clone().into_mode(), sorted.mode());
+            assert_eq!(sorted.clone().into_median(), sorted.median());
+        }
+        let sorted: Sorted<NoClone> =
+            vec![5, 1, 5, 2].into_iter().map(NoClone).collect();
+        assert_eq!(sorted.into_mode(), Some(NoClone(5)));
+        let sorted: Sorted<NoClone> =
+            vec![5, 1, 5, 2].into_iter().map(NoClone).collect();
+        assert_eq!(sorted.into_median(), Some(3.5));
+    }
+
+    #[test]
+    fn peek_extremes() {
+        let mut sorted = Sorted::new();
+        assert_eq!((sorted.peek_min(), sorted.peek_max()), (None, None));
+        let data: Vec<i64> =
+            (0..200).map(|i| (i * 7919) % 211 - 100).collect();
+        for (i, &x) in data.iter().enumerate() {
+            sorted.add(x);
+            let seen = &data[..i + 1];
+            assert_eq!(sorted.peek_min(), seen.iter().min());
+            assert_eq!(sorted.peek_max(), seen.iter().max());
+            assert_eq!(sorted.len(), i + 1);
+        }
+
+        let (a, b) = data.split_at(70);
+        let mut merged: Sorted<i64> = b.iter().cloned().collect();
+        merged.merge(a.iter().cloned().collect());
+        assert_eq!(merged.peek_min(), data.iter().min());
+        assert_eq!(merged.peek_max(), data.iter().max());
+        assert_eq!(merged.len(), data.len());
+        let mut expected = data.clone();
+        expected.sort();
+        assert_eq!(merged.into_median(), super::median_on_sorted(&expected));
+    }
+
+    #[test]
+    fn iter_sorted() {
+        let data: Vec<i64> = (0..100).map(|i| (i * 7919) % 37 - 18).collect();
+        let sorted: Sorted<i64> = data.iter().cloned().collect();
+        let got: Vec<i64> = sorted.iter_sorted().cloned().collect();
+        let mut expected = data.clone();
+        expected.sort();
+        assert_eq!(got, expected);
+        assert_eq!(Sorted::<u8>::new().iter_sorted().next(), None);
+    }
+
+    #[test]
+    fn mode_fast_agrees() {
+        let datasets: Vec<Vec<u32>> = vec![
+            vec![],
+            vec![7],
+            vec![1, 1, 2, 2, 3, 3],
+            vec![1, 1, 2, 2, 3, 3, 4],
+            vec![3, 5, 7, 9],
+            vec![3, 3, 3, 4],
+            vec![4, 3, 3, 3],
+            vec![1, 1, 2, 3, 3],
+            vec![1, 2, 2, 3, 3, 3],
+            vec![5, 1, 5, 2, 2, 5, 9, 2],
+            (0..500).map(|i| (i * 7919) % 37).collect(),
+            (0..500).map(|i| (i * i) % 29).collect(),
+        ];
+        for data in datasets {
+            let sorted: Sorted<u32> = data.iter().cloned().collect();
+            assert_eq!(sorted.mode_fast(), sorted.mode(), "{:?}", data);
+        }
+        let sorted: Sorted<u32> = vec![1, 1, 2, 2].into_iter().collect();
+        assert_eq!(sorted.mode_fast(), None);
+        let sorted: Sorted<&str> = vec!["a", "b", "b"].into_iter().collect();
+        assert_eq!(sorted.mode_fast(), Some("b"));
+    }
+
     #[test]
     fn mode_floats() {
         assert_eq!(mode(vec![3.0f64, 5.0, 7.0, 9.0].into_iter()), None);
@@ -171,4 +418,40 @@ mod test {
         assert_eq!(mode(vec![4.0f64, 3.0, 3.0, 3.0].into_iter()), Some(3.0));
         assert_eq!(mode(vec![1.0f64, 1.0, 2.0, 3.0, 3.0].into_iter()), None);
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_roundtrip() {
+        let data: Sorted<u32> = vec![5, 1, 3, 3, 9].into_iter().collect();
+        let json = serde_json::to_string(&data).unwrap();
+        let mut got: Sorted<u32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(got.len(), data.len());
+        assert_eq!(got.median(), data.median());
+        assert_eq!(got.mode(), Some(3));
+
+        got.merge(vec![1, 1, 0].into_iter().collect());
+        assert_eq!(got.mode(), Some(1));
+        assert_eq!(got.median(), Some(2.0));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_without_min() {
+        // Data written before the minimum was kept out of the heap.
+        let mut got: Sorted<u32> =
+            serde_json::from_str(r#"{"data":[5,1,3]}"#).unwrap();
+        assert!(!got.is_empty());
+        assert_eq!(got.len(), 3);
+        assert_eq!(got.peek_min(), Some(&1));
+        assert_eq!(got.peek_max(), Some(&5));
+        got.add(4);
+        assert_eq!(got.peek_min(), Some(&1));
+        assert_eq!(got.median(), Some(3.5));
+
+        // A `min` that isn't the smallest element is not trusted either.
+        let got: Sorted<u32> =
+            serde_json::from_str(r#"{"data":[5,1],"min":3}"#).unwrap();
+        assert_eq!(got.peek_min(), Some(&1));
+        assert_eq!(got.median(), Some(3.0));
+    }
 }
@@ -0,0 +1,109 @@
+use std::hash::Hasher;
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// A hasher whose output is fixed by this crate.
+///
+/// The standard library's `DefaultHasher` may change its algorithm between
+/// Rust releases, so sketches that hash with it can't be merged with
+/// sketches that were serialized by a different build. This is FNV-1a over
+/// the bytes fed to it, followed by the 64-bit finalizer of MurmurHash3 so
+/// that every output bit depends on every input bit. Integers are always
+/// written in little endian byte order, and `usize` and `isize` are always
+/// written as 64-bit integers, so the output doesn't depend on the platform
+/// either.
+///
+/// This isn't resistant to hash flooding, which doesn't matter for the
+/// sketches that use it.
+#[derive(Clone, Debug)]
+pub struct StableHasher {
+    state: u64,
+}
+
+impl StableHasher {
+    pub fn new() -> StableHasher {
+        StableHasher { state: FNV_OFFSET }
+    }
+}
+
+impl Default for StableHasher {
+    fn default() -> StableHasher {
+        StableHasher::new()
+    }
+}
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.state ^= b as u64;
+            self.state = self.state.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn write_u8(&mut self, n: u8) { self.write(&[n]) }
+    fn write_u16(&mut self, n: u16) { self.write(&n.to_le_bytes()) }
+    fn write_u32(&mut self, n: u32) { self.write(&n.to_le_bytes()) }
+    fn write_u64(&mut self, n: u64) { self.write(&n.to_le_bytes()) }
+    fn write_u128(&mut self, n: u128) { self.write(&n.to_le_bytes()) }
+    fn write_usize(&mut self, n: usize) { self.write_u64(n as u64) }
+
+    fn write_i8(&mut self, n: i8) { self.write_u8(n as u8) }
+    fn write_i16(&mut self, n: i16) { self.write_u16(n as u16) }
+    fn write_i32(&mut self, n: i32) { self.write_u32(n as u32) }
+    fn write_i64(&mut self, n: i64) { self.write_u64(n as u64) }
+    fn write_i128(&mut self, n: i128) { self.write_u128(n as u128) }
+    fn write_isize(&mut self, n: isize) { self.write_u64(n as i64 as u64) }
+
+    fn finish(&self) -> u64 {
+        fmix64(self.state)
+    }
+}
+
+/// The 64-bit finalizer of MurmurHash3.
+fn fmix64(mut h: u64) -> u64 {
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51afd7ed558ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ceb9fe1a85ec53);
+    h ^= h >> 33;
+    h
+}
+
+#[cfg(test)]
+mod test {
+    use std::hash::{Hash, Hasher};
+
+    use super::{StableHasher, fmix64};
+
+    fn hash<T: Hash + ?Sized>(v: &T) -> u64 {
+        let mut hasher = StableHasher::new();
+        v.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn fnv_vectors() {
+        // Published FNV-1a test vectors, before the final mix.
+        let mut hasher = StableHasher::new();
+        assert_eq!(hasher.state, 0xcbf29ce484222325);
+        hasher.write(b"a");
+        assert_eq!(hasher.state, 0xaf63dc4c8601ec8c);
+        assert_eq!(hasher.finish(), fmix64(0xaf63dc4c8601ec8c));
+        let mut hasher = StableHasher::new();
+        hasher.write(b"foobar");
+        assert_eq!(hasher.state, 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn platform_independent() {
+        // Integers hash as their little endian bytes, whatever their width
+        // on this platform.
+        let mut bytes = StableHasher::new();
+        bytes.write(&[1, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(hash(&1usize), bytes.finish());
+        assert_eq!(hash(&1u64), bytes.finish());
+        assert_eq!(hash(&-1isize), hash(&u64::MAX));
+        assert_ne!(hash(&1u32), hash(&1u64));
+    }
+}
@@ -0,0 +1,135 @@
+use std::default::Default;
+use std::fmt;
+use std::iter::{FromIterator, IntoIterator};
+
+use num_traits::ToPrimitive;
+
+use Commute;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A commutative data structure for summing a stream of numbers.
+///
+/// The running total is maintained with Kahan (compensated) summation, which
+/// keeps track of the low order bits lost by each addition. This greatly
+/// reduces the error that accumulates when summing many values of different
+/// magnitudes.
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Sum {
+    len: u64,
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    sum: f64,
+    /// The negation of the low order bits lost from `sum`.
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    compensation: f64,
+}
+
+impl Sum {
+    /// Create initial state with a total of `0`.
+    pub fn new() -> Sum {
+        Default::default()
+    }
+
+    /// Add a sample to the total.
+    pub fn add<T: ToPrimitive>(&mut self, sample: T) {
+        self.len += 1;
+        self.add_f64(sample.to_f64().unwrap());
+    }
+
+    fn add_f64(&mut self, x: f64) {
+        let y = x - self.compensation;
+        let t = self.sum + y;
+        self.compensation = (t - self.sum) - y;
+        self.sum = t;
+    }
+
+    /// Returns the sum of all samples.
+    pub fn total(&self) -> f64 {
+        self.sum
+    }
+
+    /// Returns the number of samples.
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Returns true if there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Commute for Sum {
+    fn merge(&mut self, v: Sum) {
+        self.len += v.len;
+        self.add_f64(v.sum);
+        self.add_f64(-v.compensation);
+    }
+}
+
+impl Default for Sum {
+    fn default() -> Sum {
+        Sum { len: 0, sum: 0.0, compensation: 0.0 }
+    }
+}
+
+impl fmt::Debug for Sum {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.sum)
+    }
+}
+
+impl<T: ToPrimitive> FromIterator<T> for Sum {
+    fn from_iter<I: IntoIterator<Item=T>>(it: I) -> Sum {
+        let mut v = Sum::new();
+        v.extend(it);
+        v
+    }
+}
+
+impl<T: ToPrimitive> Extend<T> for Sum {
+    fn extend<I: IntoIterator<Item=T>>(&mut self, it: I) {
+        for sample in it {
+            self.add(sample);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {Commute, merge_all};
+    use super::Sum;
+
+    fn pathological() -> Vec<f64> {
+        let mut xs = vec![1e-16; 1_000_001];
+        xs[0] = 1.0;
+        xs
+    }
+
+    #[test]
+    fn compensated() {
+        let xs = pathological();
+        let naive = xs.iter().fold(0.0, |acc, &x| acc + x);
+        let sum: Sum = xs.iter().cloned().collect();
+        let expected = 1.0 + 1e-10;
+        assert_eq!(naive, 1.0);
+        assert!((sum.total() - expected).abs() < 1e-15);
+        assert!((sum.total() - expected).abs() < (naive - expected).abs());
+        assert_eq!(sum.len(), 1_000_001);
+    }
+
+    #[test]
+    fn merge() {
+        let xs = pathological();
+        let sums = xs.chunks(1000).map(|c| c.iter().cloned().collect::<Sum>());
+        let merged = merge_all(sums).unwrap();
+        assert!((merged.total() - (1.0 + 1e-10)).abs() < 1e-15);
+        assert_eq!(merged.len(), 1_000_001);
+
+        let mut sum: Sum = vec![1u32, 2, 3].into_iter().collect();
+        sum.merge(Sum::new());
+        assert_eq!(sum.total(), 6.0);
+    }
+}
@@ -0,0 +1,218 @@
+use std::default::Default;
+use std::fmt;
+use std::iter::{FromIterator, IntoIterator};
+
+use num_traits::ToPrimitive;
+
+use Commute;
+use minmax::MinMax;
+use online::OnlineStats;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A commutative summary of the descriptive statistics of a stream.
+///
+/// This tracks the count, mean, variance, standard deviation, minimum and
+/// maximum of a stream in a single pass and in constant space. It is
+/// equivalent to feeding the same samples to both an `OnlineStats` and a
+/// `MinMax<f64>`.
+///
+/// Like `MinMax`, the minimum and maximum ignore `NaN` samples.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Summary {
+    stats: OnlineStats,
+    minmax: MinMax<f64>,
+}
+
+impl Summary {
+    /// Create an empty summary.
+    pub fn new() -> Summary {
+        Default::default()
+    }
+
+    /// Add a new sample.
+    pub fn add<T: ToPrimitive>(&mut self, sample: T) {
+        let sample = sample.to_f64().unwrap();
+        self.stats.add(sample);
+        self.minmax.add(sample);
+    }
+
+    /// Returns the number of samples.
+    pub fn len(&self) -> usize {
+        self.stats.len()
+    }
+
+    /// Returns true if there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.stats.is_empty()
+    }
+
+    /// Returns the mean, or `None` if there are no samples.
+    pub fn mean(&self) -> Option<f64> {
+        self.stats.try_mean()
+    }
+
+    /// Returns the population variance, or `None` if there are no samples.
+    pub fn variance(&self) -> Option<f64> {
+        self.stats.try_variance()
+    }
+
+    /// Returns the population standard deviation, or `None` if there are no
+    /// samples.
+    pub fn stddev(&self) -> Option<f64> {
+        self.stats.try_stddev()
+    }
+
+    /// Returns the smallest sample, or `None` if there are no samples.
+    pub fn min(&self) -> Option<f64> {
+        self.minmax.min().cloned()
+    }
+
+    /// Returns the largest sample, or `None` if there are no samples.
+    pub fn max(&self) -> Option<f64> {
+        self.minmax.max().cloned()
+    }
+
+    /// Returns the underlying mean and variance state.
+    pub fn online_stats(&self) -> &OnlineStats {
+        &self.stats
+    }
+
+    /// Returns the underlying minimum and maximum state.
+    pub fn minmax(&self) -> &MinMax<f64> {
+        &self.minmax
+    }
+}
+
+impl Commute for Summary {
+    fn merge(&mut self, v: Summary) {
+        self.stats.merge(v.stats);
+        self.minmax.merge(v.minmax);
+    }
+}
+
+impl Default for Summary {
+    fn default() -> Summary {
+        Summary {
+            stats: OnlineStats::new(),
+            minmax: MinMax::new(),
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "count: {}", self.len())?;
+        if let (Some(min), Some(max)) = (self.min(), self.max()) {
+            let (mean, stddev) = (self.stats.mean(), self.stats.stddev());
+            // Like the `Debug` output of `OnlineStats`, label the convention
+            // so that it isn't mistaken for the sample standard deviation.
+            match f.precision() {
+                Some(p) => write!(
+                    f, ", mean: {:.*}, population stddev: {:.*}, \
+                        min: {:.*}, max: {:.*}",
+                    p, mean, p, stddev, p, min, p, max)?,
+                None => write!(
+                    f, ", mean: {}, population stddev: {}, min: {}, max: {}",
+                    mean, stddev, min, max)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl<T: ToPrimitive> FromIterator<T> for Summary {
+    fn from_iter<I: IntoIterator<Item=T>>(it: I) -> Summary {
+        let mut v = Summary::new();
+        v.extend(it);
+        v
+    }
+}
+
+impl<T: ToPrimitive> Extend<T> for Summary {
+    fn extend<I: IntoIterator<Item=T>>(&mut self, it: I) {
+        for sample in it {
+            self.add(sample);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {Commute, MinMax, OnlineStats};
+    use super::Summary;
+
+    fn data() -> Vec<f64> {
+        (0..50).map(|i| ((i * 7919) % 101) as f64 / 4.0 - 7.5).collect()
+    }
+
+    #[test]
+    fn matches_accumulators() {
+        let data = data();
+        let summary: Summary = data.iter().cloned().collect();
+        let stats: OnlineStats = data.iter().cloned().collect();
+        let minmax: MinMax<f64> = data.iter().cloned().collect();
+        assert_eq!(summary.len(), stats.len());
+        assert_eq!(summary.mean(), Some(stats.mean()));
+        assert_eq!(summary.variance(), Some(stats.variance()));
+        assert_eq!(summary.stddev(), Some(stats.stddev()));
+        assert_eq!(summary.min(), minmax.min().cloned());
+        assert_eq!(summary.max(), minmax.max().cloned());
+    }
+
+    #[test]
+    fn merge() {
+        let data = data();
+        let expected: Summary = data.iter().cloned().collect();
+        let (a, b) = data.split_at(17);
+        let mut got: Summary = a.iter().cloned().collect();
+        got.merge(b.iter().cloned().collect());
+        assert_eq!(got.len(), expected.len());
+        assert_eq!(got.min(), expected.min());
+        assert_eq!(got.max(), expected.max());
+        let (x, y) = (got.variance().unwrap(), expected.variance().unwrap());
+        assert!((x - y).abs() < 1e-9, "{} != {}", x, y);
+    }
+
+    #[test]
+    fn empty() {
+        let summary = Summary::new();
+        assert!(summary.is_empty());
+        assert_eq!(summary.mean(), None);
+        assert_eq!(summary.stddev(), None);
+        assert_eq!(summary.min(), None);
+        assert_eq!(summary.max(), None);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Summary::new().to_string(), "count: 0");
+        let summary: Summary = vec![1, 2, 3, 6].into_iter().collect();
+        assert_eq!(summary.to_string(),
+                   "count: 4, mean: 3, population stddev: 1.8708286933869707, \
+                    min: 1, max: 6");
+        assert_eq!(format!("{:.2}", summary),
+                   "count: 4, mean: 3.00, population stddev: 1.87, \
+                    min: 1.00, max: 6.00");
+    }
+
+    #[test]
+    fn labels_population() {
+        // The population standard deviation of this data is 2, while the
+        // sample standard deviation is about 2.14.
+        let summary: Summary =
+            vec![2, 4, 4, 4, 5, 5, 7, 9].into_iter().collect();
+        assert!(summary.to_string().contains("population stddev: 2,"));
+        assert!(format!("{:?}", summary).contains("population stddev: 2,"));
+        assert!(format!("{:.1?}", summary)
+                .contains("population stddev: 2.0,"));
+    }
+}
@@ -0,0 +1,323 @@
+use std::f64::consts::PI;
+use std::fmt;
+use std::iter::IntoIterator;
+
+use num_traits::ToPrimitive;
+
+use Commute;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A commutative data structure for approximate quantiles.
+///
+/// This is the merging t-digest, which summarizes a distribution as a
+/// sorted list of centroids, each with a mean and a weight. Centroids near
+/// the extremes are kept small while those near the median may be large,
+/// so estimates of extreme quantiles are especially accurate. The number
+/// of centroids is bounded by roughly the compression parameter, regardless
+/// of how many samples are added.
+///
+/// Samples are buffered and clustered into centroids in batches. Two digests
+/// are merged by clustering the union of their centroids.
+///
+/// See: Dunning and Ertl, "Computing Extremely Accurate Quantiles Using
+/// t-Digests", 2019.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TDigest {
+    compression: f64,
+    len: u64,
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    min: f64,
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    max: f64,
+    centroids: Vec<Centroid>,
+    buffer: Vec<Centroid>,
+}
+
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+impl TDigest {
+    /// Create an empty digest with the given compression.
+    ///
+    /// Larger values of `compression` keep more centroids, which trades
+    /// memory for accuracy. A value of `100` is typical.
+    ///
+    /// This panics if `compression` is not positive.
+    pub fn new(compression: f64) -> TDigest {
+        assert!(compression > 0.0,
+                "compression must be positive, got {}", compression);
+        TDigest {
+            compression,
+            len: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            centroids: vec![],
+            buffer: vec![],
+        }
+    }
+
+    /// Add a sample to the digest.
+    ///
+    /// Samples that are not finite, like `NaN` or infinity, are ignored,
+    /// since a centroid with an infinite mean can't be merged with another.
+    pub fn add<T: ToPrimitive>(&mut self, sample: T) {
+        let x = sample.to_f64().unwrap();
+        if !x.is_finite() {
+            return;
+        }
+        self.len += 1;
+        self.min = self.min.min(x);
+        self.max = self.max.max(x);
+        self.buffer.push(Centroid { mean: x, weight: 1.0 });
+        if self.buffer.len() as f64 >= 5.0 * self.compression {
+            self.flush();
+        }
+    }
+
+    /// Returns an estimate of the `q`th quantile, where `q` is in `[0, 1]`.
+    ///
+    /// The estimate interpolates linearly between the means of adjacent
+    /// centroids, and between the extreme centroids and the exact minimum
+    /// and maximum.
+    ///
+    /// If the digest is empty or `q` is not in `[0, 1]`, then `NaN` is
+    /// returned.
+    pub fn quantile(&self, q: f64) -> f64 {
+        if self.len == 0 || !(0.0..=1.0).contains(&q) {
+            return f64::NAN;
+        }
+        let merged;
+        let centroids = if self.buffer.is_empty() {
+            &self.centroids
+        } else {
+            let mut all = self.centroids.clone();
+            all.extend_from_slice(&self.buffer);
+            merged = compress(all, self.compression);
+            &merged
+        };
+        if centroids.len() == 1 {
+            return centroids[0].mean;
+        }
+
+        let total = self.len as f64;
+        let target = q * total;
+        let (first, last) = (centroids[0], centroids[centroids.len() - 1]);
+        if target < first.weight / 2.0 {
+            return self.min
+                   + (first.mean - self.min) * target / (first.weight / 2.0);
+        }
+        if target > total - last.weight / 2.0 {
+            let rest = total - target;
+            return self.max
+                   - (self.max - last.mean) * rest / (last.weight / 2.0);
+        }
+        // Each centroid's weight is assumed to be centered on its mean.
+        let mut center = first.weight / 2.0;
+        for w in centroids.windows(2) {
+            let next = center + (w[0].weight + w[1].weight) / 2.0;
+            if target <= next {
+                let frac = (target - center) / (next - center);
+                return w[0].mean + frac * (w[1].mean - w[0].mean);
+            }
+            center = next;
+        }
+        last.mean
+    }
+
+    /// Returns an estimate of the `q`th quantile, where `q` is in `[0, 1]`.
+    ///
+    /// This is like `quantile`, except `None` is returned instead of `NaN`
+    /// when the digest is empty or `q` is not in `[0, 1]`.
+    pub fn try_quantile(&self, q: f64) -> Option<f64> {
+        let x = self.quantile(q);
+        if x.is_nan() { None } else { Some(x) }
+    }
+
+    /// Returns the compression parameter.
+    pub fn compression(&self) -> f64 {
+        self.compression
+    }
+
+    /// Returns the number of samples.
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Returns true if there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn flush(&mut self) {
+        let mut all = std::mem::take(&mut self.centroids);
+        all.append(&mut self.buffer);
+        self.centroids = compress(all, self.compression);
+    }
+}
+
+/// Sort the centroids and merge adjacent ones while the merged centroid
+/// still spans at most one unit of the scale function.
+fn compress(mut centroids: Vec<Centroid>, compression: f64) -> Vec<Centroid> {
+    if centroids.is_empty() {
+        return centroids;
+    }
+    // Only finite samples are added, but a deserialized digest could still
+    // have non-finite means, which shouldn't panic.
+    centroids.sort_by(|a, b| a.mean.total_cmp(&b.mean));
+    let total: f64 = centroids.iter().map(|c| c.weight).sum();
+    // The k1 scale function and its inverse.
+    let k = |q: f64| compression / (2.0 * PI) * (2.0 * q - 1.0).asin();
+    let k_inv = |k: f64| {
+        let k = k.min(compression / 4.0);
+        ((2.0 * PI * k / compression).sin() + 1.0) / 2.0
+    };
+
+    let mut merged = Vec::with_capacity(centroids.len());
+    let mut iter = centroids.into_iter();
+    let mut cur = iter.next().unwrap();
+    let mut seen = 0.0;
+    let mut limit = k_inv(k(0.0) + 1.0);
+    for c in iter {
+        if (seen + cur.weight + c.weight) / total <= limit {
+            let weight = cur.weight + c.weight;
+            cur.mean += (c.mean - cur.mean) * c.weight / weight;
+            cur.weight = weight;
+        } else {
+            seen += cur.weight;
+            merged.push(cur);
+            limit = k_inv(k(seen / total) + 1.0);
+            cur = c;
+        }
+    }
+    merged.push(cur);
+    merged
+}
+
+impl Commute for TDigest {
+    fn merge(&mut self, v: TDigest) {
+        self.len += v.len;
+        self.min = self.min.min(v.min);
+        self.max = self.max.max(v.max);
+        self.centroids.extend(v.centroids);
+        self.buffer.extend(v.buffer);
+        self.flush();
+    }
+}
+
+impl fmt::Debug for TDigest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TDigest(len: {}, median ~ {:.10})",
+               self.len, self.quantile(0.5))
+    }
+}
+
+impl<T: ToPrimitive> Extend<T> for TDigest {
+    fn extend<I: IntoIterator<Item=T>>(&mut self, it: I) {
+        for sample in it {
+            self.add(sample);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use {Commute, merge_all};
+    use unsorted::Unsorted;
+    use super::{Centroid, TDigest, compress};
+
+    fn data(seed: u64, n: usize) -> Vec<f64> {
+        // An exponential distribution, so the upper tail is long.
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..n).map(|_| -(1.0 - rng.gen::<f64>()).ln()).collect()
+    }
+
+    fn check(digest: &TDigest, data: Vec<f64>) {
+        let mut exact: Unsorted<f64> = data.into_iter().collect();
+        for &(q, tolerance) in &[(0.5, 0.01), (0.99, 0.05)] {
+            let (e, a) = (exact.quantile(q).unwrap(), digest.quantile(q));
+            assert!((e - a).abs() <= tolerance,
+                    "q: {}, exact: {}, approx: {}", q, e, a);
+        }
+        assert_eq!(digest.quantile(0.0), exact.quantile(0.0).unwrap());
+        assert_eq!(digest.quantile(1.0), exact.quantile(1.0).unwrap());
+    }
+
+    #[test]
+    fn accuracy() {
+        let data = data(0, 100_000);
+        let mut digest = TDigest::new(100.0);
+        digest.extend(data.iter().cloned());
+        assert!(digest.centroids.len() <= 200);
+        check(&digest, data);
+    }
+
+    #[test]
+    fn merge() {
+        let parts: Vec<Vec<f64>> = (0..10).map(|i| data(i, 10_000)).collect();
+        let digests = parts.iter().map(|part| {
+            let mut digest = TDigest::new(100.0);
+            digest.extend(part.iter().cloned());
+            digest
+        });
+        let merged = merge_all(digests).unwrap();
+        assert_eq!(merged.len(), 100_000);
+        check(&merged, parts.into_iter().flatten().collect());
+    }
+
+    #[test]
+    fn small() {
+        let mut digest = TDigest::new(100.0);
+        assert!(digest.quantile(0.5).is_nan());
+        digest.extend(vec![1.0, 2.0, 3.0, f64::NAN]);
+        assert_eq!(digest.len(), 3);
+        assert_eq!(digest.quantile(0.5), 2.0);
+        let mut other = TDigest::new(100.0);
+        other.add(10.0);
+        digest.merge(other);
+        assert_eq!(digest.quantile(1.0), 10.0);
+        assert!(digest.quantile(1.5).is_nan());
+    }
+
+    #[test]
+    fn try_quantile() {
+        let mut digest = TDigest::new(100.0);
+        assert_eq!(digest.try_quantile(0.5), None);
+        digest.extend(vec![1.0, 2.0, 3.0]);
+        assert_eq!(digest.try_quantile(0.5), Some(2.0));
+        assert_eq!(digest.try_quantile(1.5), None);
+    }
+
+    #[test]
+    fn infinite() {
+        let mut digest = TDigest::new(100.0);
+        digest.extend(vec![f64::INFINITY; 1000]);
+        digest.extend(vec![1.0; 1000]);
+        digest.extend(vec![f64::NEG_INFINITY; 1000]);
+        assert_eq!(digest.len(), 1000);
+        assert_eq!(digest.quantile(0.0), 1.0);
+        assert_eq!(digest.quantile(0.5), 1.0);
+        assert_eq!(digest.quantile(1.0), 1.0);
+
+        // Centroids with infinite means, e.g., from deserialized data, are
+        // sorted without panicking, even after merging two of them made a
+        // `NaN` mean.
+        let centroids = [f64::INFINITY, 1.0, f64::NEG_INFINITY].iter()
+            .flat_map(|&mean| (0..1000).map(move |_| {
+                Centroid { mean, weight: 1.0 }
+            }))
+            .collect();
+        let merged = compress(compress(centroids, 100.0), 100.0);
+        let weight: f64 = merged.iter().map(|c| c.weight).sum();
+        assert_eq!(weight, 3000.0);
+    }
+}
@@ -0,0 +1,144 @@
+use std::default::Default;
+use std::iter::{FromIterator, IntoIterator};
+
+use num_traits::ToPrimitive;
+
+use online::OnlineStats;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// An ordered sequence of samples for time series diagnostics.
+///
+/// Unlike most accumulators in this crate, statistics like autocorrelation
+/// depend on the order of the samples, so every sample is stored in the
+/// order it was added. For the same reason, this type does not implement
+/// `Commute`.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TimeSeries {
+    data: Vec<f64>,
+}
+
+impl TimeSeries {
+    /// Create an empty series.
+    pub fn new() -> TimeSeries {
+        Default::default()
+    }
+
+    /// Create an empty series with room for `capacity` samples before
+    /// reallocating.
+    pub fn with_capacity(capacity: usize) -> TimeSeries {
+        TimeSeries { data: Vec::with_capacity(capacity) }
+    }
+
+    /// Add a new sample to the end of the series.
+    pub fn add<T: ToPrimitive>(&mut self, sample: T) {
+        self.data.push(sample.to_f64().unwrap());
+    }
+
+    /// Returns the number of samples.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns true if there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns the samples in the order they were added.
+    pub fn as_slice(&self) -> &[f64] {
+        &self.data
+    }
+
+    /// Returns the sample autocorrelation at `lag`.
+    ///
+    /// This is the sum of `(x[t] - mean) * (x[t + lag] - mean)` over every
+    /// pair of samples `lag` apart, divided by the sum of squared
+    /// differences from the mean of the whole series. The result is in
+    /// `[-1, 1]`, and is `1` when `lag` is `0`.
+    ///
+    /// If `lag` is not less than the length of the series, or if all
+    /// samples are equal, then `NaN` is returned.
+    pub fn autocorrelation(&self, lag: usize) -> f64 {
+        if lag >= self.data.len() {
+            return f64::NAN;
+        }
+        let mean = OnlineStats::from_slice(&self.data).mean();
+        let denom: f64 = self.data.iter().map(|&x| (x - mean).powi(2)).sum();
+        let numer: f64 = self.data.iter()
+            .zip(&self.data[lag..])
+            .map(|(&x, &y)| (x - mean) * (y - mean))
+            .sum();
+        numer / denom
+    }
+}
+
+impl<T: ToPrimitive> FromIterator<T> for TimeSeries {
+    fn from_iter<I: IntoIterator<Item=T>>(it: I) -> TimeSeries {
+        let mut v = TimeSeries::new();
+        v.extend(it);
+        v
+    }
+}
+
+impl<T: ToPrimitive> Extend<T> for TimeSeries {
+    fn extend<I: IntoIterator<Item=T>>(&mut self, it: I) {
+        self.data.extend(it.into_iter().map(|x| x.to_f64().unwrap()));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::PI;
+
+    use super::TimeSeries;
+
+    #[test]
+    fn periodic_peaks() {
+        let period = 8;
+        let series: TimeSeries = (0..96)
+            .map(|i| (2.0 * PI * i as f64 / period as f64).sin())
+            .collect();
+        let acf: Vec<f64> =
+            (0..33).map(|lag| series.autocorrelation(lag)).collect();
+        assert!((acf[0] - 1.0).abs() < 1e-12);
+        // Peaks at multiples of the period, troughs half a period off.
+        for k in 1..4 {
+            let peak = acf[k * period];
+            assert!(peak > 0.6, "lag {}: {}", k * period, peak);
+            assert!(peak > acf[k * period - 1] && peak > acf[k * period + 1]);
+            let lag = k * period - period / 2;
+            assert!(acf[lag] < -0.6, "lag {}: {}", lag, acf[lag]);
+        }
+        // Peaks decay as fewer pairs overlap.
+        assert!(acf[8] > acf[16] && acf[16] > acf[24]);
+    }
+
+    #[test]
+    fn square_wave() {
+        let series: TimeSeries =
+            (0..40).map(|i| if i % 4 < 2 { 1 } else { -1 }).collect();
+        assert_eq!(series.autocorrelation(4), 36.0 / 40.0);
+        assert_eq!(series.autocorrelation(2), -38.0 / 40.0);
+    }
+
+    #[test]
+    fn with_capacity() {
+        let mut series = TimeSeries::with_capacity(4);
+        series.extend(vec![1, -1, 1, -1]);
+        assert_eq!(series.len(), 4);
+        assert_eq!(series.autocorrelation(2), 0.5);
+    }
+
+    #[test]
+    fn degenerate() {
+        let series: TimeSeries = vec![1.0, 2.0, 3.0].into_iter().collect();
+        assert!(series.autocorrelation(3).is_nan());
+        assert!(series.autocorrelation(10).is_nan());
+        assert!(TimeSeries::new().autocorrelation(0).is_nan());
+        let constant: TimeSeries = vec![5, 5, 5].into_iter().collect();
+        assert!(constant.autocorrelation(1).is_nan());
+    }
+}
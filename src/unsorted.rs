@@ -4,6 +4,9 @@ use num_traits::ToPrimitive;
 
 use {Commute, Partial};
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 /// Compute the exact median on a stream of data.
 ///
 /// (This has time complexity `O(nlogn)` and space complexity `O(n)`.)
@@ -38,7 +41,8 @@ pub fn mode<T, I>(it: I) -> Option<T>
 /// ```
 /// This has time complexity `O(n)`
 ///
-/// If the data does not have a mode, then an empty `Vec` is returned.
+/// If no value occurs more than once, then every value is tied and every
+/// value is returned. An empty `Vec` is returned only if there is no data.
 pub fn modes<T, I>(it: I) -> Vec<T>
        where T: PartialOrd + Clone, I: Iterator<Item=T> {
     it.collect::<Unsorted<T>>().modes()
@@ -60,37 +64,98 @@ fn median_on_sorted<T>(data: &[T]) -> Option<f64>
     })
 }
 
+fn median_on_sorted_f32<T>(data: &[T]) -> Option<f32>
+        where T: PartialOrd + ToPrimitive {
+    Some(match data.len() {
+        0 => return None,
+        1 => data[0].to_f32().unwrap(),
+        len if len % 2 == 0 => {
+            let v1 = data[(len / 2) - 1].to_f32().unwrap();
+            let v2 = data[len / 2].to_f32().unwrap();
+            (v1 + v2) / 2.0
+        }
+        len => {
+            data[len / 2].to_f32().unwrap()
+        }
+    })
+}
+
+fn quantile_on_sorted<T>(data: &[T], q: f64) -> Option<f64>
+        where T: PartialOrd + ToPrimitive {
+    if data.is_empty() || !(0.0..=1.0).contains(&q) {
+        return None;
+    }
+    // This is the linear interpolation between order statistics used by
+    // R's default (type 7) and NumPy's default quantile estimators.
+    let h = q * ((data.len() - 1) as f64);
+    let lo = h.floor() as usize;
+    let v1 = data[lo].to_f64().unwrap();
+    let frac = h - (lo as f64);
+    // Skipping the interpolation on an exact order statistic keeps infinite
+    // values intact, since `0 * inf` is `NaN`.
+    if frac == 0.0 {
+        return Some(v1);
+    }
+    let v2 = data[lo + 1].to_f64().unwrap();
+    if !v1.is_finite() || !v2.is_finite() {
+        // The difference below would be `NaN` next to an infinite value,
+        // but a weighted sum keeps its sign.
+        return Some((1.0 - frac) * v1 + frac * v2);
+    }
+    Some(v1 + frac * (v2 - v1))
+}
+
+fn quantile_on_sorted_f32<T>(data: &[T], q: f32) -> Option<f32>
+        where T: PartialOrd + ToPrimitive {
+    if data.is_empty() || !(0.0..=1.0).contains(&q) {
+        return None;
+    }
+    // See `quantile_on_sorted`, which this mirrors in single precision.
+    let h = q * ((data.len() - 1) as f32);
+    let lo = h.floor() as usize;
+    let v1 = data[lo].to_f32().unwrap();
+    let frac = h - (lo as f32);
+    if frac == 0.0 {
+        return Some(v1);
+    }
+    let v2 = data[lo + 1].to_f32().unwrap();
+    if !v1.is_finite() || !v2.is_finite() {
+        return Some((1.0 - frac) * v1 + frac * v2);
+    }
+    Some(v1 + frac * (v2 - v1))
+}
+
 fn mode_on_sorted<T, I>(it: I) -> Option<T>
         where T: PartialOrd, I: Iterator<Item=T> {
     // This approach to computing the mode works very nicely when the
     // number of samples is large and is close to its cardinality.
-    // In other cases, a hashmap would be much better.
-    // But really, how can we know this when given an arbitrary stream?
-    // Might just switch to a hashmap to track frequencies. That would also
-    // be generally useful for discovering the cardinality of a sample.
-    let (mut mode, mut next) = (None, None);
-    let (mut mode_count, mut next_count) = (0usize, 0usize);
+    // In other cases, a hashmap would be much better, which is what
+    // `Sorted::mode_fast` uses for types that can be hashed.
+    let (mut mode, mut mode_count, mut tied) = (None, 1usize, false);
+    let (mut run, mut run_count) = (None, 0usize);
     for x in it {
-        if mode.as_ref().map(|y| y == &x).unwrap_or(false) {
-            mode_count += 1;
-        } else if next.as_ref().map(|y| y == &x).unwrap_or(false) {
-            next_count += 1;
-        } else {
-            next = Some(x);
-            next_count = 0;
+        if run.as_ref().is_some_and(|y| y == &x) {
+            run_count += 1;
+            continue;
         }
-
-        if next_count > mode_count {
-            mode = next;
-            mode_count = next_count;
-            next = None;
-            next_count = 0;
-        } else if next_count == mode_count {
-            mode = None;
-            mode_count = 0usize;
+        if run_count > mode_count {
+            mode = run;
+            mode_count = run_count;
+            tied = false;
+        } else if run_count == mode_count {
+            tied = true;
         }
+        run = Some(x);
+        run_count = 1;
+    }
+    if run_count > mode_count {
+        mode = run;
+        tied = false;
+    } else if run_count == mode_count {
+        tied = true;
     }
-    mode
+    // A value that occurs only once is never the mode.
+    if tied { None } else { mode }
 }
 
 fn modes_on_sorted<T, I>(it: I) -> Vec<T>
@@ -101,7 +166,7 @@ fn modes_on_sorted<T, I>(it: I) -> Vec<T>
     let mut values = vec![];
     let mut count = 0;
     for x in it {
-        if values.len() == 0 {
+        if values.is_empty() {
             values.push(x);
             modes.push(1);
             continue
@@ -119,7 +184,7 @@ fn modes_on_sorted<T, I>(it: I) -> Vec<T>
     }
     modes.into_iter()
         .zip(values)
-        .filter(|(cnt, _val)| *cnt == highest_mode && highest_mode > 1)
+        .filter(|(cnt, _val)| *cnt == highest_mode)
         .map(|(_, val)| val)
         .collect()
 }
@@ -129,9 +194,14 @@ fn modes_on_sorted<T, I>(it: I) -> Vec<T>
 /// The sort does not occur until statistics need to be computed.
 ///
 /// Note that this works on types that do not define a total ordering like
-/// `f32` and `f64`. When an ordering is not defined, an arbitrary order
-/// is returned.
+/// `f32` and `f64`. The data is sorted with the ordering of `Partial`, so
+/// `NaN` values sort after every other value. This means that `NaN` values
+/// are counted by statistics like the median and quantiles, and pull them
+/// toward the top of the data. For example, the median of `[1, 2, NaN]` is
+/// `2`, and `quantile(1.0)` is `NaN`. Filter out `NaN` values before adding
+/// them if this isn't desired.
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Unsorted<T> {
     data: Vec<Partial<T>>,
     sorted: bool,
@@ -139,10 +209,22 @@ pub struct Unsorted<T> {
 
 impl<T: PartialOrd> Unsorted<T> {
     /// Create initial empty state.
+    ///
+    /// This does not allocate until the first element is added. Use
+    /// `with_capacity` if the number of elements is known.
     pub fn new() -> Unsorted<T> {
         Default::default()
     }
 
+    /// Create initial empty state with room for `capacity` data points
+    /// before reallocating.
+    pub fn with_capacity(capacity: usize) -> Unsorted<T> {
+        Unsorted {
+            data: Vec::with_capacity(capacity),
+            sorted: true,
+        }
+    }
+
     /// Add a new element to the set.
     pub fn add(&mut self, v: T) {
         self.dirtied();
@@ -154,9 +236,39 @@ impl<T: PartialOrd> Unsorted<T> {
         self.data.len()
     }
 
+    /// Returns true if there are no data points.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns the number of data points that can be held without
+    /// reallocating.
+    pub fn capacity(&self) -> usize {
+        self.data.capacity()
+    }
+
+    /// Shrink the capacity of the buffer to fit the data points.
+    ///
+    /// This is useful when many accumulators are kept around after their
+    /// statistics have been computed. The buffer is never shrunk
+    /// automatically, since that would make adding more data expensive.
+    pub fn shrink_to_fit(&mut self) {
+        self.data.shrink_to_fit();
+    }
+
+    /// Returns an iterator over the data in ascending order.
+    ///
+    /// This sorts the data in place first if it isn't already sorted, which
+    /// takes `O(n log n)` time. Iterating takes no extra memory.
+    pub fn iter_sorted(&mut self) -> impl Iterator<Item=&T> {
+        self.sort();
+        self.data.iter().map(|p| &p.0)
+    }
+
     fn sort(&mut self) {
         if !self.sorted {
             self.data.sort();
+            self.sorted = true;
         }
     }
 
@@ -182,6 +294,10 @@ impl<T: PartialOrd + Clone> Unsorted<T> {
     }
 
     /// Returns the modes of the data.
+    ///
+    /// Every value tied for the highest frequency is returned in ascending
+    /// order. So if no value occurs more than once, then every value is
+    /// returned. An empty `Vec` is returned only if there is no data.
     pub fn modes(&mut self) -> Vec<T> {
         self.sort();
         modes_on_sorted(self.data.iter())
@@ -189,29 +305,294 @@ impl<T: PartialOrd + Clone> Unsorted<T> {
             .map(|p| p.0.clone())
             .collect()
     }
+
+    /// Returns the `k` smallest values in ascending order.
+    ///
+    /// If `k` is greater than the number of data points, then all of the
+    /// data is returned.
+    pub fn k_smallest(&mut self, k: usize) -> Vec<T> {
+        self.sort();
+        let k = k.min(self.data.len());
+        self.data[..k].iter().map(|p| p.0.clone()).collect()
+    }
+
+    /// Returns the `k` largest values in descending order.
+    ///
+    /// If `k` is greater than the number of data points, then all of the
+    /// data is returned.
+    pub fn k_largest(&mut self, k: usize) -> Vec<T> {
+        self.sort();
+        self.data.iter().rev().take(k).map(|p| p.0.clone()).collect()
+    }
 }
 
 impl<T: PartialOrd + ToPrimitive> Unsorted<T> {
     /// Returns the median of the data.
     pub fn median(&mut self) -> Option<f64> {
         self.sort();
-        median_on_sorted(&*self.data)
+        median_on_sorted(&self.data)
+    }
+
+    /// Returns the `q`th quantile of the data, where `q` is in `[0, 1]`.
+    ///
+    /// The quantile is computed by linear interpolation between the two
+    /// closest order statistics. So `quantile(0.0)` is the minimum,
+    /// `quantile(1.0)` is the maximum and `quantile(0.5)` is the median.
+    ///
+    /// `None` is returned if there is no data or if `q` is not in `[0, 1]`.
+    pub fn quantile(&mut self, q: f64) -> Option<f64> {
+        self.sort();
+        quantile_on_sorted(&self.data, q)
+    }
+
+    /// Returns the fraction of the data that is less than or equal to `x`.
+    ///
+    /// This is the empirical cumulative distribution function, computed with
+    /// a binary search over the sorted data. `0.0` is returned if there is no
+    /// data.
+    pub fn cdf(&mut self, x: f64) -> f64 {
+        if self.data.is_empty() {
+            return 0.0;
+        }
+        self.sort();
+        let rank = self.data.partition_point(|v| v.0.to_f64().unwrap() <= x);
+        rank as f64 / self.data.len() as f64
+    }
+
+    /// Returns the percentile rank of `x`, i.e., the percentage (from `0` to
+    /// `100`) of the data that is less than or equal to `x`.
+    ///
+    /// This is `cdf(x) * 100`. `0.0` is returned if there is no data.
+    pub fn percentile_rank(&mut self, x: f64) -> f64 {
+        self.cdf(x) * 100.0
+    }
+
+    /// Returns the interquartile range of the data, i.e., `Q3 - Q1`.
+    ///
+    /// The quartiles are computed with `quantile`. `None` is returned if
+    /// there is no data.
+    pub fn iqr(&mut self) -> Option<f64> {
+        let q1 = self.quantile(0.25)?;
+        let q3 = self.quantile(0.75)?;
+        Some(q3 - q1)
+    }
+
+    /// Returns the five number summary of the data as
+    /// `(min, Q1, median, Q3, max)`, which is what a box plot draws.
+    ///
+    /// The data is sorted once and each value is computed with the same
+    /// interpolation as `quantile`. `None` is returned if there is no data.
+    pub fn five_number_summary(
+        &mut self,
+    ) -> Option<(f64, f64, f64, f64, f64)> {
+        self.sort();
+        let q = |p| quantile_on_sorted(&self.data, p);
+        Some((q(0.0)?, q(0.25)?, q(0.5)?, q(0.75)?, q(1.0)?))
+    }
+
+    /// Returns the median absolute deviation of the data.
+    ///
+    /// This is the median of the absolute deviations of each value from the
+    /// median, which is a robust estimate of scale. The deviations are
+    /// computed in `f64`. `None` is returned if there is no data.
+    pub fn mad(&mut self) -> Option<f64> {
+        let median = self.median()?;
+        let mut devs: Vec<Partial<f64>> = self.data.iter()
+            .map(|x| Partial((x.to_f64().unwrap() - median).abs()))
+            .collect();
+        devs.sort();
+        median_on_sorted(&devs)
+    }
+
+    /// Returns the modified z-score of `x`, i.e.,
+    /// `0.6745 * (x - median) / MAD`.
+    ///
+    /// This is a robust alternative to `OnlineStats::zscore`, since the
+    /// median and the median absolute deviation aren't dragged around by
+    /// the outliers it is usually used to find. A common rule of thumb flags
+    /// values with an absolute score above `3.5`.
+    ///
+    /// `None` is returned if there is no data or if the MAD is `0`.
+    pub fn modified_zscore(&mut self, x: f64) -> Option<f64> {
+        let mad = self.mad()?;
+        if mad == 0.0 {
+            return None;
+        }
+        let median = self.median()?;
+        Some(0.6745 * (x - median) / mad)
+    }
+
+    /// Returns the mean of the data after discarding the lowest and highest
+    /// `proportion` of values.
+    ///
+    /// The number of values discarded from each end is `proportion * N`,
+    /// rounded down.
+    ///
+    /// `None` is returned if there is no data or if `proportion` is not in
+    /// `[0, 0.5)`.
+    pub fn trimmed_mean(&mut self, proportion: f64) -> Option<f64> {
+        if self.data.is_empty() || !(0.0..0.5).contains(&proportion) {
+            return None;
+        }
+        self.sort();
+        let n = self.data.len();
+        let k = ((n as f64) * proportion) as usize;
+        let kept = &self.data[k..n - k];
+        let sum: f64 = kept.iter().map(|x| x.to_f64().unwrap()).sum();
+        Some(sum / (kept.len() as f64))
+    }
+
+    /// Returns the mean of the data after clamping the lowest and highest
+    /// `proportion` of values to the nearest remaining values.
+    ///
+    /// Unlike `trimmed_mean`, the sample size is preserved. The number of
+    /// values clamped at each end is `proportion * N`, rounded down.
+    ///
+    /// `None` is returned if there is no data or if `proportion` is not in
+    /// `[0, 0.5)`.
+    pub fn winsorized_mean(&mut self, proportion: f64) -> Option<f64> {
+        if self.data.is_empty() || !(0.0..0.5).contains(&proportion) {
+            return None;
+        }
+        self.sort();
+        let n = self.data.len();
+        let k = ((n as f64) * proportion) as usize;
+        let lo = self.data[k].to_f64().unwrap();
+        let hi = self.data[n - 1 - k].to_f64().unwrap();
+        let inner: f64 = self.data[k..n - k].iter()
+            .map(|x| x.to_f64().unwrap())
+            .sum();
+        Some((inner + (k as f64) * (lo + hi)) / (n as f64))
+    }
+
+    /// Returns the median of the data in single precision.
+    ///
+    /// Unlike `median`, the values are converted to `f32` and the middle two
+    /// values of an even length sequence are averaged in `f32`. This avoids a
+    /// round trip through `f64` for `f32` data.
+    pub fn median_f32(&mut self) -> Option<f32> {
+        self.sort();
+        median_on_sorted_f32(&self.data)
+    }
+
+    /// Returns the `q`th quantile of the data in single precision, where `q`
+    /// is in `[0, 1]`.
+    ///
+    /// This is like `quantile`, except the values are converted to `f32`
+    /// and interpolated in `f32`.
+    ///
+    /// `None` is returned if there is no data or if `q` is not in `[0, 1]`.
+    pub fn quantile_f32(&mut self, q: f32) -> Option<f32> {
+        self.sort();
+        quantile_on_sorted_f32(&self.data, q)
+    }
+
+    /// Returns the range of the most populated bin when the data is split
+    /// into `nbins` bins of equal width.
+    ///
+    /// This is a useful stand in for the mode of continuous data, where
+    /// values rarely repeat exactly. The bins span the minimum to the maximum
+    /// of the data, and the last bin includes the maximum. When two bins are
+    /// tied, the lower one is returned.
+    ///
+    /// `None` is returned if there is no data or if `nbins` is `0`.
+    pub fn modal_bin(&mut self, nbins: usize) -> Option<(f64, f64)> {
+        if self.data.is_empty() || nbins == 0 {
+            return None;
+        }
+        self.sort();
+        let min = self.data[0].to_f64().unwrap();
+        let max = self.data[self.data.len() - 1].to_f64().unwrap();
+        let width = (max - min) / (nbins as f64);
+        if width == 0.0 {
+            return Some((min, max));
+        }
+        let mut counts = vec![0u64; nbins];
+        for x in &self.data {
+            let bin = ((x.to_f64().unwrap() - min) / width) as usize;
+            counts[bin.min(nbins - 1)] += 1;
+        }
+        let mut best = 0;
+        for (i, &count) in counts.iter().enumerate() {
+            if count > counts[best] {
+                best = i;
+            }
+        }
+        let lo = min + (best as f64) * width;
+        Some((lo, lo + width))
+    }
+
+    /// Returns an approximate mode of the data, treating values that are
+    /// within `tolerance` of each other as equal.
+    ///
+    /// The sorted data is split into clusters wherever two consecutive
+    /// values differ by more than `tolerance`, so a cluster may span more
+    /// than `tolerance` overall. A `tolerance` of `0` clusters exact
+    /// duplicates only. The mean of the cluster with the most
+    /// values is returned. When two clusters are tied, the lower one is
+    /// used. This is useful for noisy measurements, where values like `3.0`
+    /// and `3.0000001` should count as the same.
+    ///
+    /// `NaN` values are ignored. `None` is returned if there is no other
+    /// data.
+    pub fn mode_within(&mut self, tolerance: f64) -> Option<f64> {
+        self.sort();
+        let mut values = self.data.iter()
+            .map(|x| x.to_f64().unwrap())
+            .filter(|x| !x.is_nan());
+        let first = values.next()?;
+        let (mut best_sum, mut best_len) = (first, 1usize);
+        let (mut sum, mut len, mut prev) = (first, 1usize, first);
+        for x in values {
+            if x - prev <= tolerance {
+                sum += x;
+                len += 1;
+            } else {
+                sum = x;
+                len = 1;
+            }
+            if len > best_len {
+                best_sum = sum;
+                best_len = len;
+            }
+            prev = x;
+        }
+        Some(best_sum / (best_len as f64))
+    }
+}
+
+impl<T: PartialOrd + ToPrimitive + Clone> Unsorted<T> {
+    /// Returns the values outside of Tukey's fences, in ascending order.
+    ///
+    /// The fences are `[Q1 - k * IQR, Q3 + k * IQR]`, where `k` is usually
+    /// `1.5`. Values on a fence are not outliers. An empty `Vec` is returned
+    /// if there are no outliers or no data.
+    pub fn outliers(&mut self, k: f64) -> Vec<T> {
+        let (q1, q3) = match (self.quantile(0.25), self.quantile(0.75)) {
+            (Some(q1), Some(q3)) => (q1, q3),
+            _ => return vec![],
+        };
+        let (lo, hi) = (q1 - k * (q3 - q1), q3 + k * (q3 - q1));
+        self.data.iter()
+            .filter(|x| {
+                let x = x.to_f64().unwrap();
+                x < lo || x > hi
+            })
+            .map(|x| x.0.clone())
+            .collect()
     }
 }
 
 impl<T: PartialOrd> Commute for Unsorted<T> {
     fn merge(&mut self, v: Unsorted<T>) {
         self.dirtied();
-        self.data.extend(v.data.into_iter());
+        self.data.extend(v.data);
     }
 }
 
 impl<T: PartialOrd> Default for Unsorted<T> {
     fn default() -> Unsorted<T> {
-        Unsorted {
-            data: Vec::with_capacity(1000),
-            sorted: true,
-        }
+        Unsorted::with_capacity(0)
     }
 }
 
@@ -230,9 +611,181 @@ impl<T: PartialOrd> Extend<T> for Unsorted<T> {
     }
 }
 
+/// A commutative data structure for lazily sorted sequences of weighted data.
+///
+/// This is like `Unsorted`, except every value carries a weight. The sort,
+/// which is keyed on the values only, does not occur until statistics need
+/// to be computed.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WeightedUnsorted<T> {
+    data: Vec<(Partial<T>, f64)>,
+    sorted: bool,
+}
+
+impl<T: PartialOrd> WeightedUnsorted<T> {
+    /// Create initial empty state.
+    pub fn new() -> WeightedUnsorted<T> {
+        Default::default()
+    }
+
+    /// Create initial empty state with room for `capacity` data points
+    /// before reallocating.
+    pub fn with_capacity(capacity: usize) -> WeightedUnsorted<T> {
+        WeightedUnsorted {
+            data: Vec::with_capacity(capacity),
+            sorted: true,
+        }
+    }
+
+    /// Add a new element with the given weight to the set.
+    pub fn add<W: ToPrimitive>(&mut self, v: T, weight: W) {
+        self.sorted = false;
+        self.data.push((Partial(v), weight.to_f64().unwrap()));
+    }
+
+    /// Return the number of data points.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns true if there are no data points.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn sort(&mut self) {
+        if !self.sorted {
+            self.data.sort_by(|a, b| a.0.cmp(&b.0));
+            self.sorted = true;
+        }
+    }
+}
+
+impl<T: PartialOrd + ToPrimitive> WeightedUnsorted<T> {
+    /// Returns the weighted median of the data.
+    ///
+    /// This is the first value at which the cumulative weight reaches half
+    /// of the total weight. If the cumulative weight is exactly half of the
+    /// total, then the median is the average of that value and the next
+    /// value with a positive weight. With equal weights, this is the same as
+    /// the ordinary median.
+    ///
+    /// `None` is returned if the total weight is not positive.
+    pub fn median(&mut self) -> Option<f64> {
+        self.sort();
+        let total: f64 = self.data.iter().map(|&(_, w)| w).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let half = total / 2.0;
+        let mut cumulative = 0.0;
+        for (i, &(ref x, w)) in self.data.iter().enumerate() {
+            cumulative += w;
+            if cumulative < half {
+                continue;
+            }
+            let x = x.to_f64().unwrap();
+            if cumulative == half {
+                let next = self.data[i + 1..].iter().find(|&&(_, w)| w > 0.0);
+                if let Some((y, _)) = next {
+                    return Some((x + y.to_f64().unwrap()) / 2.0);
+                }
+            }
+            return Some(x);
+        }
+        None
+    }
+}
+
+impl<T: PartialOrd> Commute for WeightedUnsorted<T> {
+    fn merge(&mut self, v: WeightedUnsorted<T>) {
+        self.sorted = false;
+        self.data.extend(v.data);
+    }
+}
+
+impl<T: PartialOrd> Default for WeightedUnsorted<T> {
+    fn default() -> WeightedUnsorted<T> {
+        WeightedUnsorted { data: Vec::new(), sorted: true }
+    }
+}
+
+impl<T, W> FromIterator<(T, W)> for WeightedUnsorted<T>
+        where T: PartialOrd, W: ToPrimitive {
+    fn from_iter<I: IntoIterator<Item=(T, W)>>(it: I) -> WeightedUnsorted<T> {
+        let mut v = WeightedUnsorted::new();
+        v.extend(it);
+        v
+    }
+}
+
+impl<T: PartialOrd, W: ToPrimitive> Extend<(T, W)> for WeightedUnsorted<T> {
+    fn extend<I: IntoIterator<Item=(T, W)>>(&mut self, it: I) {
+        for (v, weight) in it {
+            self.add(v, weight);
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::{median, mode, modes};
+    #[cfg(feature = "serde")]
+    use serde_json;
+
+    use Commute;
+    use super::{Unsorted, WeightedUnsorted, median, mode, modes};
+
+    #[test]
+    fn with_capacity() {
+        let mut v = Unsorted::with_capacity(3);
+        v.extend(vec![3usize, 5, 7, 5]);
+        assert_eq!(v.len(), 4);
+        assert_eq!(v.median(), Some(5.0));
+        assert_eq!(v.mode(), Some(5));
+    }
+
+    #[test]
+    fn default_capacity() {
+        let mut v: Unsorted<usize> = Unsorted::new();
+        assert_eq!(v.capacity(), 0);
+        v.extend(vec![3, 5, 7, 5]);
+        assert!(v.capacity() >= 4);
+        assert_eq!(v.median(), Some(5.0));
+        assert_eq!(v.mode(), Some(5));
+        assert!(Unsorted::<usize>::with_capacity(50).capacity() >= 50);
+    }
+
+    #[test]
+    fn shrink_to_fit() {
+        let mut v: Unsorted<usize> = Unsorted::with_capacity(1000);
+        v.extend(vec![3, 5, 7, 5]);
+        assert_eq!(v.median(), Some(5.0));
+        v.shrink_to_fit();
+        assert!(v.capacity() < 1000);
+        assert_eq!(v.len(), 4);
+        assert_eq!(v.median(), Some(5.0));
+        v.add(1);
+        assert_eq!(v.median(), Some(5.0));
+        assert_eq!(v.quantile(0.0), Some(1.0));
+    }
+
+    #[test]
+    fn nan_sorts_last() {
+        let nan = f64::NAN;
+        let mut data: Unsorted<f64> =
+            vec![nan, 2.0, 1.0].into_iter().collect();
+        assert_eq!(data.median(), Some(2.0));
+        assert_eq!(data.quantile(0.0), Some(1.0));
+        assert_eq!(data.quantile(0.25), Some(1.5));
+        assert!(data.quantile(1.0).unwrap().is_nan());
+        assert_eq!(data.len(), 3);
+
+        data.extend(vec![nan, 0.0]);
+        assert_eq!(data.median(), Some(2.0));
+        assert!(data.mode().unwrap().is_nan());
+        assert_eq!(data.k_smallest(3), vec![0.0, 1.0, 2.0]);
+    }
 
     #[test]
     fn median_stream() {
@@ -247,6 +800,8 @@ mod test {
         assert_eq!(mode(vec![3usize, 3, 3, 4].into_iter()), Some(3));
         assert_eq!(mode(vec![4usize, 3, 3, 3].into_iter()), Some(3));
         assert_eq!(mode(vec![1usize, 1, 2, 3, 3].into_iter()), None);
+        assert_eq!(mode(vec![1usize, 1, 2, 2, 3, 3].into_iter()), None);
+        assert_eq!(mode(vec![1usize, 1, 2, 2, 3, 3, 3].into_iter()), Some(3));
     }
 
     #[test]
@@ -256,6 +811,265 @@ mod test {
         assert_eq!(median(vec![1.0f64, 2.5, 3.0].into_iter()), Some(2.5));
     }
 
+    #[test]
+    fn quantile() {
+        let mut v: Unsorted<usize> =
+            vec![7usize, 1, 5, 3].into_iter().collect();
+        assert_eq!(v.quantile(0.0), Some(1.0));
+        assert_eq!(v.quantile(1.0), Some(7.0));
+        assert_eq!(v.quantile(0.5), v.median());
+        assert_eq!(v.quantile(0.25), Some(2.5));
+        assert_eq!(v.quantile(0.9), Some(6.4));
+        assert_eq!(v.quantile(-0.1), None);
+        assert_eq!(v.quantile(1.1), None);
+        assert_eq!(Unsorted::<f64>::new().quantile(0.5), None);
+    }
+
+    #[test]
+    fn quantile_infinite() {
+        let (inf, ninf) = (f64::INFINITY, f64::NEG_INFINITY);
+        let mut v: Unsorted<f64> = vec![2.0, inf, ninf, 1.0, 3.0]
+            .into_iter().collect();
+        assert_eq!(v.quantile(0.0), Some(ninf));
+        assert_eq!(v.quantile(1.0), Some(inf));
+        assert_eq!(v.quantile(0.5), Some(2.0));
+        assert_eq!(v.quantile(0.125), Some(ninf));
+        assert_eq!(v.quantile(0.875), Some(inf));
+        assert_eq!(v.five_number_summary(),
+                   Some((ninf, 1.0, 2.0, 3.0, inf)));
+
+        let mut v: Unsorted<f64> = vec![1.0, 2.0, inf].into_iter().collect();
+        assert_eq!(v.quantile(1.0), Some(inf));
+        let mut v: Unsorted<f64> = vec![ninf, 2.0, 3.0].into_iter().collect();
+        assert_eq!(v.quantile(0.0), Some(ninf));
+    }
+
+    #[test]
+    fn k_extremes() {
+        let mut data: Unsorted<u32> =
+            vec![7, 3, 9, 1, 5, 8, 2, 6, 4].into_iter().collect();
+        assert_eq!(data.k_smallest(3), vec![1, 2, 3]);
+        assert_eq!(data.k_largest(3), vec![9, 8, 7]);
+        assert_eq!(data.k_smallest(0), Vec::<u32>::new());
+        assert_eq!(data.k_smallest(20), (1..10).collect::<Vec<_>>());
+        assert_eq!(data.k_largest(20), (1..10).rev().collect::<Vec<_>>());
+        data.add(0);
+        assert_eq!(data.k_smallest(2), vec![0, 1]);
+    }
+
+    #[test]
+    fn iter_sorted() {
+        let data = vec![7.5, -3.0, 9.0, 1.0, 7.5, f64::NAN, 2.0];
+        let mut unsorted: Unsorted<f64> = data.into_iter().collect();
+        let got: Vec<f64> = unsorted.iter_sorted().cloned().collect();
+        assert_eq!(&got[..6], &[-3.0, 1.0, 2.0, 7.5, 7.5, 9.0]);
+        assert!(got[6].is_nan());
+
+        unsorted.add(0.0);
+        let got: Vec<f64> = unsorted.iter_sorted().take(3).cloned().collect();
+        assert_eq!(got, vec![-3.0, 0.0, 1.0]);
+        assert_eq!(Unsorted::<u32>::new().iter_sorted().next(), None);
+    }
+
+    #[test]
+    fn mode_within() {
+        let mut data: Unsorted<f64> = vec![
+            1.0, 3.0000001, 2.5, 3.0, 2.9999998, 7.0, 7.0, f64::NAN, 3.0000002,
+        ].into_iter().collect();
+        let close = |x: Option<f64>, y: f64| (x.unwrap() - y).abs() < 1e-6;
+        assert!(close(data.mode_within(1e-3), 3.0));
+        // Without a tolerance, the exact duplicates win.
+        assert_eq!(data.mode_within(0.0), Some(7.0));
+
+        // Clusters chain through consecutive values.
+        let mut chain: Unsorted<f64> =
+            vec![0.0, 0.5, 1.0, 1.5, 10.0, 10.1].into_iter().collect();
+        assert!(close(chain.mode_within(0.6), 0.75));
+        assert!(close(chain.mode_within(0.2), 10.05));
+        // Tied clusters resolve to the lower one.
+        let mut tied: Unsorted<f64> =
+            vec![5.0, 5.05, 1.0, 1.05].into_iter().collect();
+        assert!(close(tied.mode_within(0.1), 1.025));
+
+        assert_eq!(Unsorted::<f64>::new().mode_within(1.0), None);
+    }
+
+    #[test]
+    fn cdf() {
+        let mut data: Unsorted<u32> = vec![4, 1, 3, 2].into_iter().collect();
+        assert_eq!(data.cdf(0.0), 0.0);
+        assert_eq!(data.cdf(1.0), 0.25);
+        assert_eq!(data.cdf(2.5), 0.5);
+        assert_eq!(data.cdf(3.0), 0.75);
+        assert_eq!(data.cdf(4.0), 1.0);
+        assert_eq!(data.cdf(100.0), 1.0);
+        assert_eq!(Unsorted::<u32>::new().cdf(1.0), 0.0);
+    }
+
+    #[test]
+    fn cdf_ties() {
+        let mut data: Unsorted<f64> =
+            vec![1.0, 2.0, 2.0, 2.0, 3.0].into_iter().collect();
+        assert_eq!(data.cdf(1.999), 0.2);
+        assert_eq!(data.cdf(2.0), 0.8);
+    }
+
+    #[test]
+    fn percentile_rank() {
+        let mut data: Unsorted<u32> = (1..101).rev().collect();
+        assert_eq!(data.percentile_rank(0.0), 0.0);
+        assert_eq!(data.percentile_rank(1000.0), 100.0);
+        for p in 1..100 {
+            let q = data.quantile(p as f64 / 100.0).unwrap();
+            let rank = data.percentile_rank(q);
+            assert!((rank - p as f64).abs() <= 1.0, "p: {}, rank: {}", p, rank);
+        }
+        assert_eq!(data.percentile_rank(25.0), 25.0);
+        assert_eq!(Unsorted::<u32>::new().percentile_rank(1.0), 0.0);
+    }
+
+    #[test]
+    fn iqr() {
+        // Q1 = 2.75 and Q3 = 6.25
+        let mut v: Unsorted<usize> = (1..9).collect();
+        assert_eq!(v.iqr(), Some(3.5));
+
+        let mut one: Unsorted<usize> = vec![4].into_iter().collect();
+        assert_eq!(one.iqr(), Some(0.0));
+        assert_eq!(Unsorted::<f64>::new().iqr(), None);
+    }
+
+    #[test]
+    fn five_number_summary() {
+        // Q1 is halfway between 15 and 36 and Q3 is halfway between 42
+        // and 43.
+        let mut v: Unsorted<u32> =
+            vec![39, 6, 42, 15, 41, 7, 43, 36, 49, 40, 47]
+            .into_iter().collect();
+        assert_eq!(v.five_number_summary(),
+                   Some((6.0, 25.5, 40.0, 42.5, 49.0)));
+
+        let mut v: Unsorted<f64> = (1..10).map(|x| x as f64).collect();
+        assert_eq!(v.five_number_summary(), Some((1.0, 3.0, 5.0, 7.0, 9.0)));
+
+        let mut one: Unsorted<u32> = vec![4].into_iter().collect();
+        assert_eq!(one.five_number_summary(), Some((4.0, 4.0, 4.0, 4.0, 4.0)));
+        assert_eq!(Unsorted::<f64>::new().five_number_summary(), None);
+    }
+
+    #[test]
+    fn outliers() {
+        let mut v: Unsorted<f64> = vec![
+            10.1, 9.8, 10.0, 10.3, 9.9, 10.2, 55.0, 10.0, 9.7, -20.0,
+        ].into_iter().collect();
+        assert_eq!(v.outliers(1.5), vec![-20.0, 55.0]);
+
+        let mut tight: Unsorted<u32> = (1..20).collect();
+        assert_eq!(tight.outliers(1.5), Vec::<u32>::new());
+        assert_eq!(Unsorted::<f64>::new().outliers(1.5), Vec::<f64>::new());
+    }
+
+    #[test]
+    fn mad() {
+        // median = 2, deviations = 1, 1, 0, 0, 2, 4, 7
+        let mut v: Unsorted<u32> =
+            vec![1u32, 1, 2, 2, 4, 6, 9].into_iter().collect();
+        assert_eq!(v.mad(), Some(1.0));
+
+        let mut v: Unsorted<f64> =
+            vec![2.5, -1.0, 4.0, 3.5].into_iter().collect();
+        assert_eq!(v.mad(), Some(0.75));
+        assert_eq!(Unsorted::<f64>::new().mad(), None);
+    }
+
+    #[test]
+    fn modified_zscore() {
+        let mut v: Unsorted<f64> = vec![
+            10.1, 9.8, 10.0, 10.3, 9.9, 10.2, 10.0, 9.7, 10.1, 250.0,
+        ].into_iter().collect();
+        let outlier = v.modified_zscore(250.0).unwrap();
+        assert!(outlier > 1000.0, "outlier: {}", outlier);
+        for x in [10.1, 9.8, 10.0, 10.3, 9.9, 10.2, 9.7] {
+            let z = v.modified_zscore(x).unwrap();
+            assert!(z.abs() < 3.5, "{}: {}", x, z);
+        }
+        // median = 2, MAD = 1
+        let mut v: Unsorted<u32> =
+            vec![1u32, 1, 2, 2, 4, 6, 9].into_iter().collect();
+        assert_eq!(v.modified_zscore(4.0), Some(2.0 * 0.6745));
+
+        let mut constant: Unsorted<f64> =
+            vec![3.0, 3.0, 3.0, 8.0].into_iter().collect();
+        assert_eq!(constant.modified_zscore(8.0), None);
+        assert_eq!(Unsorted::<f64>::new().modified_zscore(1.0), None);
+    }
+
+    #[test]
+    fn trimmed_mean() {
+        let mut v: Unsorted<f64> = vec![
+            -500.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 1000.0,
+        ].into_iter().collect();
+        assert_eq!(v.trimmed_mean(0.1), Some(4.5));
+        assert_eq!(v.trimmed_mean(0.0), Some(53.6));
+        assert_eq!(v.trimmed_mean(0.5), None);
+        assert_eq!(v.trimmed_mean(-0.1), None);
+        assert_eq!(Unsorted::<f64>::new().trimmed_mean(0.1), None);
+    }
+
+    #[test]
+    fn winsorized_mean() {
+        let mut v: Unsorted<f64> = vec![
+            -500.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 1000.0,
+        ].into_iter().collect();
+        // The tails are clamped to 1 and 8.
+        assert_eq!(v.winsorized_mean(0.1), Some(4.5));
+        assert_eq!(v.winsorized_mean(0.0), Some(53.6));
+        assert_eq!(v.winsorized_mean(0.5), None);
+        assert_eq!(Unsorted::<f64>::new().winsorized_mean(0.1), None);
+
+        let mut v: Unsorted<u32> =
+            vec![1u32, 2, 3, 4, 100].into_iter().collect();
+        // 2, 2, 3, 4, 4
+        assert_eq!(v.winsorized_mean(0.2), Some(3.0));
+        assert_eq!(v.trimmed_mean(0.2), Some(3.0));
+    }
+
+    #[test]
+    fn median_f32() {
+        let mut v: Unsorted<f32> =
+            vec![0.1f32, 0.7, 0.2, 0.3].into_iter().collect();
+        let got: f32 = v.median_f32().unwrap();
+        assert_eq!(got, (0.2f32 + 0.3f32) / 2.0);
+
+        let mut odd: Unsorted<f32> =
+            vec![0.1f32, 0.7, 0.2].into_iter().collect();
+        assert_eq!(odd.median_f32(), Some(0.2f32));
+
+        let mut empty: Unsorted<f32> = Unsorted::new();
+        assert_eq!(empty.median_f32(), None);
+    }
+
+    #[test]
+    fn quantile_f32() {
+        let mut v: Unsorted<f32> =
+            vec![0.1f32, 0.7, 0.2, 0.3].into_iter().collect();
+        // h = 0.3 * 3 = 0.9, between 0.1 and 0.2.
+        let h = 0.3f32 * 3.0;
+        let expected = 0.1f32 + (h - h.floor()) * (0.2f32 - 0.1f32);
+        assert_eq!(v.quantile_f32(0.3), Some(expected));
+        assert_eq!(v.quantile_f32(0.0), Some(0.1));
+        assert_eq!(v.quantile_f32(1.0), Some(0.7));
+        assert_eq!(v.quantile_f32(0.5), v.median_f32());
+        assert_eq!(v.quantile_f32(1.5), None);
+
+        let mut inf: Unsorted<f32> =
+            vec![1.0f32, f32::INFINITY].into_iter().collect();
+        assert_eq!(inf.quantile_f32(0.5), Some(f32::INFINITY));
+
+        let mut empty: Unsorted<f32> = Unsorted::new();
+        assert_eq!(empty.quantile_f32(0.5), None);
+    }
+
     #[test]
     fn mode_floats() {
         assert_eq!(mode(vec![3.0f64, 5.0, 7.0, 9.0].into_iter()), None);
@@ -267,20 +1081,116 @@ mod test {
 
     #[test]
     fn modes_stream() {
-        assert_eq!(modes(vec![3usize, 5, 7, 9].into_iter()), vec![]);
+        assert_eq!(modes(vec![3usize, 5, 7, 9].into_iter()),
+                   vec![3, 5, 7, 9]);
         assert_eq!(modes(vec![3usize, 3, 3, 3].into_iter()), vec![3]);
         assert_eq!(modes(vec![3usize, 3, 4, 4].into_iter()), vec![3, 4]);
         assert_eq!(modes(vec![4usize, 3, 3, 3].into_iter()), vec![3]);
         assert_eq!(modes(vec![1usize, 1, 2, 2].into_iter()), vec![1, 2]);
         let vec: Vec<u32> = vec![];
-        assert_eq!(modes(vec.into_iter()), vec![]);
+        assert_eq!(modes(vec.into_iter()), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn modes_unsorted_bimodal() {
+        let mut ints: Unsorted<i32> =
+            vec![5, 1, 9, 5, 2, 9, 3].into_iter().collect();
+        assert_eq!(ints.modes(), vec![5, 9]);
+        let mut floats: Unsorted<f64> =
+            vec![0.5, 2.5, 0.5, 1.5, 2.5].into_iter().collect();
+        assert_eq!(floats.modes(), vec![0.5, 2.5]);
+        assert!(Unsorted::<i32>::new().modes().is_empty());
+    }
+
+    #[test]
+    fn modes_unsorted_distinct() {
+        let mut ints: Unsorted<i32> = vec![7, 3, 5].into_iter().collect();
+        assert_eq!(ints.modes(), vec![3, 5, 7]);
+        let mut floats: Unsorted<f64> =
+            vec![2.5, -1.0, 0.5].into_iter().collect();
+        assert_eq!(floats.modes(), vec![-1.0, 0.5, 2.5]);
+        let mut one: Unsorted<i32> = vec![4].into_iter().collect();
+        assert_eq!(one.modes(), vec![4]);
+        assert_eq!(modes(vec![7, 3, 5].into_iter()), vec![3, 5, 7]);
+        assert_eq!(modes(vec![4].into_iter()), vec![4]);
     }
 
     #[test]
     fn modes_floats() {
-        assert_eq!(modes(vec![3_f64, 5.0, 7.0, 9.0].into_iter()), vec![]);
+        assert_eq!(modes(vec![3_f64, 5.0, 7.0, 9.0].into_iter()),
+                   vec![3.0, 5.0, 7.0, 9.0]);
         assert_eq!(modes(vec![3_f64, 3.0, 3.0, 3.0].into_iter()), vec![3.0]);
         assert_eq!(modes(vec![3_f64, 3.0, 4.0, 4.0].into_iter()), vec![3.0, 4.0]);
         assert_eq!(modes(vec![1_f64, 1.0, 2.0, 3.0, 3.0].into_iter()), vec![1.0, 3.0]);
     }
+
+    #[test]
+    fn modal_bin() {
+        let mut v: Unsorted<f64> = vec![
+            0.0, 1.3, 2.2, 4.1, 4.3, 4.4, 4.6, 4.7, 4.9, 5.2, 7.5, 10.0,
+        ].into_iter().collect();
+        assert_eq!(v.modal_bin(5), Some((4.0, 6.0)));
+        assert_eq!(v.modal_bin(0), None);
+
+        let mut empty: Unsorted<f64> = Unsorted::new();
+        assert_eq!(empty.modal_bin(5), None);
+
+        let mut same: Unsorted<f64> = vec![2.5, 2.5].into_iter().collect();
+        assert_eq!(same.modal_bin(5), Some((2.5, 2.5)));
+    }
+
+    #[test]
+    fn weighted_median_equal_weights() {
+        for data in &[vec![3usize, 9, 5, 7], vec![3usize, 9, 5], vec![4]] {
+            let mut weighted: WeightedUnsorted<usize> =
+                data.iter().map(|&x| (x, 2.0)).collect();
+            let expected = median(data.iter().cloned());
+            assert_eq!(weighted.median(), expected);
+        }
+        assert_eq!(WeightedUnsorted::<f64>::new().median(), None);
+    }
+
+    #[test]
+    fn weighted_with_capacity() {
+        let mut weighted = WeightedUnsorted::with_capacity(2);
+        weighted.extend(vec![(1usize, 1.0), (5, 3.0), (9, 1.0)]);
+        assert_eq!(weighted.len(), 3);
+        assert_eq!(weighted.median(), Some(5.0));
+    }
+
+    #[test]
+    fn weighted_median() {
+        let mut v: WeightedUnsorted<f64> = vec![
+            (1.0, 1.0), (2.0, 1.0), (3.0, 5.0), (100.0, 2.0),
+        ].into_iter().collect();
+        assert_eq!(v.median(), Some(3.0));
+
+        // Zero weights are skipped when averaging an exact half.
+        let mut v: WeightedUnsorted<f64> = vec![
+            (1.0, 1.0), (2.0, 0.0), (3.0, 1.0),
+        ].into_iter().collect();
+        assert_eq!(v.median(), Some(2.0));
+
+        let mut v1: WeightedUnsorted<u32> =
+            vec![(10u32, 3u32)].into_iter().collect();
+        v1.merge(vec![(1u32, 1u32), (2, 1)].into_iter().collect());
+        assert_eq!(v1.median(), Some(10.0));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_roundtrip() {
+        let mut data: Unsorted<f64> =
+            vec![3.5, 1.0, 2.25, 8.0, 2.25].into_iter().collect();
+        let json = serde_json::to_string(&data).unwrap();
+        let mut got: Unsorted<f64> = serde_json::from_str(&json).unwrap();
+        assert_eq!(got.len(), data.len());
+        assert_eq!(got.median(), data.median());
+        assert_eq!(got.mode(), data.mode());
+        assert_eq!(got.quantile(0.9), data.quantile(0.9));
+
+        got.merge(vec![0.5, 9.0].into_iter().collect());
+        assert_eq!(got.median(), Some(2.25));
+        assert_eq!(got.quantile(0.0), Some(0.5));
+    }
 }
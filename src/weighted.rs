@@ -0,0 +1,447 @@
+use std::default::Default;
+use std::fmt;
+use std::iter::{FromIterator, IntoIterator};
+
+use num_traits::ToPrimitive;
+
+use Commute;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A commutative count of weighted samples.
+///
+/// Instead of counting each sample as `1`, this accumulates the sum of the
+/// weights of each sample. With unit weights, the total weight is the same
+/// as an ordinary count. This is the count that weighted statistics are
+/// normalized by.
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WeightedCount {
+    len: u64,
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    total: f64,
+}
+
+impl WeightedCount {
+    /// Create initial state with a total weight of `0`.
+    pub fn new() -> WeightedCount {
+        Default::default()
+    }
+
+    /// Add a sample with the given weight.
+    pub fn add<W: ToPrimitive>(&mut self, weight: W) {
+        self.len += 1;
+        self.total += weight.to_f64().unwrap();
+    }
+
+    /// Returns the sum of the weights of all samples.
+    pub fn total_weight(&self) -> f64 {
+        self.total
+    }
+
+    /// Returns the number of samples, ignoring their weights.
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Returns true if there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Commute for WeightedCount {
+    fn merge(&mut self, v: WeightedCount) {
+        self.len += v.len;
+        self.total += v.total;
+    }
+}
+
+impl Default for WeightedCount {
+    fn default() -> WeightedCount {
+        WeightedCount { len: 0, total: 0.0 }
+    }
+}
+
+impl fmt::Debug for WeightedCount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} ({} samples)", self.total, self.len)
+    }
+}
+
+impl<W: ToPrimitive> FromIterator<W> for WeightedCount {
+    fn from_iter<I: IntoIterator<Item=W>>(it: I) -> WeightedCount {
+        let mut v = WeightedCount::new();
+        v.extend(it);
+        v
+    }
+}
+
+impl<W: ToPrimitive> Extend<W> for WeightedCount {
+    fn extend<I: IntoIterator<Item=W>>(&mut self, it: I) {
+        for weight in it {
+            self.add(weight);
+        }
+    }
+}
+
+/// Online state for computing a weighted mean.
+///
+/// This tracks the sum of `weight * value` alongside the total weight, so the
+/// weighted mean is available in constant space.
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WeightedMean {
+    count: WeightedCount,
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    sum: f64,
+}
+
+impl WeightedMean {
+    /// Create initial state with no samples.
+    pub fn new() -> WeightedMean {
+        Default::default()
+    }
+
+    /// Add a sample with the given weight.
+    pub fn add<T: ToPrimitive, W: ToPrimitive>(&mut self, value: T, weight: W) {
+        let w = weight.to_f64().unwrap();
+        self.count.add(w);
+        self.sum += w * value.to_f64().unwrap();
+    }
+
+    /// Returns the weighted mean.
+    ///
+    /// If the total weight is `0` (including when there are no samples),
+    /// then `NaN` is returned.
+    pub fn mean(&self) -> f64 {
+        let total = self.count.total_weight();
+        if total == 0.0 {
+            return f64::NAN;
+        }
+        self.sum / total
+    }
+
+    /// Returns the sum of the weights of all samples.
+    pub fn total_weight(&self) -> f64 {
+        self.count.total_weight()
+    }
+
+    /// Returns the number of samples, ignoring their weights.
+    pub fn len(&self) -> usize {
+        self.count.len()
+    }
+
+    /// Returns true if there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.count.is_empty()
+    }
+}
+
+impl Commute for WeightedMean {
+    fn merge(&mut self, v: WeightedMean) {
+        self.count.merge(v.count);
+        self.sum += v.sum;
+    }
+}
+
+impl fmt::Debug for WeightedMean {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.10}", self.mean())
+    }
+}
+
+impl<T: ToPrimitive, W: ToPrimitive> FromIterator<(T, W)> for WeightedMean {
+    fn from_iter<I: IntoIterator<Item=(T, W)>>(it: I) -> WeightedMean {
+        let mut v = WeightedMean::new();
+        v.extend(it);
+        v
+    }
+}
+
+impl<T: ToPrimitive, W: ToPrimitive> Extend<(T, W)> for WeightedMean {
+    fn extend<I: IntoIterator<Item=(T, W)>>(&mut self, it: I) {
+        for (value, weight) in it {
+            self.add(value, weight);
+        }
+    }
+}
+
+/// Online state for computing a weighted mean, variance and standard
+/// deviation.
+///
+/// This uses West's weighted generalization of Welford's method, so it is
+/// numerically stable and uses constant space.
+///
+/// How the variance should be normalized depends on what the weights mean:
+///
+/// * `variance` treats the weights as normalized (or population) weights and
+///   divides the weighted sum of squares by the total weight.
+/// * `frequency_variance` treats the weights as frequency weights, i.e., a
+///   weight of `3` means the value was observed three times. This divides
+///   by the total weight minus `1`, so it matches the sample variance of the
+///   expanded data.
+/// * `reliability_variance` treats the weights as reliability weights, i.e.,
+///   each weight describes the importance of a single observation. This is
+///   the unbiased estimator `S / (W - W2 / W)`, where `W2` is the sum of the
+///   squared weights.
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WeightedVariance {
+    count: WeightedCount,
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    weight_sq: f64,
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    mean: f64,
+    /// The weighted sum of squared differences from the mean.
+    #[cfg_attr(feature = "serde", serde(with = "::serde_helpers::float"))]
+    sum_sq: f64,
+}
+
+impl WeightedVariance {
+    /// Create initial state with no samples.
+    pub fn new() -> WeightedVariance {
+        Default::default()
+    }
+
+    /// Add a sample with the given weight.
+    pub fn add<T: ToPrimitive, W: ToPrimitive>(&mut self, value: T, weight: W) {
+        let (x, w) = (value.to_f64().unwrap(), weight.to_f64().unwrap());
+        self.count.add(w);
+        self.weight_sq += w * w;
+        let total = self.count.total_weight();
+        if total == 0.0 {
+            return;
+        }
+        // See: West, "Updating Mean and Variance Estimates: An Improved
+        // Method", 1979.
+        let oldmean = self.mean;
+        self.mean += (w / total) * (x - oldmean);
+        self.sum_sq += w * (x - oldmean) * (x - self.mean);
+    }
+
+    /// Returns the weighted mean.
+    ///
+    /// If the total weight is `0`, then `NaN` is returned.
+    pub fn mean(&self) -> f64 {
+        if self.count.total_weight() == 0.0 {
+            return f64::NAN;
+        }
+        self.mean
+    }
+
+    /// Returns the weighted variance, treating the weights as normalized
+    /// weights.
+    ///
+    /// If the total weight is `0`, then `NaN` is returned.
+    pub fn variance(&self) -> f64 {
+        self.sum_sq / self.count.total_weight()
+    }
+
+    /// Returns the weighted standard deviation, treating the weights as
+    /// normalized weights.
+    ///
+    /// If the total weight is `0`, then `NaN` is returned.
+    pub fn stddev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    /// Returns the unbiased weighted variance, treating the weights as
+    /// frequency weights.
+    ///
+    /// If the total weight is not greater than `1`, then `NaN` is returned.
+    pub fn frequency_variance(&self) -> f64 {
+        let total = self.count.total_weight();
+        if total <= 1.0 {
+            return f64::NAN;
+        }
+        self.sum_sq / (total - 1.0)
+    }
+
+    /// Returns the unbiased weighted variance, treating the weights as
+    /// reliability weights.
+    ///
+    /// If all of the weight is on a single sample (or there is no weight),
+    /// then `NaN` is returned.
+    pub fn reliability_variance(&self) -> f64 {
+        let total = self.count.total_weight();
+        if total == 0.0 {
+            return f64::NAN;
+        }
+        let denom = total - self.weight_sq / total;
+        if denom <= 0.0 {
+            return f64::NAN;
+        }
+        self.sum_sq / denom
+    }
+
+    /// Returns the sum of the weights of all samples.
+    pub fn total_weight(&self) -> f64 {
+        self.count.total_weight()
+    }
+
+    /// Returns the number of samples, ignoring their weights.
+    pub fn len(&self) -> usize {
+        self.count.len()
+    }
+
+    /// Returns true if there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.count.is_empty()
+    }
+}
+
+impl Commute for WeightedVariance {
+    fn merge(&mut self, v: WeightedVariance) {
+        let (w1, w2) = (self.count.total_weight(), v.count.total_weight());
+        self.count.merge(v.count);
+        self.weight_sq += v.weight_sq;
+        if w2 == 0.0 {
+            return;
+        } else if w1 == 0.0 {
+            self.mean = v.mean;
+            self.sum_sq = v.sum_sq;
+            return;
+        }
+        let total = w1 + w2;
+        let delta = v.mean - self.mean;
+        self.mean += delta * w2 / total;
+        self.sum_sq += v.sum_sq + delta * delta * w1 * w2 / total;
+    }
+}
+
+impl Default for WeightedVariance {
+    fn default() -> WeightedVariance {
+        WeightedVariance {
+            count: WeightedCount::new(),
+            weight_sq: 0.0,
+            mean: 0.0,
+            sum_sq: 0.0,
+        }
+    }
+}
+
+impl fmt::Debug for WeightedVariance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.10} +/- {:.10} (normalized weights)",
+               self.mean(), self.stddev())
+    }
+}
+
+impl<T, W> FromIterator<(T, W)> for WeightedVariance
+        where T: ToPrimitive, W: ToPrimitive {
+    fn from_iter<I: IntoIterator<Item=(T, W)>>(it: I) -> WeightedVariance {
+        let mut v = WeightedVariance::new();
+        v.extend(it);
+        v
+    }
+}
+
+impl<T: ToPrimitive, W: ToPrimitive> Extend<(T, W)> for WeightedVariance {
+    fn extend<I: IntoIterator<Item=(T, W)>>(&mut self, it: I) {
+        for (value, weight) in it {
+            self.add(value, weight);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {Commute, OnlineStats, merge_all};
+    use super::{WeightedCount, WeightedMean, WeightedVariance};
+
+    #[test]
+    fn unit_weights() {
+        let count: WeightedCount = vec![1u32; 7].into_iter().collect();
+        assert_eq!(count.len(), 7);
+        assert_eq!(count.total_weight(), 7.0);
+    }
+
+    #[test]
+    fn merge() {
+        let mut c1: WeightedCount = vec![0.5, 1.5].into_iter().collect();
+        let c2: WeightedCount = vec![2.0, 0.25, 0.25].into_iter().collect();
+        c1.merge(c2);
+        assert_eq!(c1.len(), 5);
+        assert_eq!(c1.total_weight(), 4.5);
+    }
+
+    #[test]
+    fn weighted_mean() {
+        let wm: WeightedMean =
+            vec![(1.0, 1.0), (2.0, 2.0), (4.0, 1.0)].into_iter().collect();
+        assert_eq!(wm.mean(), 2.25);
+        assert_eq!(wm.total_weight(), 4.0);
+
+        let unit: WeightedMean =
+            vec![(3u32, 1u32), (5, 1), (10, 1)].into_iter().collect();
+        assert_eq!(unit.mean(), 6.0);
+
+        assert!(WeightedMean::new().mean().is_nan());
+        let zero: WeightedMean = vec![(5.0, 0.0)].into_iter().collect();
+        assert!(zero.mean().is_nan());
+    }
+
+    #[test]
+    fn weighted_mean_merge() {
+        let all = [(1.0, 0.5), (7.0, 2.0), (3.0, 1.5), (-2.0, 1.0)];
+        let expected: WeightedMean = all.iter().cloned().collect();
+        let mut got: WeightedMean = all[..1].iter().cloned().collect();
+        got.merge(all[1..].iter().cloned().collect());
+        assert!((got.mean() - expected.mean()).abs() < 1e-12);
+        assert_eq!(got.len(), 4);
+    }
+
+    fn close(x: f64, y: f64) -> bool {
+        (x - y).abs() < 1e-9
+    }
+
+    fn frequency_data() -> Vec<(f64, u32)> {
+        vec![(1.5, 3), (4.0, 1), (-2.0, 2), (10.0, 5), (3.25, 1)]
+    }
+
+    #[test]
+    fn frequency_weights() {
+        let data = frequency_data();
+        let expanded: OnlineStats = data.iter()
+            .flat_map(|&(x, w)| vec![x; w as usize])
+            .collect();
+        let wv: WeightedVariance = data.into_iter().collect();
+        assert!(close(wv.mean(), expanded.mean()));
+        assert!(close(wv.variance(), expanded.variance()));
+        assert!(close(wv.stddev(), expanded.stddev()));
+        assert!(close(wv.frequency_variance(), expanded.sample_variance()));
+        assert_eq!(wv.total_weight(), 12.0);
+    }
+
+    #[test]
+    fn reliability_weights() {
+        // With equal weights, the reliability variance is the ordinary
+        // sample variance no matter the scale of the weights.
+        let wv: WeightedVariance =
+            vec![(2.0, 0.5), (4.0, 0.5), (9.0, 0.5)].into_iter().collect();
+        let stats = OnlineStats::from_slice(&[2.0, 4.0, 9.0]);
+        assert!(close(wv.reliability_variance(), stats.sample_variance()));
+
+        let one: WeightedVariance = vec![(2.0, 3.0)].into_iter().collect();
+        assert!(one.reliability_variance().is_nan());
+        assert!(WeightedVariance::new().variance().is_nan());
+    }
+
+    #[test]
+    fn weighted_variance_merge() {
+        let data = frequency_data();
+        let expected: WeightedVariance = data.iter().cloned().collect();
+        let parts = data.chunks(2).map(|c| c.iter().cloned().collect());
+        let got: WeightedVariance = merge_all(parts).unwrap();
+        assert!(close(got.mean(), expected.mean()));
+        assert!(close(got.variance(), expected.variance()));
+        assert!(close(got.reliability_variance(),
+                      expected.reliability_variance()));
+
+        let mut got = expected;
+        got.merge(WeightedVariance::new());
+        assert!(close(got.variance(), expected.variance()));
+    }
+}